@@ -0,0 +1,273 @@
+//! age
+//!
+//! Recipients, identities and stanzas of `age-plugin-kybe`, an age plugin wrapping the
+//! file key to the X25519Kyber768 hybrid KEM.
+//!
+//! A `kybe` stanza is `-> kybe <ciphertext>` with the unpadded base64 hybrid ciphertext as
+//! argument and the wrapped file key as body: as for the native X25519 recipients, the file
+//! key is encrypted with ChaCha20-Poly1305 under a zero nonce and the key
+//! `HKDF-SHA256(salt = ciphertext || recipient, ikm = shared secret, info = LABEL)`.
+//!
+//! Recipients `age1kybe1...` are the Bech32 hybrid public keys and identities
+//! `AGE-PLUGIN-KYBE-1...` the Bech32 96-byte key generation seeds. The Kyber side is the
+//! round 2 KEM of this crate, not ML-KEM.
+
+pub mod plugin;
+pub mod stanza;
+
+pub use stanza::Stanza;
+
+use crate::formats::bech32;
+use crate::hybrid::{x25519_kyber768, X25519Kyber768};
+use crate::structures::ByteArray;
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Human-readable part of the recipients
+pub const RECIPIENT_HRP: &str = "age1kybe";
+
+/// Human-readable part of the identities
+pub const IDENTITY_HRP: &str = "AGE-PLUGIN-KYBE-";
+
+/// Stanza type
+pub const STANZA_TYPE: &str = "kybe";
+
+/// Label of the stanzas, age does not mix them with non post-quantum recipients
+pub const LABEL: &str = "postquantum";
+
+/// Size of an age file key
+pub const FILE_KEY_BYTES: usize = 16;
+
+/// HKDF info of the wrapping key
+const WRAP_INFO: &[u8] = b"age-encryption.org/v1/kybe";
+
+/// Size of a wrapped file key
+const WRAPPED_BYTES: usize = FILE_KEY_BYTES + 16;
+
+/// ChaCha20-Poly1305 key wrapping the file key
+fn wrap_key(ss: &ByteArray, ct: &ByteArray, pk: &ByteArray) -> ChaCha20Poly1305 {
+    let salt = ct.append(pk);
+    let mut key = [0; 32];
+    // The output length is valid for HKDF-SHA256
+    Hkdf::<Sha256>::new(Some(&salt.data), &ss.data)
+        .expand(WRAP_INFO, &mut key)
+        .unwrap();
+    ChaCha20Poly1305::new(&key.into())
+}
+
+/// Recipient: hybrid public key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recipient {
+    pk: ByteArray,
+}
+
+impl Recipient {
+    /// Recipient of a hybrid public key
+    pub fn from_public_key(pk: &ByteArray) -> Result<Self, String> {
+        if pk.data.len() != X25519Kyber768::PK_BYTES {
+            return Err(format!(
+                "public key must be {} bytes",
+                X25519Kyber768::PK_BYTES
+            ));
+        }
+        Ok(Self { pk: pk.clone() })
+    }
+
+    /// Hybrid public key
+    pub fn public_key(&self) -> &ByteArray {
+        &self.pk
+    }
+
+    /// Wrap a file key => `kybe` stanza
+    pub fn wrap(&self, file_key: &[u8]) -> Result<Stanza, String> {
+        if file_key.len() != FILE_KEY_BYTES {
+            return Err(format!("file key must be {} bytes", FILE_KEY_BYTES));
+        }
+        let (ct, ss) = x25519_kyber768().encaps(&self.pk)?;
+        let body = wrap_key(&ss, &ct, &self.pk)
+            .encrypt(&[0; 12].into(), file_key)
+            .map_err(|_| "file key encryption failed".to_string())?;
+
+        Ok(Stanza::new(
+            STANZA_TYPE,
+            &[&stanza::base64_encode(&ct.data)],
+            &body,
+        ))
+    }
+}
+
+impl fmt::Display for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The human-readable part is valid
+        f.write_str(&bech32::encode(RECIPIENT_HRP, &self.pk.data).unwrap())
+    }
+}
+
+impl FromStr for Recipient {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match bech32::decode(s)? {
+            (hrp, data) if hrp == RECIPIENT_HRP => {
+                Self::from_public_key(&ByteArray::from_bytes(&data))
+            }
+            _ => Err("not a kybe recipient".to_string()),
+        }
+    }
+}
+
+/// Identity: key generation seed and the derived key pair
+#[derive(Clone)]
+pub struct Identity {
+    seed: ByteArray,
+    sk: ByteArray,
+    pk: ByteArray,
+}
+
+impl Identity {
+    /// Random identity
+    pub fn generate() -> Self {
+        // A random seed has the right size
+        Self::from_seed(&ByteArray::random(X25519Kyber768::SEED_BYTES)).unwrap()
+    }
+
+    /// Identity of a `SEED_BYTES` seed
+    pub fn from_seed(seed: &ByteArray) -> Result<Self, String> {
+        let (sk, pk) = x25519_kyber768().keygen_derand(seed)?;
+        Ok(Self {
+            seed: seed.clone(),
+            sk,
+            pk,
+        })
+    }
+
+    /// Recipient of the identity
+    pub fn recipient(&self) -> Recipient {
+        Recipient {
+            pk: self.pk.clone(),
+        }
+    }
+
+    /// Unwrap the file key of a stanza
+    ///
+    /// Returns `None` for stanzas of another type or to another recipient, and an error for
+    /// malformed `kybe` stanzas.
+    pub fn unwrap(&self, stanza: &Stanza) -> Result<Option<Vec<u8>>, String> {
+        if stanza.tag != STANZA_TYPE {
+            return Ok(None);
+        }
+        let ct = match &stanza.args[..] {
+            [ct] => ByteArray::from_bytes(&stanza::base64_decode(ct)?),
+            _ => return Err("kybe stanza must have one argument".to_string()),
+        };
+        if ct.data.len() != X25519Kyber768::CT_BYTES {
+            return Err("invalid kybe stanza ciphertext".to_string());
+        }
+        if stanza.body.len() != WRAPPED_BYTES {
+            return Err("invalid kybe stanza body".to_string());
+        }
+
+        let ss = x25519_kyber768().decaps(&ct, &self.sk)?;
+        // Decryption fails when the stanza is for another recipient
+        Ok(wrap_key(&ss, &ct, &self.pk)
+            .decrypt(&[0; 12].into(), &stanza.body[..])
+            .ok())
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The human-readable part is valid
+        let s = bech32::encode(IDENTITY_HRP, &self.seed.data).unwrap();
+        f.write_str(&s.to_ascii_uppercase())
+    }
+}
+
+impl FromStr for Identity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match bech32::decode(s)? {
+            (hrp, data) if hrp.eq_ignore_ascii_case(IDENTITY_HRP) => {
+                Self::from_seed(&ByteArray::from_bytes(&data))
+            }
+            _ => Err("not a kybe identity".to_string()),
+        }
+    }
+}
+
+#[test]
+fn age_keys() {
+    let identity = Identity::generate();
+    let recipient = identity.recipient();
+
+    let s = identity.to_string();
+    assert!(s.starts_with("AGE-PLUGIN-KYBE-1"));
+    assert_eq!(s.parse::<Identity>().unwrap().recipient(), recipient);
+    let s = recipient.to_string();
+    assert!(s.starts_with("age1kybe1"));
+    assert_eq!(s.parse::<Recipient>().unwrap(), recipient);
+
+    // Wrong human-readable parts and sizes
+    let seed = bech32::encode(IDENTITY_HRP, &[0; 96]).unwrap();
+    assert!(seed.parse::<Recipient>().is_err());
+    assert!(seed.to_ascii_uppercase().parse::<Identity>().is_ok());
+    assert!(recipient.to_string().parse::<Identity>().is_err());
+    assert!(bech32::encode(RECIPIENT_HRP, &[0; 32])
+        .unwrap()
+        .parse::<Recipient>()
+        .is_err());
+    assert!(bech32::encode(IDENTITY_HRP, &[0; 64])
+        .unwrap()
+        .parse::<Identity>()
+        .is_err());
+}
+
+#[test]
+fn age_wrap() {
+    let identity = Identity::generate();
+    let other = Identity::generate();
+    let file_key = [7; FILE_KEY_BYTES];
+
+    let stanza = identity.recipient().wrap(&file_key).unwrap();
+    assert_eq!(stanza.tag, STANZA_TYPE);
+    assert_eq!(identity.unwrap(&stanza).unwrap().unwrap(), file_key);
+    assert_eq!(other.unwrap(&stanza).unwrap(), None);
+    assert!(identity.recipient().wrap(&[7; 32]).is_err());
+
+    // Other stanza types are skipped, malformed kybe stanzas rejected
+    let x25519 = Stanza::new("X25519", &["AAAA"], &[0; 32]);
+    assert_eq!(identity.unwrap(&x25519).unwrap(), None);
+    let mut altered = stanza.clone();
+    altered.args.push("extra".to_string());
+    assert!(identity.unwrap(&altered).is_err());
+    let mut altered = stanza.clone();
+    altered.body.pop();
+    assert!(identity.unwrap(&altered).is_err());
+    let mut altered = stanza;
+    altered.args[0].truncate(100);
+    assert!(identity.unwrap(&altered).is_err());
+}
+
+#[test]
+fn age_test_vector() {
+    // Regression vector: identity of the seed 00 01 .. 5f
+    let seed: Vec<u8> = (0..96).collect();
+    let identity = Identity::from_seed(&ByteArray::from_bytes(&seed)).unwrap();
+    assert_eq!(
+        identity.to_string(),
+        bech32::encode(IDENTITY_HRP, &seed)
+            .unwrap()
+            .to_ascii_uppercase()
+    );
+    // The checksum covers the whole public key
+    let recipient = identity.recipient().to_string();
+    assert_eq!(recipient.len(), 9 + 1946 + 6);
+    assert!(recipient.ends_with("ajy6lpd9rgtykevh"));
+}
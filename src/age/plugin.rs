@@ -0,0 +1,350 @@
+//! Plugin protocol
+//!
+//! State machines of the `recipient-v1` and `identity-v1` age plugin protocols over any
+//! reader and writer (stdin and stdout for a plugin binary). In phase 1 the plugin reads
+//! the client commands until `done`, ignoring unknown ones; in phase 2 it sends its
+//! results, each acknowledged by the client, and ends with `done`.
+
+use super::{Identity, Recipient, Stanza, FILE_KEY_BYTES, LABEL, STANZA_TYPE};
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+/// Send a phase 2 command and wait for its acknowledgement
+fn send<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    command: &Stanza,
+) -> Result<(), String> {
+    command
+        .write(output)
+        .and_then(|_| output.flush())
+        .map_err(|e| e.to_string())?;
+
+    let response = Stanza::read(input)?;
+    match response.tag.as_str() {
+        "ok" => Ok(()),
+        "fail" => Err(format!("client failed the {} command", command.tag)),
+        other => Err(format!("unexpected client response {:?}", other)),
+    }
+}
+
+/// Send errors and end phase 2
+fn send_errors<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    errors: &[Stanza],
+) -> Result<(), String> {
+    for error in errors {
+        send(input, output, error)?;
+    }
+    done(output)
+}
+
+/// End phase 2
+fn done<W: Write>(output: &mut W) -> Result<(), String> {
+    Stanza::new("done", &[], &[])
+        .write(output)
+        .and_then(|_| output.flush())
+        .map_err(|e| e.to_string())
+}
+
+/// Error command
+fn error(kind: &str, index: &[usize], message: &str) -> Stanza {
+    let index: Vec<String> = index.iter().map(usize::to_string).collect();
+    let mut args = vec![kind];
+    args.extend(index.iter().map(String::as_str));
+    Stanza::new("error", &args, message.as_bytes())
+}
+
+/// Run the `recipient-v1` protocol: wrap the file keys to the recipients and identities
+pub fn recipient_v1<R: BufRead, W: Write>(input: &mut R, output: &mut W) -> Result<(), String> {
+    let mut recipients = Vec::new();
+    let mut errors = Vec::new();
+    let mut file_keys = Vec::new();
+    let mut labels = false;
+    let mut recipient_count = 0;
+    let mut identity_count = 0;
+
+    loop {
+        let command = Stanza::read(input)?;
+        match (command.tag.as_str(), &command.args[..]) {
+            ("add-recipient", [recipient]) => {
+                match recipient.parse::<Recipient>() {
+                    Ok(recipient) => recipients.push(recipient),
+                    Err(e) => errors.push(error("recipient", &[recipient_count], &e)),
+                }
+                recipient_count += 1;
+            }
+            ("add-identity", [identity]) => {
+                match identity.parse::<Identity>() {
+                    Ok(identity) => recipients.push(identity.recipient()),
+                    Err(e) => errors.push(error("identity", &[identity_count], &e)),
+                }
+                identity_count += 1;
+            }
+            ("wrap-file-key", []) => {
+                if command.body.len() == FILE_KEY_BYTES {
+                    file_keys.push(command.body);
+                } else {
+                    errors.push(error("internal", &[], "invalid file key"));
+                }
+            }
+            ("extension-labels", []) => labels = true,
+            ("done", []) => break,
+            // Unknown commands, including grease, are ignored
+            _ => {}
+        }
+    }
+
+    if !errors.is_empty() {
+        return send_errors(input, output, &errors);
+    }
+    if labels {
+        send(input, output, &Stanza::new("labels", &[LABEL], &[]))?;
+    }
+    for (index, file_key) in file_keys.iter().enumerate() {
+        for recipient in recipients.iter() {
+            let stanza = match recipient.wrap(file_key) {
+                Ok(stanza) => stanza,
+                Err(e) => return send_errors(input, output, &[error("internal", &[], &e)]),
+            };
+            let index = index.to_string();
+            let mut args = vec![index.as_str(), stanza.tag.as_str()];
+            args.extend(stanza.args.iter().map(String::as_str));
+            send(
+                input,
+                output,
+                &Stanza::new("recipient-stanza", &args, &stanza.body),
+            )?;
+        }
+    }
+
+    done(output)
+}
+
+/// Run the `identity-v1` protocol: unwrap the file keys of the stanzas with the identities
+pub fn identity_v1<R: BufRead, W: Write>(input: &mut R, output: &mut W) -> Result<(), String> {
+    let mut identities = Vec::new();
+    let mut errors = Vec::new();
+    let mut files: BTreeMap<usize, Vec<Stanza>> = BTreeMap::new();
+    let mut count = 0;
+
+    loop {
+        let command = Stanza::read(input)?;
+        match (command.tag.as_str(), &command.args[..]) {
+            ("add-identity", [identity]) => {
+                match identity.parse::<Identity>() {
+                    Ok(identity) => identities.push(identity),
+                    Err(e) => errors.push(error("identity", &[count], &e)),
+                }
+                count += 1;
+            }
+            ("recipient-stanza", [index, tag, args @ ..]) => {
+                let index: usize = index.parse().map_err(|_| "invalid file index")?;
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                files
+                    .entry(index)
+                    .or_default()
+                    .push(Stanza::new(tag, &args, &command.body));
+            }
+            ("done", []) => break,
+            // Unknown commands, including grease, are ignored
+            _ => {}
+        }
+    }
+
+    if !errors.is_empty() {
+        return send_errors(input, output, &errors);
+    }
+    for (index, stanzas) in files.iter() {
+        'file: for (position, stanza) in stanzas.iter().enumerate() {
+            if stanza.tag != STANZA_TYPE {
+                continue;
+            }
+            for identity in identities.iter() {
+                match identity.unwrap(stanza) {
+                    Ok(Some(file_key)) => {
+                        let file_index = index.to_string();
+                        send(
+                            input,
+                            output,
+                            &Stanza::new("file-key", &[&file_index], &file_key),
+                        )?;
+                        break 'file;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        send(input, output, &error("stanza", &[*index, position], &e))?;
+                        break 'file;
+                    }
+                }
+            }
+        }
+    }
+
+    done(output)
+}
+
+/// Client side of a scripted transcript: the commands followed by one `ok` per expected
+/// response
+#[cfg(test)]
+fn transcript(commands: &[Stanza], responses: usize) -> Vec<u8> {
+    let mut input: Vec<u8> = commands.iter().flat_map(Stanza::to_bytes).collect();
+    for _ in 0..responses {
+        input.extend(Stanza::new("ok", &[], &[]).to_bytes());
+    }
+    input
+}
+
+/// Plugin side of a transcript
+#[cfg(test)]
+fn responses(output: &[u8]) -> Vec<Stanza> {
+    let mut reader = output;
+    let mut stanzas = Vec::new();
+    while !reader.is_empty() {
+        stanzas.push(Stanza::read(&mut reader).unwrap());
+    }
+    stanzas
+}
+
+#[test]
+fn age_plugin_transcript() {
+    let identity = Identity::generate();
+    let recipient = identity.recipient().to_string();
+    let other = Identity::generate();
+    let file_keys = [[1; FILE_KEY_BYTES], [2; FILE_KEY_BYTES]];
+
+    // Encryption of two files to two recipients, one of them given as an identity
+    let input = transcript(
+        &[
+            Stanza::new("add-recipient", &[&recipient], &[]),
+            Stanza::new("grease-x!", &["y"], b"ignored"),
+            Stanza::new("add-identity", &[&other.to_string()], &[]),
+            Stanza::new("extension-labels", &[], &[]),
+            Stanza::new("wrap-file-key", &[], &file_keys[0]),
+            Stanza::new("wrap-file-key", &[], &file_keys[1]),
+            Stanza::new("done", &[], &[]),
+        ],
+        5,
+    );
+    let mut output = Vec::new();
+    recipient_v1(&mut &input[..], &mut output).unwrap();
+    let sent = responses(&output);
+    assert_eq!(sent.len(), 6);
+    assert_eq!(sent[0], Stanza::new("labels", &["postquantum"], &[]));
+    for stanza in sent[1..5].iter() {
+        assert_eq!(stanza.tag, "recipient-stanza");
+        assert_eq!(stanza.args[1], STANZA_TYPE);
+    }
+    assert_eq!(sent[5], Stanza::new("done", &[], &[]));
+
+    // Decryption of both files, with a stanza of another plugin and grease
+    let mut commands = vec![
+        Stanza::new("add-identity", &[&identity.to_string()], &[]),
+        Stanza::new("recipient-stanza", &["0", "X25519", "AAAA"], &[0; 32]),
+    ];
+    commands.extend(sent[1..5].iter().cloned());
+    commands.push(Stanza::new("grease-y", &[], &[]));
+    commands.push(Stanza::new("done", &[], &[]));
+    let mut output = Vec::new();
+    identity_v1(&mut &transcript(&commands, 2)[..], &mut output).unwrap();
+    assert_eq!(
+        responses(&output),
+        [
+            Stanza::new("file-key", &["0"], &file_keys[0]),
+            Stanza::new("file-key", &["1"], &file_keys[1]),
+            Stanza::new("done", &[], &[]),
+        ]
+    );
+}
+
+#[test]
+fn age_plugin_errors() {
+    // Invalid recipient
+    let input = transcript(
+        &[
+            Stanza::new("add-recipient", &["age1kybe1qqqqqq"], &[]),
+            Stanza::new("wrap-file-key", &[], &[0; FILE_KEY_BYTES]),
+            Stanza::new("done", &[], &[]),
+        ],
+        1,
+    );
+    let mut output = Vec::new();
+    recipient_v1(&mut &input[..], &mut output).unwrap();
+    let sent = responses(&output);
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[0].tag, "error");
+    assert_eq!(sent[0].args, ["recipient", "0"]);
+    assert_eq!(sent[1].tag, "done");
+
+    // Malformed kybe stanza, wrong identity
+    let identity = Identity::generate();
+    let input = transcript(
+        &[
+            Stanza::new("add-identity", &[&identity.to_string()], &[]),
+            Stanza::new("recipient-stanza", &["0", "X25519", "AAAA"], &[0; 32]),
+            Stanza::new("recipient-stanza", &["0", STANZA_TYPE], &[0; 32]),
+            Stanza::new("done", &[], &[]),
+        ],
+        1,
+    );
+    let mut output = Vec::new();
+    identity_v1(&mut &input[..], &mut output).unwrap();
+    let sent = responses(&output);
+    assert_eq!(sent[0].tag, "error");
+    assert_eq!(sent[0].args, ["stanza", "0", "1"]);
+    assert_eq!(sent[1].tag, "done");
+
+    let stanza = Identity::generate()
+        .recipient()
+        .wrap(&[0; FILE_KEY_BYTES])
+        .unwrap();
+    let mut args = vec!["0", STANZA_TYPE];
+    args.extend(stanza.args.iter().map(String::as_str));
+    let input = transcript(
+        &[
+            Stanza::new("add-identity", &[&identity.to_string()], &[]),
+            Stanza::new("recipient-stanza", &args, &stanza.body),
+            Stanza::new("done", &[], &[]),
+        ],
+        0,
+    );
+    let mut output = Vec::new();
+    identity_v1(&mut &input[..], &mut output).unwrap();
+    assert_eq!(responses(&output), [Stanza::new("done", &[], &[])]);
+
+    // Truncated transcripts and refused commands
+    let input = transcript(
+        &[Stanza::new("add-identity", &[&identity.to_string()], &[])],
+        0,
+    );
+    assert!(identity_v1(&mut &input[..], &mut Vec::new()).is_err());
+    let input = transcript(
+        &[
+            Stanza::new("add-recipient", &[&identity.recipient().to_string()], &[]),
+            Stanza::new("wrap-file-key", &[], &[0; FILE_KEY_BYTES]),
+            Stanza::new("done", &[], &[]),
+            Stanza::new("fail", &[], &[]),
+        ],
+        0,
+    );
+    assert!(recipient_v1(&mut &input[..], &mut Vec::new()).is_err());
+
+    // Invalid file key
+    let input = transcript(
+        &[
+            Stanza::new("add-recipient", &[&identity.recipient().to_string()], &[]),
+            Stanza::new("wrap-file-key", &[], &[0; 32]),
+            Stanza::new("done", &[], &[]),
+        ],
+        1,
+    );
+    let mut output = Vec::new();
+    recipient_v1(&mut &input[..], &mut output).unwrap();
+    let sent = responses(&output);
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[0].tag, "error");
+    assert_eq!(sent[0].args, ["internal"]);
+    assert_eq!(sent[1].tag, "done");
+}
@@ -0,0 +1,187 @@
+//! Stanzas
+//!
+//! `-> tag args...` lines followed by an unpadded base64 body wrapped at 64 columns, whose
+//! last line is shorter than 64 characters (possibly empty). This is the format of the age
+//! header recipients and of the plugin protocol messages.
+
+use crate::formats::pem;
+
+use std::io::{self, BufRead, Write};
+
+/// Number of base64 characters per body line
+const LINE_LEN: usize = 64;
+
+/// Unpadded standard base64 encoding
+pub fn base64_encode(data: &[u8]) -> String {
+    pem::base64_encode(data).trim_end_matches('=').to_string()
+}
+
+/// Unpadded standard base64 decoding, rejecting non-canonical encodings
+pub fn base64_decode(s: &str) -> Result<Vec<u8>, String> {
+    if s.contains(|c: char| c == '=' || c.is_ascii_whitespace()) {
+        return Err("invalid base64 character".to_string());
+    }
+    let mut padded = s.to_string();
+    while !padded.len().is_multiple_of(4) {
+        padded.push('=');
+    }
+    let data = pem::base64_decode(&padded)?;
+    // Non-zero trailing bits are not canonical
+    if base64_encode(&data) != s {
+        return Err("non-canonical base64".to_string());
+    }
+    Ok(data)
+}
+
+/// Stanza: tag, arguments and body
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stanza {
+    /// First argument, the stanza type or command
+    pub tag: String,
+    /// Other arguments
+    pub args: Vec<String>,
+    /// Decoded body
+    pub body: Vec<u8>,
+}
+
+/// Arguments are non-empty strings of visible ASCII characters
+fn valid_arg(arg: &str) -> bool {
+    !arg.is_empty() && arg.bytes().all(|c| (33..=126).contains(&c))
+}
+
+/// Read a line and strip its line feed
+fn read_line<R: BufRead>(input: &mut R) -> Result<String, String> {
+    let mut line = String::new();
+    input.read_line(&mut line).map_err(|e| e.to_string())?;
+    match line.strip_suffix('\n') {
+        Some(line) => Ok(line.to_string()),
+        None => Err("unexpected end of input".to_string()),
+    }
+}
+
+impl Stanza {
+    /// Stanza of a tag, arguments and body
+    pub fn new(tag: &str, args: &[&str], body: &[u8]) -> Self {
+        Self {
+            tag: tag.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            body: body.to_vec(),
+        }
+    }
+
+    /// Write the stanza
+    pub fn write<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        write!(output, "->")?;
+        for arg in std::iter::once(&self.tag).chain(self.args.iter()) {
+            write!(output, " {}", arg)?;
+        }
+        writeln!(output)?;
+
+        let body = base64_encode(&self.body);
+        let mut lines = body.as_bytes().chunks(LINE_LEN).peekable();
+        while let Some(line) = lines.next() {
+            // Base64 output is ASCII
+            writeln!(output, "{}", std::str::from_utf8(line).unwrap())?;
+            if line.len() == LINE_LEN && lines.peek().is_none() {
+                writeln!(output)?;
+            }
+        }
+        if body.is_empty() {
+            writeln!(output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read a stanza
+    pub fn read<R: BufRead>(input: &mut R) -> Result<Self, String> {
+        let line = read_line(input)?;
+        let mut args = line
+            .strip_prefix("-> ")
+            .ok_or("stanza must start with \"-> \"")?
+            .split(' ');
+        // `split` yields at least one item
+        let tag = args.next().unwrap().to_string();
+        let args: Vec<String> = args.map(str::to_string).collect();
+        if !valid_arg(&tag) || !args.iter().all(|a| valid_arg(a)) {
+            return Err("invalid stanza argument".to_string());
+        }
+
+        let mut body = String::new();
+        loop {
+            let line = read_line(input)?;
+            if line.len() > LINE_LEN {
+                return Err("stanza body line is too long".to_string());
+            }
+            body.push_str(&line);
+            if line.len() < LINE_LEN {
+                break;
+            }
+        }
+
+        Ok(Self {
+            tag,
+            args,
+            body: base64_decode(&body)?,
+        })
+    }
+
+    /// Serialized stanza
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        // Writing to a vector does not fail
+        self.write(&mut out).unwrap();
+        out
+    }
+}
+
+#[test]
+fn age_stanza_encoding() {
+    // age header test: empty body, full last line, longer body
+    let stanza = Stanza::new("X25519", &["abc"], &[]);
+    assert_eq!(stanza.to_bytes(), b"-> X25519 abc\n\n");
+    let stanza = Stanza::new("ok", &[], &[0; 48]);
+    assert_eq!(
+        stanza.to_bytes(),
+        [b"-> ok\n", &[b'A'; 64][..], b"\n\n"].concat()
+    );
+    let stanza = Stanza::new("t", &["a", "b"], &[0xff; 50]);
+    let bytes = stanza.to_bytes();
+    assert_eq!(bytes.iter().filter(|c| **c == b'\n').count(), 3);
+
+    for stanza in [
+        Stanza::new("ok", &[], &[]),
+        Stanza::new("ok", &[], &[0; 48]),
+        Stanza::new("recipient-stanza", &["0", "kybe", "AAAA"], &[1; 100]),
+    ]
+    .iter()
+    {
+        let bytes = stanza.to_bytes();
+        let mut input = &bytes[..];
+        assert_eq!(Stanza::read(&mut input).unwrap(), *stanza);
+        assert!(input.is_empty());
+    }
+}
+
+#[test]
+fn age_stanza_malformed() {
+    for bytes in [
+        &b"-> ok\n"[..],
+        b"-> ok",
+        b"->ok\n\n",
+        b"-> ok  a\n\n",
+        b"-> \n\n",
+        b"-> ok\nAA==\n",
+        b"-> ok\nAB\n",
+        b"-> ok\n A\n",
+    ]
+    .iter()
+    {
+        assert!(Stanza::read(&mut &bytes[..]).is_err(), "{:?}", bytes);
+    }
+    // A full line must be followed by another line
+    let bytes = [b"-> ok\n", &[b'A'; 64][..], b"\n"].concat();
+    assert!(Stanza::read(&mut &bytes[..]).is_err());
+    let bytes = [b"-> ok\n", &[b'A'; 65][..], b"\n\n"].concat();
+    assert!(Stanza::read(&mut &bytes[..]).is_err());
+}
@@ -0,0 +1,363 @@
+//! Authenticated key exchange
+//!
+//! Kyber.UAKE and Kyber.AKE from the Kyber paper (section 5), built on the CCA KEM.
+//!
+//! - UAKE authenticates the responder B: the initiator sends an ephemeral public key
+//!   `pk_e` and an encapsulation `c_b` to B's static key, B answers with an encapsulation
+//!   `c_e` to `pk_e`. The session key is `KDF(k_e || k_b)`.
+//! - AKE authenticates both parties: B also encapsulates `c_a` to A's static key, and
+//!   the session key is `KDF(k_e || k_a || k_b)`.
+//!
+//! The parties are state machines consuming their state at each step, and the messages
+//! are typed by the parameter set and can be encoded with `to_bytes`/`from_bytes`.
+
+#[cfg(feature = "serde")]
+mod serialization;
+
+use crate::functions::utils::kdf;
+use crate::kem::KEM;
+use crate::params::ParameterSet;
+use crate::structures::ByteArray;
+use crate::validate::{validate_ciphertext, validate_public_key, validate_secret_key};
+
+use std::marker::PhantomData;
+
+/// Size of a session key
+pub const SESSION_KEY_BYTES: usize = 32;
+
+/// First message, from the initiator: `pk_e || c_b`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InitiatorMessage<P: ParameterSet, const N: usize, const K: usize> {
+    pk: ByteArray,
+    c: ByteArray,
+    params: PhantomData<P>,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> InitiatorMessage<P, N, K> {
+    /// Size of the encoded message
+    pub const BYTES: usize = P::PK_BYTES + P::CT_BYTES;
+
+    /// Decode a message after validating it
+    pub fn from_bytes(bytes: &ByteArray) -> Result<Self, String> {
+        if bytes.data.len() != Self::BYTES {
+            return Err(format!("initiator message must be {} bytes", Self::BYTES));
+        }
+        let (pk, c) = bytes.split_at(P::PK_BYTES);
+        validate_public_key::<P>(&pk)?;
+
+        Ok(Self {
+            pk,
+            c,
+            params: PhantomData,
+        })
+    }
+
+    /// Encoded message
+    pub fn to_bytes(&self) -> ByteArray {
+        self.pk.append(&self.c)
+    }
+}
+
+/// Second message of Kyber.UAKE, from the responder: `c_e`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UakeResponse<P: ParameterSet, const N: usize, const K: usize> {
+    c: ByteArray,
+    params: PhantomData<P>,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> UakeResponse<P, N, K> {
+    /// Size of the encoded message
+    pub const BYTES: usize = P::CT_BYTES;
+
+    /// Decode a message after validating its length
+    pub fn from_bytes(bytes: &ByteArray) -> Result<Self, String> {
+        validate_ciphertext::<P>(bytes)?;
+
+        Ok(Self {
+            c: bytes.clone(),
+            params: PhantomData,
+        })
+    }
+
+    /// Encoded message
+    pub fn to_bytes(&self) -> ByteArray {
+        self.c.clone()
+    }
+}
+
+/// Second message of Kyber.AKE, from the responder: `c_e || c_a`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AkeResponse<P: ParameterSet, const N: usize, const K: usize> {
+    c_e: ByteArray,
+    c_a: ByteArray,
+    params: PhantomData<P>,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> AkeResponse<P, N, K> {
+    /// Size of the encoded message
+    pub const BYTES: usize = 2 * P::CT_BYTES;
+
+    /// Decode a message after validating its length
+    pub fn from_bytes(bytes: &ByteArray) -> Result<Self, String> {
+        if bytes.data.len() != Self::BYTES {
+            return Err(format!("responder message must be {} bytes", Self::BYTES));
+        }
+        let (c_e, c_a) = bytes.split_at(P::CT_BYTES);
+
+        Ok(Self {
+            c_e,
+            c_a,
+            params: PhantomData,
+        })
+    }
+
+    /// Encoded message
+    pub fn to_bytes(&self) -> ByteArray {
+        self.c_e.append(&self.c_a)
+    }
+}
+
+/// Start of the exchange shared by both protocols => (ephemeral secret key, k_b, message)
+fn initiate<P: ParameterSet, const N: usize, const K: usize>(
+    kem: &KEM<P, N, K>,
+    pk_b: &ByteArray,
+) -> Result<(ByteArray, ByteArray, InitiatorMessage<P, N, K>), String> {
+    let (sk_e, pk_e) = kem.keygen();
+    let (c_b, k_b) = kem.encaps_checked(pk_b)?;
+    let msg = InitiatorMessage {
+        pk: pk_e,
+        c: c_b,
+        params: PhantomData,
+    };
+
+    Ok((sk_e, k_b, msg))
+}
+
+/// Session key derived from the concatenated shared secrets
+fn session_key(secrets: &[&ByteArray]) -> ByteArray {
+    kdf(&ByteArray::concat(secrets), SESSION_KEY_BYTES)
+}
+
+/// Kyber.UAKE initiator, waiting for the response
+pub struct UakeInitiator<P: ParameterSet, const N: usize, const K: usize> {
+    kem: KEM<P, N, K>,
+    sk_e: ByteArray,
+    k_b: ByteArray,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> UakeInitiator<P, N, K> {
+    /// Start an exchange with the responder of static public key `pk_b`
+    pub fn start(
+        kem: KEM<P, N, K>,
+        pk_b: &ByteArray,
+    ) -> Result<(Self, InitiatorMessage<P, N, K>), String> {
+        let (sk_e, k_b, msg) = initiate(&kem, pk_b)?;
+
+        Ok((Self { kem, sk_e, k_b }, msg))
+    }
+
+    /// Process the response => session key
+    pub fn finish(self, msg: &UakeResponse<P, N, K>) -> Result<ByteArray, String> {
+        let k_e = self.kem.decaps_checked(&msg.c, &self.sk_e)?;
+
+        Ok(session_key(&[&k_e, &self.k_b]))
+    }
+}
+
+/// Kyber.UAKE responder, holding its static secret key
+pub struct UakeResponder<P: ParameterSet, const N: usize, const K: usize> {
+    kem: KEM<P, N, K>,
+    sk_b: ByteArray,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> UakeResponder<P, N, K> {
+    /// Responder of static secret key `sk_b`
+    pub fn new(kem: KEM<P, N, K>, sk_b: &ByteArray) -> Result<Self, String> {
+        validate_secret_key::<P>(sk_b)?;
+
+        Ok(Self {
+            kem,
+            sk_b: sk_b.clone(),
+        })
+    }
+
+    /// Answer an initiator => (response, session key)
+    pub fn respond(
+        &self,
+        msg: &InitiatorMessage<P, N, K>,
+    ) -> Result<(UakeResponse<P, N, K>, ByteArray), String> {
+        let (c_e, k_e) = self.kem.encaps_checked(&msg.pk)?;
+        let k_b = self.kem.decaps_checked(&msg.c, &self.sk_b)?;
+        let response = UakeResponse {
+            c: c_e,
+            params: PhantomData,
+        };
+
+        Ok((response, session_key(&[&k_e, &k_b])))
+    }
+}
+
+/// Kyber.AKE initiator, waiting for the response
+pub struct AkeInitiator<P: ParameterSet, const N: usize, const K: usize> {
+    kem: KEM<P, N, K>,
+    sk_a: ByteArray,
+    sk_e: ByteArray,
+    k_b: ByteArray,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> AkeInitiator<P, N, K> {
+    /// Start an exchange as the owner of `sk_a` with the responder of static public key `pk_b`
+    pub fn start(
+        kem: KEM<P, N, K>,
+        sk_a: &ByteArray,
+        pk_b: &ByteArray,
+    ) -> Result<(Self, InitiatorMessage<P, N, K>), String> {
+        validate_secret_key::<P>(sk_a)?;
+        let (sk_e, k_b, msg) = initiate(&kem, pk_b)?;
+        let initiator = Self {
+            kem,
+            sk_a: sk_a.clone(),
+            sk_e,
+            k_b,
+        };
+
+        Ok((initiator, msg))
+    }
+
+    /// Process the response => session key
+    pub fn finish(self, msg: &AkeResponse<P, N, K>) -> Result<ByteArray, String> {
+        let k_e = self.kem.decaps_checked(&msg.c_e, &self.sk_e)?;
+        let k_a = self.kem.decaps_checked(&msg.c_a, &self.sk_a)?;
+
+        Ok(session_key(&[&k_e, &k_a, &self.k_b]))
+    }
+}
+
+/// Kyber.AKE responder, holding its static secret key
+pub struct AkeResponder<P: ParameterSet, const N: usize, const K: usize> {
+    kem: KEM<P, N, K>,
+    sk_b: ByteArray,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> AkeResponder<P, N, K> {
+    /// Responder of static secret key `sk_b`
+    pub fn new(kem: KEM<P, N, K>, sk_b: &ByteArray) -> Result<Self, String> {
+        validate_secret_key::<P>(sk_b)?;
+
+        Ok(Self {
+            kem,
+            sk_b: sk_b.clone(),
+        })
+    }
+
+    /// Answer the initiator of static public key `pk_a` => (response, session key)
+    pub fn respond(
+        &self,
+        msg: &InitiatorMessage<P, N, K>,
+        pk_a: &ByteArray,
+    ) -> Result<(AkeResponse<P, N, K>, ByteArray), String> {
+        let (c_e, k_e) = self.kem.encaps_checked(&msg.pk)?;
+        let (c_a, k_a) = self.kem.encaps_checked(pk_a)?;
+        let k_b = self.kem.decaps_checked(&msg.c, &self.sk_b)?;
+        let response = AkeResponse {
+            c_e,
+            c_a,
+            params: PhantomData,
+        };
+
+        Ok((response, session_key(&[&k_e, &k_a, &k_b])))
+    }
+}
+
+#[cfg(test)]
+fn uake<P: ParameterSet + Clone, const N: usize, const K: usize>(kem: KEM<P, N, K>) {
+    let (sk_b, pk_b) = kem.keygen();
+    let responder = UakeResponder::new(kem.clone(), &sk_b).unwrap();
+
+    // Messages go through their encoding
+    let (initiator, msg1) = UakeInitiator::start(kem.clone(), &pk_b).unwrap();
+    let msg1 = InitiatorMessage::from_bytes(&msg1.to_bytes()).unwrap();
+    let (msg2, key_b) = responder.respond(&msg1).unwrap();
+    let msg2 = UakeResponse::from_bytes(&msg2.to_bytes()).unwrap();
+    let key_a = initiator.finish(&msg2).unwrap();
+    assert_eq!(key_a, key_b);
+    assert_eq!(key_a.data.len(), SESSION_KEY_BYTES);
+
+    // A fresh exchange gives a fresh key
+    let (initiator, msg1) = UakeInitiator::start(kem.clone(), &pk_b).unwrap();
+    let (msg2, key) = responder.respond(&msg1).unwrap();
+    assert_ne!(key, key_a);
+    assert_eq!(initiator.finish(&msg2).unwrap(), key);
+
+    // An impostor without sk_b does not agree on the key
+    let (sk_m, _) = kem.keygen();
+    let impostor = UakeResponder::new(kem.clone(), &sk_m).unwrap();
+    let (initiator, msg1) = UakeInitiator::start(kem, &pk_b).unwrap();
+    let (msg2, key_m) = impostor.respond(&msg1).unwrap();
+    assert_ne!(initiator.finish(&msg2).unwrap(), key_m);
+}
+
+#[cfg(test)]
+fn ake<P: ParameterSet + Clone, const N: usize, const K: usize>(kem: KEM<P, N, K>) {
+    let (sk_a, pk_a) = kem.keygen();
+    let (sk_b, pk_b) = kem.keygen();
+    let responder = AkeResponder::new(kem.clone(), &sk_b).unwrap();
+
+    let (initiator, msg1) = AkeInitiator::start(kem.clone(), &sk_a, &pk_b).unwrap();
+    let msg1 = InitiatorMessage::from_bytes(&msg1.to_bytes()).unwrap();
+    let (msg2, key_b) = responder.respond(&msg1, &pk_a).unwrap();
+    let msg2 = AkeResponse::from_bytes(&msg2.to_bytes()).unwrap();
+    let key_a = initiator.finish(&msg2).unwrap();
+    assert_eq!(key_a, key_b);
+
+    // The responder expecting another initiator does not agree on the key
+    let (sk_m, _) = kem.keygen();
+    let (initiator, msg1) = AkeInitiator::start(kem.clone(), &sk_m, &pk_b).unwrap();
+    let (msg2, key_b) = responder.respond(&msg1, &pk_a).unwrap();
+    assert_ne!(initiator.finish(&msg2).unwrap(), key_b);
+
+    // The initiator does not agree with an impostor responder either
+    let impostor = AkeResponder::new(kem.clone(), &sk_m).unwrap();
+    let (initiator, msg1) = AkeInitiator::start(kem, &sk_a, &pk_b).unwrap();
+    let (msg2, key_m) = impostor.respond(&msg1, &pk_a).unwrap();
+    assert_ne!(initiator.finish(&msg2).unwrap(), key_m);
+}
+
+#[test]
+fn uake_exchange() {
+    uake(crate::kyber512kem());
+    uake(crate::kyber768kem());
+    uake(crate::kyber1024kem());
+}
+
+#[test]
+fn ake_exchange() {
+    ake(crate::kyber512kem());
+    ake(crate::kyber768kem());
+    ake(crate::kyber1024kem());
+}
+
+#[test]
+fn ake_malformed_messages() {
+    use crate::params::Kyber768;
+
+    let kem = crate::kyber768kem();
+    let (sk, pk) = kem.keygen();
+    let (_, msg1) = UakeInitiator::start(kem.clone(), &pk).unwrap();
+    let bytes = msg1.to_bytes();
+    assert_eq!(
+        bytes.data.len(),
+        InitiatorMessage::<Kyber768, 256, 3>::BYTES
+    );
+
+    type Msg1 = InitiatorMessage<Kyber768, 256, 3>;
+    assert!(Msg1::from_bytes(&bytes.truncate(bytes.data.len() - 1)).is_err());
+    assert!(Msg1::from_bytes(&bytes.append(&ByteArray::from_bytes(&[0]))).is_err());
+    assert!(UakeResponse::<Kyber768, 256, 3>::from_bytes(&bytes).is_err());
+    assert!(AkeResponse::<Kyber768, 256, 3>::from_bytes(&bytes).is_err());
+
+    // Static keys are validated
+    assert!(UakeResponder::new(kem.clone(), &sk.truncate(10)).is_err());
+    assert!(AkeInitiator::start(kem.clone(), &sk, &pk.truncate(10)).is_err());
+    assert!(UakeInitiator::start(kem, &pk.truncate(10)).is_err());
+}
@@ -0,0 +1,58 @@
+//! Serialization
+//!
+//! `serde` support for the messages, encoded like a `ByteArray` holding their `to_bytes`
+//! encoding. Deserialization validates them like `from_bytes`.
+
+use crate::ake::{AkeResponse, InitiatorMessage, UakeResponse};
+use crate::params::ParameterSet;
+use crate::structures::ByteArray;
+
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+macro_rules! impl_serde {
+    ($message:ident) => {
+        impl<P: ParameterSet, const N: usize, const K: usize> Serialize for $message<P, N, K> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.to_bytes().serialize(serializer)
+            }
+        }
+
+        impl<'de, P: ParameterSet, const N: usize, const K: usize> Deserialize<'de>
+            for $message<P, N, K>
+        {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let bytes = ByteArray::deserialize(deserializer)?;
+                Self::from_bytes(&bytes).map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+impl_serde!(InitiatorMessage);
+impl_serde!(UakeResponse);
+impl_serde!(AkeResponse);
+
+#[test]
+fn serde_messages() {
+    use crate::ake::{AkeInitiator, AkeResponder};
+    use crate::Kyber512;
+
+    let kem = crate::kyber512kem();
+    let (sk_a, pk_a) = kem.keygen();
+    let (sk_b, pk_b) = kem.keygen();
+    let responder = AkeResponder::new(kem.clone(), &sk_b).unwrap();
+
+    let (initiator, msg1) = AkeInitiator::start(kem, &sk_a, &pk_b).unwrap();
+    let json = serde_json::to_string(&msg1).unwrap();
+    let msg1: InitiatorMessage<Kyber512, 256, 2> = serde_json::from_str(&json).unwrap();
+
+    let (msg2, key) = responder.respond(&msg1, &pk_a).unwrap();
+    let mut cbor = Vec::new();
+    ciborium::ser::into_writer(&msg2, &mut cbor).unwrap();
+    let msg2: AkeResponse<Kyber512, 256, 2> = ciborium::de::from_reader(&cbor[..]).unwrap();
+    assert_eq!(initiator.finish(&msg2).unwrap(), key);
+
+    assert!(serde_json::from_str::<UakeResponse<Kyber512, 256, 2>>(&json).is_err());
+}
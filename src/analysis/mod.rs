@@ -0,0 +1,228 @@
+//! Analysis
+//!
+//! Exact decryption failure probability of a parameter set, following the
+//! Kyber security analysis scripts
+
+use crate::functions::compress::{compress_integer, decompress_integer};
+use crate::params::ParameterSet;
+use crate::structures::F3329;
+
+/// Probabilities below this threshold (about 2^-300, as in the reference scripts) are dropped
+const CLEAN_THRESHOLD: f64 = 1e-90;
+
+/// A probability law on a range of integers
+#[derive(Clone, Debug)]
+struct Law {
+    /// Smallest value of the support
+    offset: i64,
+    /// `probs[i]` is the probability of `offset + i`
+    probs: Vec<f64>,
+}
+
+impl Law {
+    /// Build a law from a list of (value, probability)
+    fn from_pairs(pairs: &[(i64, f64)]) -> Self {
+        let min = pairs.iter().map(|(x, _)| *x).min().unwrap_or(0);
+        let max = pairs.iter().map(|(x, _)| *x).max().unwrap_or(0);
+
+        let mut probs = vec![0.0; (max - min + 1) as usize];
+        for (x, p) in pairs {
+            probs[(x - min) as usize] += p;
+        }
+
+        Self { offset: min, probs }.clean()
+    }
+
+    /// Centered binomial distribution of parameter eta
+    fn centered_binomial(eta: usize) -> Self {
+        // Row 2 eta of Pascal's triangle divided by 2^(2 eta), one halving per row so that
+        // no intermediate value overflows
+        let mut probs = vec![1.0];
+        for _ in 0..2 * eta {
+            let mut next = vec![0.0; probs.len() + 1];
+            for (i, p) in probs.iter().enumerate() {
+                next[i] += p / 2.0;
+                next[i + 1] += p / 2.0;
+            }
+            probs = next;
+        }
+
+        Self {
+            offset: -(eta as i64),
+            probs,
+        }
+        .clean()
+    }
+
+    /// Law of x - decompress(compress(x, d)) for x uniform modulo q
+    fn compression_error(d: usize) -> Self {
+        let q = F3329::order();
+        // From 12 bits on the compression is lossless, larger shifts would overflow
+        let d = d.min(12);
+        let pairs: Vec<(i64, f64)> = (0..q)
+            .map(|x| {
+                let y = decompress_integer(compress_integer(x, d, q), d, q);
+                (center(x as i64 - y as i64, q as i64), 1.0 / q as f64)
+            })
+            .collect();
+
+        Self::from_pairs(&pairs)
+    }
+
+    /// Law of X + Y
+    fn convolution(&self, other: &Self) -> Self {
+        let mut probs = vec![0.0; self.probs.len() + other.probs.len() - 1];
+        for (i, a) in self.probs.iter().enumerate() {
+            for (j, b) in other.probs.iter().enumerate() {
+                probs[i + j] += a * b;
+            }
+        }
+
+        Self {
+            offset: self.offset + other.offset,
+            probs,
+        }
+        .clean()
+    }
+
+    /// Law of X * Y
+    fn product(&self, other: &Self) -> Self {
+        let mut pairs = Vec::with_capacity(self.probs.len() * other.probs.len());
+        for (i, a) in self.probs.iter().enumerate() {
+            for (j, b) in other.probs.iter().enumerate() {
+                pairs.push(((self.offset + i as i64) * (other.offset + j as i64), a * b));
+            }
+        }
+
+        Self::from_pairs(&pairs)
+    }
+
+    /// Law of the sum of `n` independent copies of X
+    fn iter_convolution(&self, n: usize) -> Self {
+        let mut result = Self::from_pairs(&[(0, 1.0)]);
+        let mut square = self.clone();
+        let mut n = n;
+
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.convolution(&square);
+            }
+            n >>= 1;
+            if n > 0 {
+                square = square.convolution(&square);
+            }
+        }
+
+        result
+    }
+
+    /// Probability that |X| > t
+    fn tail_probability(&self, t: f64) -> f64 {
+        self.probs
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| ((self.offset + *i as i64) as f64).abs() > t)
+            .map(|(_, p)| p)
+            .sum()
+    }
+
+    /// Drop negligible probabilities at both ends of the support
+    fn clean(mut self) -> Self {
+        let first = self.probs.iter().position(|p| *p > CLEAN_THRESHOLD);
+        let last = self.probs.iter().rposition(|p| *p > CLEAN_THRESHOLD);
+
+        if let (Some(first), Some(last)) = (first, last) {
+            self.probs.truncate(last + 1);
+            self.probs.drain(..first);
+            self.offset += first as i64;
+        }
+
+        self
+    }
+}
+
+/// Representative of x modulo q in (-q/2, q/2]
+fn center(x: i64, q: i64) -> i64 {
+    let x = x.rem_euclid(q);
+    if x > q / 2 {
+        x - q
+    } else {
+        x
+    }
+}
+
+/// Decryption failure probability of a PKE parameter set, as log2(probability)
+///
+/// The error term `e^T r + e2 + c_v - s^T (e1 + c_u)` is computed exactly by
+/// convolving the centered binomial laws with the compression errors `c_u` and `c_v`
+/// of `compress_poly` for `du` and `dv`. Decryption fails if one of the N
+/// coefficients exceeds q/4.
+pub fn failure_log2(n: usize, k: usize, eta1: usize, eta2: usize, du: usize, dv: usize) -> f64 {
+    let q = F3329::order() as f64;
+
+    let chi1 = Law::centered_binomial(eta1);
+    let chi2 = Law::centered_binomial(eta2);
+
+    // e * r
+    let b1 = chi1.product(&chi1);
+    // s * (e1 + c_u)
+    let b2 = chi1.product(&chi2.convolution(&Law::compression_error(du)));
+    let c = b1.convolution(&b2).iter_convolution(k * n);
+
+    // + e2 + c_v
+    let d = c.convolution(&chi2.convolution(&Law::compression_error(dv)));
+
+    let p = (n as f64 * d.tail_probability(q / 4.0)).min(1.0);
+    p.log2()
+}
+
+/// Decryption failure probability of the parameter set `P`, as log2(probability)
+pub fn failure_log2_of<P: ParameterSet>() -> f64 {
+    failure_log2(P::N, P::K, P::ETA1, P::ETA2, P::DU, P::DV)
+}
+
+#[test]
+fn centered_binomial_law() {
+    let law = Law::centered_binomial(2);
+    assert_eq!(law.offset, -2);
+    assert_eq!(
+        law.probs,
+        vec![1.0 / 16.0, 4.0 / 16.0, 6.0 / 16.0, 4.0 / 16.0, 1.0 / 16.0]
+    );
+
+    // Large parameters do not overflow
+    for eta in [32, 40, 100] {
+        let law = Law::centered_binomial(eta);
+        assert!(law.offset >= -(eta as i64));
+        assert!((law.probs.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn compression_error_law() {
+    // No error when there are more bits than needed
+    let law = Law::compression_error(12);
+    assert_eq!(law.offset, 0);
+    assert_eq!(law.probs.len(), 1);
+    let law = Law::compression_error(64);
+    assert_eq!(law.offset, 0);
+    assert_eq!(law.probs.len(), 1);
+
+    // Error is bounded by q/2^(d+1)
+    let law = Law::compression_error(3);
+    assert!(law.offset >= -209);
+    assert!(law.offset + law.probs.len() as i64 <= 209);
+    assert!((law.probs.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn failure_rate_presets() {
+    use crate::params::{Kyber512, Kyber768};
+
+    let f512 = failure_log2_of::<Kyber512>();
+    let f768 = failure_log2_of::<Kyber768>();
+
+    // Values from the specification: 2^-178 and 2^-164
+    assert!((f512 + 178.0).abs() < 2.0, "{}", f512);
+    assert!((f768 + 164.0).abs() < 2.0, "{}", f768);
+}
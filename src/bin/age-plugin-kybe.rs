@@ -0,0 +1,43 @@
+//! `age-plugin-kybe` age plugin
+//!
+//! Speaks the age plugin protocol on stdin/stdout when run by age with
+//! `--age-plugin=recipient-v1` or `--age-plugin=identity-v1`, and generates identities
+//! with `--generate`. Exits with 1 on protocol errors and 2 on invalid usage.
+
+use kybe_rs::age::{plugin, Identity};
+
+use std::{env, io, process};
+
+const USAGE: &str = "usage: age-plugin-kybe --generate
+
+Prints a new identity, preceded by its recipient in a comment. The identity file
+can be used with `age -d -i FILE` and the recipient with `age -r RECIPIENT`.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let result = match args[..] {
+        ["--generate"] => {
+            let identity = Identity::generate();
+            println!("# recipient: {}", identity.recipient());
+            println!("{}", identity);
+            Ok(())
+        }
+        ["--age-plugin=recipient-v1"] => {
+            plugin::recipient_v1(&mut stdin.lock(), &mut stdout.lock())
+        }
+        ["--age-plugin=identity-v1"] => plugin::identity_v1(&mut stdin.lock(), &mut stdout.lock()),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("age-plugin-kybe: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,105 @@
+//! Core-SVP security table
+//!
+//! Prints the estimated security of kyber512/768/1024 and of any parameter file given
+//! as argument. A parameter file contains one `name = value` per line for `n`, `k`,
+//! `q`, `eta1`, `eta2`, `du` and `dv`, lines starting with `#` are ignored.
+
+use kybe_rs::estimator::{core_svp, Instance, KYBER1024, KYBER512, KYBER768};
+
+use std::{env, fs, process};
+
+/// Parse a parameter file
+fn parse_instance(content: &str) -> Result<Instance, String> {
+    let mut instance = Instance {
+        n: 256,
+        k: 0,
+        q: 3329,
+        eta1: 0,
+        eta2: 0,
+        du: 0,
+        dv: 0,
+    };
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = line
+            .split_once('=')
+            .ok_or(format!("invalid line: {}", line))?;
+        let value: usize = value
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid value: {}", line))?;
+
+        match name.trim() {
+            "n" => instance.n = value,
+            "k" => instance.k = value,
+            "q" => instance.q = value,
+            "eta1" => instance.eta1 = value,
+            "eta2" => instance.eta2 = value,
+            "du" => instance.du = value,
+            "dv" => instance.dv = value,
+            other => return Err(format!("unknown parameter: {}", other)),
+        }
+    }
+
+    let Instance {
+        n,
+        k,
+        q,
+        eta1,
+        eta2,
+        du,
+        dv,
+    } = instance;
+    if n == 0 || k == 0 || q < 2 || eta1 == 0 || eta2 == 0 || du == 0 || dv == 0 {
+        return Err("n, k, q, eta1, eta2, du and dv must all be set".to_string());
+    }
+
+    Ok(instance)
+}
+
+fn print_row(name: &str, instance: &Instance) {
+    let s = core_svp(instance);
+    println!(
+        "{:<16} {:>7} {:>7} {:>7} {:>7} {:>9.1} {:>9.1}",
+        name,
+        s.primal.blocksize,
+        s.dual.blocksize,
+        format!("{:.1}", s.primal.classical),
+        format!("{:.1}", s.dual.classical),
+        s.classical(),
+        s.quantum()
+    );
+}
+
+fn main() {
+    let mut instances = vec![
+        ("kyber512".to_string(), KYBER512),
+        ("kyber768".to_string(), KYBER768),
+        ("kyber1024".to_string(), KYBER1024),
+    ];
+
+    for path in env::args().skip(1) {
+        let instance = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| parse_instance(&content));
+
+        match instance {
+            Ok(instance) => instances.push((path, instance)),
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                process::exit(1);
+            }
+        }
+    }
+
+    println!(
+        "{:<16} {:>7} {:>7} {:>7} {:>7} {:>9} {:>9}",
+        "parameters", "b_p", "b_d", "primal", "dual", "classical", "quantum"
+    );
+    for (name, instance) in instances.iter() {
+        print_row(name, instance);
+    }
+}
@@ -0,0 +1,322 @@
+//! `kybe` command-line tool
+//!
+//! Key generation, encapsulation, decapsulation and PKE encryption/decryption on files.
+//! Exits with 1 on malformed input and 2 on invalid usage.
+
+use kybe_rs::formats::{hex, pem};
+use kybe_rs::{
+    kyber1024kem, kyber1024pke, kyber512kem, kyber512pke, kyber768kem, kyber768pke, ByteArray,
+    Kyber1024, Kyber512, Kyber768, ParameterSet, KEM, PKE,
+};
+
+use std::io::{self, Write};
+use std::{collections::HashMap, env, fs, process};
+
+const USAGE: &str = "usage: kybe <command> [options] [--format raw|hex|pem]
+
+commands:
+    keygen      --level 512|768|1024 --pk FILE --sk FILE
+    encaps      --pk FILE --ct FILE --ss FILE
+    decaps      --sk FILE --ct FILE --ss FILE
+    pke-encrypt --pk FILE --in FILE --out FILE
+    pke-decrypt --sk FILE --in FILE --out FILE
+
+The parameter set is inferred from the size of the keys. Files are read and
+written in the given format (raw by default).";
+
+/// Errors of the tool
+enum Error {
+    /// Invalid command line, exit code 2
+    Usage(String),
+    /// Malformed or unreadable input, exit code 1
+    Input(String),
+}
+
+/// File format
+#[derive(Clone, Copy)]
+enum Format {
+    Raw,
+    Hex,
+    Pem,
+}
+
+/// Kind of data stored in a file, used for the PEM labels
+#[derive(Clone, Copy)]
+enum Kind {
+    PublicKey,
+    SecretKey,
+    Ciphertext,
+    SharedSecret,
+    Message,
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Kind::PublicKey => "KYBER PUBLIC KEY",
+            Kind::SecretKey => "KYBER SECRET KEY",
+            Kind::Ciphertext => "KYBER CIPHERTEXT",
+            Kind::SharedSecret => "KYBER SHARED SECRET",
+            Kind::Message => "KYBER MESSAGE",
+        }
+    }
+
+    /// Whether files of this kind must only be readable by their owner
+    fn is_secret(self) -> bool {
+        matches!(self, Kind::SecretKey | Kind::SharedSecret | Kind::Message)
+    }
+}
+
+/// Parsed command line
+struct Options {
+    format: Format,
+    values: HashMap<String, String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, Error> {
+        let mut values = HashMap::new();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| Error::Usage(format!("unexpected argument {}", arg)))?;
+            let value = iter
+                .next()
+                .ok_or_else(|| Error::Usage(format!("missing value for --{}", name)))?;
+            values.insert(name.to_string(), value.clone());
+        }
+
+        let format = match values.remove("format").as_deref() {
+            None | Some("raw") => Format::Raw,
+            Some("hex") => Format::Hex,
+            Some("pem") => Format::Pem,
+            Some(other) => return Err(Error::Usage(format!("unknown format {}", other))),
+        };
+
+        Ok(Self { format, values })
+    }
+
+    /// Get a mandatory option
+    fn get(&self, name: &str) -> Result<&str, Error> {
+        self.values
+            .get(name)
+            .map(String::as_str)
+            .ok_or_else(|| Error::Usage(format!("missing --{}", name)))
+    }
+
+    /// Read a file in the selected format
+    fn read(&self, name: &str, kind: Kind) -> Result<ByteArray, Error> {
+        let path = self.get(name)?;
+        let content = fs::read(path).map_err(|e| Error::Input(format!("{}: {}", path, e)))?;
+        let text = || {
+            String::from_utf8(content.clone())
+                .map_err(|_| Error::Input(format!("{}: not a text file", path)))
+        };
+
+        let data = match self.format {
+            Format::Raw => content.clone(),
+            Format::Hex => {
+                hex::decode(&text()?).map_err(|e| Error::Input(format!("{}: {}", path, e)))?
+            }
+            Format::Pem => {
+                let (label, data) =
+                    pem::decode(&text()?).map_err(|e| Error::Input(format!("{}: {}", path, e)))?;
+                if label != kind.label() {
+                    return Err(Error::Input(format!(
+                        "{}: expected {}, found {}",
+                        path,
+                        kind.label(),
+                        label
+                    )));
+                }
+                data
+            }
+        };
+
+        Ok(ByteArray::from_bytes(&data))
+    }
+
+    /// Write a file in the selected format
+    fn write(&self, name: &str, kind: Kind, data: &ByteArray) -> Result<(), Error> {
+        let path = self.get(name)?;
+        let content = match self.format {
+            Format::Raw => data.data.clone(),
+            Format::Hex => (hex::encode(&data.data) + "\n").into_bytes(),
+            Format::Pem => pem::encode(kind.label(), &data.data).into_bytes(),
+        };
+
+        let error = |e: io::Error| Error::Input(format!("{}: {}", path, e));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            if kind.is_secret() {
+                options.mode(0o600);
+            }
+        }
+
+        let mut file = options.open(path).map_err(error)?;
+        // The mode only applies to new files, existing ones are restricted too
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if kind.is_secret() {
+                file.set_permissions(fs::Permissions::from_mode(0o600))
+                    .map_err(error)?;
+            }
+        }
+        file.write_all(&content).map_err(error)
+    }
+
+    /// Check the optional `--level` against the one inferred from the inputs
+    fn check_level(&self, level: usize) -> Result<(), Error> {
+        match self.values.get("level") {
+            Some(l) if *l != level.to_string() => Err(Error::Input(format!(
+                "input is for level {}, not {}",
+                level, l
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Security level of a parameter set
+fn level<P: ParameterSet>() -> usize {
+    P::K * P::N
+}
+
+fn keygen<P: ParameterSet, const N: usize, const K: usize>(
+    kem: KEM<P, N, K>,
+    opts: &Options,
+) -> Result<(), Error> {
+    let (sk, pk) = kem.keygen_checked().map_err(Error::Input)?;
+    opts.write("pk", Kind::PublicKey, &pk)?;
+    opts.write("sk", Kind::SecretKey, &sk)
+}
+
+fn encaps<P: ParameterSet, const N: usize, const K: usize>(
+    kem: KEM<P, N, K>,
+    pk: &ByteArray,
+    opts: &Options,
+) -> Result<(), Error> {
+    opts.check_level(level::<P>())?;
+    let (ct, ss) = kem.encaps_checked(pk).map_err(Error::Input)?;
+    opts.write("ct", Kind::Ciphertext, &ct)?;
+    opts.write("ss", Kind::SharedSecret, &ss)
+}
+
+fn decaps<P: ParameterSet, const N: usize, const K: usize>(
+    kem: KEM<P, N, K>,
+    sk: &ByteArray,
+    opts: &Options,
+) -> Result<(), Error> {
+    opts.check_level(level::<P>())?;
+    let ct = opts.read("ct", Kind::Ciphertext)?;
+
+    let ss = kem.decaps_checked(&ct, sk).map_err(Error::Input)?;
+    opts.write("ss", Kind::SharedSecret, &ss)
+}
+
+fn pke_encrypt<P: ParameterSet, const N: usize, const K: usize>(
+    pke: PKE<P, N, K>,
+    pk: &ByteArray,
+    opts: &Options,
+) -> Result<(), Error> {
+    opts.check_level(level::<P>())?;
+    let m = opts.read("in", Kind::Message)?;
+
+    let c = pke
+        .encrypt_checked(pk, &m, ByteArray::random(32))
+        .map_err(Error::Input)?;
+    opts.write("out", Kind::Ciphertext, &c)
+}
+
+fn pke_decrypt<P: ParameterSet, const N: usize, const K: usize>(
+    pke: PKE<P, N, K>,
+    sk: &ByteArray,
+    opts: &Options,
+) -> Result<(), Error> {
+    opts.check_level(level::<P>())?;
+    let c = opts.read("in", Kind::Ciphertext)?;
+
+    // A KEM secret key starts with the PKE secret key
+    let (sk, _) = sk.split_at(P::PKE_SK_BYTES);
+    let m = pke.decrypt_checked(&sk, &c).map_err(Error::Input)?;
+    opts.write("out", Kind::Message, &m)
+}
+
+fn run(args: &[String]) -> Result<(), Error> {
+    let command = args
+        .first()
+        .ok_or_else(|| Error::Usage("missing command".to_string()))?;
+    let opts = Options::parse(&args[1..])?;
+
+    match command.as_str() {
+        "keygen" => match opts.get("level")? {
+            "512" => keygen(kyber512kem(), &opts),
+            "768" => keygen(kyber768kem(), &opts),
+            "1024" => keygen(kyber1024kem(), &opts),
+            other => Err(Error::Usage(format!("unknown level {}", other))),
+        },
+        "encaps" | "pke-encrypt" => {
+            let pk = opts.read("pk", Kind::PublicKey)?;
+            let encrypt = command == "pke-encrypt";
+
+            match pk.data.len() {
+                Kyber512::PK_BYTES if encrypt => pke_encrypt(kyber512pke(), &pk, &opts),
+                Kyber768::PK_BYTES if encrypt => pke_encrypt(kyber768pke(), &pk, &opts),
+                Kyber1024::PK_BYTES if encrypt => pke_encrypt(kyber1024pke(), &pk, &opts),
+                Kyber512::PK_BYTES => encaps(kyber512kem(), &pk, &opts),
+                Kyber768::PK_BYTES => encaps(kyber768kem(), &pk, &opts),
+                Kyber1024::PK_BYTES => encaps(kyber1024kem(), &pk, &opts),
+                len => Err(Error::Input(format!("invalid public key size {}", len))),
+            }
+        }
+        "decaps" => {
+            let sk = opts.read("sk", Kind::SecretKey)?;
+
+            match sk.data.len() {
+                Kyber512::SK_BYTES => decaps(kyber512kem(), &sk, &opts),
+                Kyber768::SK_BYTES => decaps(kyber768kem(), &sk, &opts),
+                Kyber1024::SK_BYTES => decaps(kyber1024kem(), &sk, &opts),
+                len => Err(Error::Input(format!("invalid secret key size {}", len))),
+            }
+        }
+        "pke-decrypt" => {
+            let sk = opts.read("sk", Kind::SecretKey)?;
+
+            match sk.data.len() {
+                Kyber512::SK_BYTES | Kyber512::PKE_SK_BYTES => {
+                    pke_decrypt(kyber512pke(), &sk, &opts)
+                }
+                Kyber768::SK_BYTES | Kyber768::PKE_SK_BYTES => {
+                    pke_decrypt(kyber768pke(), &sk, &opts)
+                }
+                Kyber1024::SK_BYTES | Kyber1024::PKE_SK_BYTES => {
+                    pke_decrypt(kyber1024pke(), &sk, &opts)
+                }
+                len => Err(Error::Input(format!("invalid secret key size {}", len))),
+            }
+        }
+        other => Err(Error::Usage(format!("unknown command {}", other))),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match run(&args) {
+        Ok(()) => {}
+        Err(Error::Usage(e)) => {
+            eprintln!("kybe: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+        Err(Error::Input(e)) => {
+            eprintln!("kybe: {}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Envelope
+//!
+//! Hybrid public-key encryption of arbitrary-length messages: a fresh shared secret is
+//! encapsulated with the `KEM`, and an AEAD key and nonce are derived from it with
+//! SHAKE256. The ciphertext is self-describing:
+//!
+//! `version || parameter set || AEAD || KEM ciphertext || AEAD ciphertext`
+//!
+//! The 3-byte header is authenticated together with the caller's associated data.
+
+use crate::functions::hash::shake_256;
+use crate::hpke::{Aead as _, Aes256GcmAead, ChaCha20Poly1305};
+use crate::params::{Kyber1024, Kyber512, Kyber768, ParameterSet};
+use crate::structures::ByteArray;
+use crate::{kyber1024kem, kyber512kem, kyber768kem, KEM};
+
+/// Version of the format
+pub const VERSION: u8 = 1;
+
+/// Size of the header
+const HEADER_BYTES: usize = 3;
+
+/// Size of the AEAD tag
+const TAG_BYTES: usize = 16;
+
+/// Domain separation of the key derivation
+const LABEL: &[u8] = b"kybe-rs envelope";
+
+/// AEAD used to encrypt the message
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aead {
+    /// ChaCha20-Poly1305 (identifier 1)
+    ChaCha20Poly1305 = 1,
+    /// AES-256-GCM (identifier 2)
+    Aes256Gcm = 2,
+}
+
+impl Aead {
+    fn from_id(id: u8) -> Result<Self, String> {
+        match id {
+            1 => Ok(Aead::ChaCha20Poly1305),
+            2 => Ok(Aead::Aes256Gcm),
+            _ => Err(format!("unknown AEAD identifier {}", id)),
+        }
+    }
+
+    fn seal(self, key: &[u8], nonce: &[u8], aad: &[u8], pt: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Aead::ChaCha20Poly1305 => ChaCha20Poly1305::seal(key, nonce, aad, pt),
+            Aead::Aes256Gcm => Aes256GcmAead::seal(key, nonce, aad, pt),
+        }
+    }
+
+    fn open(self, key: &[u8], nonce: &[u8], aad: &[u8], ct: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Aead::ChaCha20Poly1305 => ChaCha20Poly1305::open(key, nonce, aad, ct),
+            Aead::Aes256Gcm => Aes256GcmAead::open(key, nonce, aad, ct),
+        }
+    }
+}
+
+/// Identifier of a parameter set: 1, 2 and 3 for Kyber512, Kyber768 and Kyber1024
+fn parameter_id<P: ParameterSet>() -> u8 {
+    P::K as u8 - 1
+}
+
+/// Derive the AEAD (key, nonce) from the shared secret, bound to the header and KEM ciphertext
+fn derive(ss: &ByteArray, header: &[u8], c: &ByteArray) -> (Vec<u8>, Vec<u8>) {
+    let input = [LABEL, header, &c.data, &ss.data].concat();
+    let mut okm = shake_256(&input, 44);
+    let nonce = okm.split_off(32);
+
+    (okm, nonce)
+}
+
+fn seal_kem<P: ParameterSet, const N: usize, const K: usize>(
+    kem: KEM<P, N, K>,
+    aead: Aead,
+    pk: &ByteArray,
+    pt: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, String> {
+    let (c, ss) = kem.encaps_checked(pk)?;
+
+    let header = [VERSION, parameter_id::<P>(), aead as u8];
+    let (key, nonce) = derive(&ss, &header, &c);
+    let body = aead.seal(&key, &nonce, &[&header[..], aad].concat(), pt)?;
+
+    Ok([&header[..], &c.data, &body].concat())
+}
+
+fn open_kem<P: ParameterSet, const N: usize, const K: usize>(
+    kem: KEM<P, N, K>,
+    sk: &ByteArray,
+    ct: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, String> {
+    if sk.data.len() != P::SK_BYTES {
+        return Err(format!("secret key is not a Kyber{} key", P::K * P::N));
+    }
+    if ct.len() < HEADER_BYTES + P::CT_BYTES + TAG_BYTES {
+        return Err("truncated ciphertext".to_string());
+    }
+    let (header, rest) = ct.split_at(HEADER_BYTES);
+    let (c, body) = rest.split_at(P::CT_BYTES);
+    let aead = Aead::from_id(header[2])?;
+
+    let c = ByteArray::from_bytes(c);
+    let ss = kem.decaps_checked(&c, sk)?;
+    let (key, nonce) = derive(&ss, header, &c);
+
+    aead.open(&key, &nonce, &[header, aad].concat(), body)
+}
+
+/// Encrypt `pt` for `pk` with ChaCha20-Poly1305, authenticating `aad`
+///
+/// The parameter set is inferred from the size of the public key.
+pub fn seal(pk: &ByteArray, pt: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    seal_with(Aead::ChaCha20Poly1305, pk, pt, aad)
+}
+
+/// Encrypt `pt` for `pk` with the given AEAD, authenticating `aad`
+pub fn seal_with(aead: Aead, pk: &ByteArray, pt: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    match pk.data.len() {
+        Kyber512::PK_BYTES => seal_kem(kyber512kem(), aead, pk, pt, aad),
+        Kyber768::PK_BYTES => seal_kem(kyber768kem(), aead, pk, pt, aad),
+        Kyber1024::PK_BYTES => seal_kem(kyber1024kem(), aead, pk, pt, aad),
+        len => Err(format!("invalid public key size {}", len)),
+    }
+}
+
+/// Decrypt a ciphertext produced by `seal` with the matching secret key and `aad`
+pub fn open(sk: &ByteArray, ct: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    match ct {
+        [VERSION, id, ..] if *id == parameter_id::<Kyber512>() => {
+            open_kem(kyber512kem(), sk, ct, aad)
+        }
+        [VERSION, id, ..] if *id == parameter_id::<Kyber768>() => {
+            open_kem(kyber768kem(), sk, ct, aad)
+        }
+        [VERSION, id, ..] if *id == parameter_id::<Kyber1024>() => {
+            open_kem(kyber1024kem(), sk, ct, aad)
+        }
+        [VERSION, id, ..] => Err(format!("unknown parameter set identifier {}", id)),
+        [version, ..] => Err(format!("unsupported version {}", version)),
+        [] => Err("empty ciphertext".to_string()),
+    }
+}
+
+#[test]
+fn envelope_roundtrip() {
+    let keys = [
+        kyber512kem().keygen(),
+        kyber768kem().keygen(),
+        kyber1024kem().keygen(),
+    ];
+    let message = vec![42; 1000];
+
+    for (level, (sk, pk)) in keys.iter().enumerate() {
+        for aead in [Aead::ChaCha20Poly1305, Aead::Aes256Gcm].iter() {
+            for pt in [&b""[..], b"hello", &message].iter() {
+                let ct = seal_with(*aead, pk, pt, b"aad").unwrap();
+                assert_eq!(&ct[..3], &[VERSION, level as u8 + 1, *aead as u8]);
+                assert_eq!(open(sk, &ct, b"aad").unwrap(), *pt);
+            }
+        }
+    }
+}
+
+#[test]
+fn envelope_rejects_invalid() {
+    let (sk, pk) = kyber768kem().keygen();
+    let (other_sk, _) = kyber768kem().keygen();
+    let (sk512, _) = kyber512kem().keygen();
+    let ct = seal(&pk, b"message", b"aad").unwrap();
+
+    assert!(open(&sk, &ct, b"other").is_err());
+    assert!(open(&other_sk, &ct, b"aad").is_err());
+    assert!(open(&sk512, &ct, b"aad").is_err());
+    assert!(open(&sk, &ct[..ct.len() - 1], b"aad").is_err());
+    assert!(open(&sk, &ct[..20], b"aad").is_err());
+    assert!(open(&sk, &[], b"aad").is_err());
+
+    // Every header byte is authenticated or checked
+    for (pos, value) in [(0, 2), (1, 1), (1, 9), (2, 2), (2, 9)].iter() {
+        let mut tampered = ct.clone();
+        tampered[*pos] = *value;
+        assert!(open(&sk, &tampered, b"aad").is_err());
+    }
+    let mut tampered = ct.clone();
+    tampered[10] ^= 1;
+    assert!(open(&sk, &tampered, b"aad").is_err());
+
+    assert!(seal(&pk.truncate(100), b"message", b"aad").is_err());
+}
@@ -0,0 +1,272 @@
+//! Security estimator
+//!
+//! Core-SVP cost of the primal and dual attacks on a parameter set, as in the
+//! Kyber specification
+
+use crate::functions::compress::{compress_integer, decompress_integer};
+use crate::params::{Kyber1024, Kyber512, Kyber768, ParameterSet};
+use crate::structures::F3329;
+
+use std::f64::consts::{E, LN_2, PI};
+
+/// Smallest BKZ block size considered
+const MIN_BLOCKSIZE: usize = 50;
+
+/// Cost exponent of sieving in dimension b: classical 0.292 b, quantum 0.265 b
+const CLASSICAL_SVP: f64 = 0.292;
+const QUANTUM_SVP: f64 = 0.265;
+
+/// Number of short vectors produced by sieving in dimension b: (4/3)^(b/2)
+const SIEVE_VECTORS: f64 = 0.2075;
+
+/// Parameters of a (possibly custom) Kyber instance
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instance {
+    /// Degree of the polynomial ring
+    pub n: usize,
+    /// Rank of the module
+    pub k: usize,
+    /// Modulus
+    pub q: usize,
+    /// Noise parameter for keygen secrets and encryption randomness
+    pub eta1: usize,
+    /// Noise parameter for encryption errors
+    pub eta2: usize,
+    /// Compression of `u`
+    pub du: usize,
+    /// Compression of `v`
+    pub dv: usize,
+}
+
+impl Instance {
+    /// Instance of a parameter set
+    pub const fn of<P: ParameterSet>() -> Self {
+        Self {
+            n: P::N,
+            k: P::K,
+            q: F3329::order(),
+            eta1: P::ETA1,
+            eta2: P::ETA2,
+            du: P::DU,
+            dv: P::DV,
+        }
+    }
+}
+
+/// Cost of an attack
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttackCost {
+    /// BKZ block size
+    pub blocksize: usize,
+    /// Number of LWE samples used
+    pub samples: usize,
+    /// Classical bit-security
+    pub classical: f64,
+    /// Quantum bit-security
+    pub quantum: f64,
+}
+
+/// Core-SVP security of an instance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Security {
+    /// Best primal attack
+    pub primal: AttackCost,
+    /// Best dual attack
+    pub dual: AttackCost,
+}
+
+impl Security {
+    /// Classical bit-security (cheapest attack)
+    pub fn classical(&self) -> f64 {
+        self.primal.classical.min(self.dual.classical)
+    }
+
+    /// Quantum bit-security (cheapest attack)
+    pub fn quantum(&self) -> f64 {
+        self.primal.quantum.min(self.dual.quantum)
+    }
+}
+
+/// An LWE instance: dimension n, up to max_m samples, secret and error standard deviations
+struct Lwe {
+    n: usize,
+    max_m: usize,
+    q: f64,
+    sigma_s: f64,
+    sigma_e: f64,
+}
+
+/// ln of the root Hermite factor of BKZ with block size b
+fn ln_delta(b: usize) -> f64 {
+    let b = b as f64;
+    ((PI * b).ln() / b + (b / (2.0 * PI * E)).ln()) / (2.0 * b - 2.0)
+}
+
+/// Variance of the error x - decompress(compress(x, d)) for x uniform modulo q
+fn compression_variance(d: usize, q: usize) -> f64 {
+    // Lossless when 2^d >= q, larger shifts would overflow
+    if d >= usize::BITS as usize || 1 << d >= q {
+        return 0.0;
+    }
+
+    let sum: f64 = (0..q)
+        .map(|x| {
+            let y = decompress_integer(compress_integer(x, d, q), d, q);
+            let mut e = (x + q - y % q) % q;
+            if e > q / 2 {
+                e = q - e;
+            }
+            (e * e) as f64
+        })
+        .sum();
+
+    sum / q as f64
+}
+
+impl Lwe {
+    /// ln of the volume of the (rescaled) primal lattice with m samples
+    fn ln_volume(&self, m: usize) -> f64 {
+        m as f64 * self.q.ln() + self.n as f64 * (self.sigma_e / self.sigma_s).ln()
+    }
+
+    /// Whether BKZ-b recovers the error with m samples
+    fn primal_succeeds(&self, m: usize, b: usize) -> bool {
+        let d = (self.n + m + 1) as f64;
+        let lhs = self.sigma_e.ln() + 0.5 * (b as f64).ln();
+        let rhs = (2.0 * b as f64 - d - 1.0) * ln_delta(b) + self.ln_volume(m) / d;
+        lhs < rhs
+    }
+
+    /// Smallest block size of the primal attack
+    fn primal(&self) -> (usize, usize) {
+        for b in MIN_BLOCKSIZE..=self.n + self.max_m {
+            if let Some(m) = (1..=self.max_m).find(|m| self.primal_succeeds(*m, b)) {
+                return (b, m);
+            }
+        }
+        (self.n + self.max_m, self.max_m)
+    }
+
+    /// log2 of the cost of the dual attack with m samples and BKZ-b
+    fn dual_cost(&self, m: usize, b: usize, svp: f64) -> f64 {
+        let d = (self.n + m) as f64;
+        let ln_l = d * ln_delta(b) + self.n as f64 * self.q.ln() / d;
+        let tau = ln_l.exp() * self.sigma_e.max(self.sigma_s) / self.q;
+        let log2_eps = -2.0 * PI * PI * tau * tau / LN_2;
+        let log2_repeat = (-2.0 * log2_eps - SIEVE_VECTORS * b as f64).max(0.0);
+
+        svp * b as f64 + log2_repeat
+    }
+
+    /// Cheapest dual attack
+    fn dual(&self, svp: f64) -> (usize, usize, f64) {
+        let mut best = (0, 0, f64::INFINITY);
+        for b in MIN_BLOCKSIZE..=self.n + self.max_m {
+            if svp * b as f64 > best.2 {
+                break;
+            }
+            for m in 1..=self.max_m {
+                let cost = self.dual_cost(m, b, svp);
+                if cost < best.2 {
+                    best = (b, m, cost);
+                }
+            }
+        }
+        best
+    }
+
+    fn security(&self) -> Security {
+        let (b, m) = self.primal();
+        let primal = AttackCost {
+            blocksize: b,
+            samples: m,
+            classical: CLASSICAL_SVP * b as f64,
+            quantum: QUANTUM_SVP * b as f64,
+        };
+
+        let (b, m, classical) = self.dual(CLASSICAL_SVP);
+        let (_, _, quantum) = self.dual(QUANTUM_SVP);
+        let dual = AttackCost {
+            blocksize: b,
+            samples: m,
+            classical,
+            quantum,
+        };
+
+        Security { primal, dual }
+    }
+}
+
+/// Estimate the core-SVP security of an instance
+///
+/// Both the key (secret and error drawn with `eta1`) and the ciphertext (secret drawn
+/// with `eta1`, error made of `e1` drawn with `eta2` and the compression error of `u`)
+/// are attacked, the cheapest attack is reported. `dv` only affects the last N samples
+/// of the ciphertext, which are not used.
+pub fn core_svp(instance: &Instance) -> Security {
+    let Instance {
+        n,
+        k,
+        q,
+        eta1,
+        eta2,
+        du,
+        ..
+    } = *instance;
+
+    let sigma1 = (eta1 as f64 / 2.0).sqrt();
+    let key = Lwe {
+        n: k * n,
+        max_m: k * n,
+        q: q as f64,
+        sigma_s: sigma1,
+        sigma_e: sigma1,
+    };
+    let ciphertext = Lwe {
+        n: k * n,
+        max_m: k * n,
+        q: q as f64,
+        sigma_s: sigma1,
+        sigma_e: (eta2 as f64 / 2.0 + compression_variance(du, q)).sqrt(),
+    };
+
+    let (s1, s2) = (key.security(), ciphertext.security());
+    let cheapest = |a: AttackCost, b: AttackCost| if a.classical <= b.classical { a } else { b };
+
+    Security {
+        primal: cheapest(s1.primal, s2.primal),
+        dual: cheapest(s1.dual, s2.dual),
+    }
+}
+
+/// Kyber 512 parameters
+pub const KYBER512: Instance = Instance::of::<Kyber512>();
+
+/// Kyber 768 parameters
+pub const KYBER768: Instance = Instance::of::<Kyber768>();
+
+/// Kyber 1024 parameters
+pub const KYBER1024: Instance = Instance::of::<Kyber1024>();
+
+#[test]
+fn core_svp_presets() {
+    // Values from the specification (classical, quantum): (111, 100), (181, 164), (254, 230)
+    for (instance, classical, quantum) in [
+        (KYBER512, 111.0, 100.0),
+        (KYBER768, 181.0, 164.0),
+        (KYBER1024, 254.0, 230.0),
+    ] {
+        let s = core_svp(&instance);
+        assert!((s.classical() - classical).abs() < 2.0);
+        assert!((s.quantum() - quantum).abs() < 2.0);
+    }
+}
+
+#[test]
+fn compression_variance_bounds() {
+    assert_eq!(compression_variance(12, 3329), 0.0);
+    assert_eq!(compression_variance(64, 3329), 0.0);
+    assert_eq!(compression_variance(1000, 3329), 0.0);
+    assert!(compression_variance(10, 3329) > 0.0);
+    assert!(compression_variance(4, 3329) > compression_variance(10, 3329));
+}
@@ -0,0 +1,99 @@
+//! Bech32
+//!
+//! Bech32 strings of BIP 173, without the 90-character limit (as used by age). Data is
+//! converted between bytes and 5-bit groups with zero padding.
+
+use bech32::primitives::decode::CheckedHrpstring;
+use bech32::{Bech32, Checksum, Hrp};
+
+/// Bech32 checksum without the length limit of BIP 173
+enum Bech32Long {}
+
+impl Checksum for Bech32Long {
+    type MidstateRepr = u32;
+    const CODE_LENGTH: usize = usize::MAX;
+    const CHECKSUM_LENGTH: usize = Bech32::CHECKSUM_LENGTH;
+    const GENERATOR_SH: [u32; 5] = Bech32::GENERATOR_SH;
+    const TARGET_RESIDUE: u32 = Bech32::TARGET_RESIDUE;
+}
+
+/// Encode bytes with a human-readable part, in lower case
+pub fn encode(hrp: &str, data: &[u8]) -> Result<String, String> {
+    let hrp = Hrp::parse(hrp).map_err(|e| format!("invalid bech32 human-readable part: {}", e))?;
+
+    bech32::encode_lower::<Bech32Long>(hrp, data).map_err(|e| e.to_string())
+}
+
+/// Decode a string => (lower case human-readable part, bytes)
+///
+/// Mixed-case strings are rejected.
+pub fn decode(s: &str) -> Result<(String, Vec<u8>), String> {
+    let checked =
+        CheckedHrpstring::new::<Bech32Long>(s).map_err(|e| format!("invalid bech32: {}", e))?;
+    checked
+        .validate_segwit_padding()
+        .map_err(|_| "invalid bech32 padding".to_string())?;
+
+    Ok((checked.hrp().to_lowercase(), checked.byte_iter().collect()))
+}
+
+#[test]
+fn bech32_vectors() {
+    // BIP 173 valid checksums
+    for s in [
+        "A12UEL5L",
+        "a12uel5l",
+        "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+        "?1ezyfcl",
+    ]
+    .iter()
+    {
+        decode(s).unwrap();
+    }
+    assert_eq!(decode("A12UEL5L").unwrap(), ("a".to_string(), vec![]));
+    assert_eq!(encode("a", &[]).unwrap(), "a12uel5l");
+
+    // BIP 173 invalid strings
+    for s in [
+        "\u{20}1nwldj5",
+        "pzry9x0s0muk",
+        "1pzry9x0s0muk",
+        "x1b4n0q5v",
+        "li1dgmt3",
+        "A1G7SGD8",
+        "10a06t8",
+        "1qzzfhee",
+        "a12UEL5L",
+    ]
+    .iter()
+    {
+        assert!(decode(s).is_err(), "{}", s);
+    }
+
+    // age identity with the secret key 42 42 .. 42
+    let (hrp, data) =
+        decode("AGE-SECRET-KEY-1GFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPQ4EGAEX")
+            .unwrap();
+    assert_eq!(hrp, "age-secret-key-");
+    assert_eq!(data, [0x42; 32]);
+}
+
+#[test]
+fn bech32_roundtrip() {
+    for len in [0, 1, 31, 32, 96, 1216].iter() {
+        let data: Vec<u8> = (0..*len).map(|i| (i * 7) as u8).collect();
+        let s = encode("age1kybe", &data).unwrap();
+        assert_eq!(decode(&s).unwrap(), ("age1kybe".to_string(), data.clone()));
+        assert_eq!(
+            decode(&s.to_ascii_uppercase()).unwrap(),
+            ("age1kybe".to_string(), data)
+        );
+
+        // Any single character change is detected
+        let mut altered = s.into_bytes();
+        let last = altered.len() - 1;
+        altered[last] = if altered[last] == b'q' { b'p' } else { b'q' };
+        assert!(decode(std::str::from_utf8(&altered).unwrap()).is_err());
+    }
+    assert!(encode("", &[1]).is_err());
+}
@@ -0,0 +1,543 @@
+//! CMS
+//!
+//! `KEMRecipientInfo` (RFC 9629) for the Kyber algorithms of `formats::keys`, and
+//! `EnvelopedData` (RFC 5652) with a single KEM recipient and AES-256-CBC content encryption.
+//!
+//! The recipient is identified by its subject key identifier, the key-encryption key is
+//! derived with HKDF-SHA256 and the content-encryption key is wrapped with AES-KW, as in
+//! the ML-KEM profile. KDF3, the other key derivation of that profile, is not supported:
+//! recipients using it are skipped.
+//!
+//! The KEM is round 2 Kyber, so the recipients use the private OIDs of `formats::keys`
+//! rather than the ML-KEM OIDs: messages are not interoperable with FIPS 203 implementations.
+
+use crate::formats::der;
+use crate::formats::keys::Algorithm;
+use crate::structures::ByteArray;
+
+use aes::{Aes128, Aes256};
+use aes_kw::Kek;
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use std::convert::TryFrom;
+
+/// id-ori-kem: 1.2.840.113549.1.9.16.13.3
+const OID_ORI_KEM: [u8; 11] = [
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x0d, 0x03,
+];
+/// id-alg-hkdf-with-sha256: 1.2.840.113549.1.9.16.3.28
+const OID_HKDF_SHA256: [u8; 11] = [
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x03, 0x1c,
+];
+/// id-kdf-kdf3: 1.3.133.16.840.9.44.1.2
+const OID_KDF3: [u8; 10] = [0x2b, 0x81, 0x05, 0x10, 0x86, 0x48, 0x09, 0x2c, 0x01, 0x02];
+/// id-aes128-wrap: 2.16.840.1.101.3.4.1.5
+const OID_AES128_WRAP: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x05];
+/// id-aes256-wrap: 2.16.840.1.101.3.4.1.45
+const OID_AES256_WRAP: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2d];
+/// id-aes256-CBC: 2.16.840.1.101.3.4.1.42
+const OID_AES256_CBC: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a];
+/// id-data: 1.2.840.113549.1.7.1
+const OID_DATA: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+/// id-envelopedData: 1.2.840.113549.1.7.3
+const OID_ENVELOPED_DATA: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x03];
+
+/// Size of the content-encryption key
+const CEK_BYTES: usize = 32;
+
+/// Size of the AES-CBC IV
+const IV_BYTES: usize = 16;
+
+/// Key-encryption algorithm wrapping the content-encryption key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyWrap {
+    /// id-aes128-wrap
+    Aes128,
+    /// id-aes256-wrap
+    Aes256,
+}
+
+impl KeyWrap {
+    /// Size of the key-encryption key, the `kekLength`
+    pub fn kek_bytes(self) -> usize {
+        match self {
+            KeyWrap::Aes128 => 16,
+            KeyWrap::Aes256 => 32,
+        }
+    }
+
+    /// `AlgorithmIdentifier` with absent parameters (RFC 3565)
+    fn identifier(self) -> Vec<u8> {
+        let oid = match self {
+            KeyWrap::Aes128 => &OID_AES128_WRAP,
+            KeyWrap::Aes256 => &OID_AES256_WRAP,
+        };
+        der::sequence(&[&der::tlv(der::OID, oid)])
+    }
+
+    fn read_identifier(reader: &mut der::Reader) -> Result<Self, String> {
+        let mut identifier = reader.sequence()?;
+        let wrap = match identifier.read(der::OID)? {
+            oid if oid == OID_AES128_WRAP => KeyWrap::Aes128,
+            oid if oid == OID_AES256_WRAP => KeyWrap::Aes256,
+            _ => return Err("unsupported key wrap algorithm".to_string()),
+        };
+        identifier.finish()?;
+        Ok(wrap)
+    }
+
+    fn wrap(self, kek: &[u8], cek: &[u8]) -> Result<Vec<u8>, String> {
+        let wrapped = match self {
+            KeyWrap::Aes128 => Kek::<Aes128>::try_from(kek).and_then(|k| k.wrap_vec(cek)),
+            KeyWrap::Aes256 => Kek::<Aes256>::try_from(kek).and_then(|k| k.wrap_vec(cek)),
+        };
+        wrapped.map_err(|e| format!("key wrap failed: {}", e))
+    }
+
+    fn unwrap(self, kek: &[u8], wrapped: &[u8]) -> Result<Vec<u8>, String> {
+        let cek = match self {
+            KeyWrap::Aes128 => Kek::<Aes128>::try_from(kek).and_then(|k| k.unwrap_vec(wrapped)),
+            KeyWrap::Aes256 => Kek::<Aes256>::try_from(kek).and_then(|k| k.unwrap_vec(wrapped)),
+        };
+        cek.map_err(|e| format!("key unwrap failed: {}", e))
+    }
+}
+
+/// `KEMRecipientInfo` with a subject key identifier and HKDF-SHA256
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KemRecipientInfo {
+    /// Subject key identifier of the recipient
+    pub rid: Vec<u8>,
+    /// KEM algorithm
+    pub kem: Algorithm,
+    /// KEM ciphertext
+    pub kemct: Vec<u8>,
+    /// Optional user keying material
+    pub ukm: Option<Vec<u8>>,
+    /// Key-encryption algorithm
+    pub wrap: KeyWrap,
+    /// Wrapped content-encryption key
+    pub encrypted_key: Vec<u8>,
+}
+
+/// DER of `CMSORIforKEMOtherInfo`, the info of the key derivation
+fn other_info(wrap: KeyWrap, ukm: Option<&[u8]>) -> Vec<u8> {
+    let ukm = ukm.map(|ukm| {
+        der::tlv(
+            der::context_constructed(0),
+            &der::tlv(der::OCTET_STRING, ukm),
+        )
+    });
+
+    der::sequence(&[
+        &wrap.identifier(),
+        &der::integer(wrap.kek_bytes() as u8),
+        &ukm.unwrap_or_default(),
+    ])
+}
+
+/// Key-encryption key: HKDF-SHA256 of the shared secret with an empty salt
+fn derive_kek(ss: &ByteArray, wrap: KeyWrap, ukm: Option<&[u8]>) -> Vec<u8> {
+    let mut kek = vec![0; wrap.kek_bytes()];
+    // Unwraps safely since the output is at most 32 bytes
+    Hkdf::<Sha256>::new(Some(&[]), &ss.data)
+        .expand(&other_info(wrap, ukm), &mut kek)
+        .unwrap();
+
+    kek
+}
+
+impl KemRecipientInfo {
+    /// Encapsulate to the recipient public key `pk` and wrap the content-encryption key `cek`
+    pub fn encaps(
+        kem: Algorithm,
+        pk: &ByteArray,
+        rid: &[u8],
+        cek: &[u8],
+        wrap: KeyWrap,
+        ukm: Option<&[u8]>,
+    ) -> Result<Self, String> {
+        let (c, ss) = kem.encaps(pk)?;
+        let kek = derive_kek(&ss, wrap, ukm);
+        let encrypted_key = wrap.wrap(&kek, cek)?;
+
+        Ok(Self {
+            rid: rid.to_vec(),
+            kem,
+            kemct: c.data,
+            ukm: ukm.map(|ukm| ukm.to_vec()),
+            wrap,
+            encrypted_key,
+        })
+    }
+
+    /// Recover the content-encryption key with the expanded secret key `sk`
+    pub fn decaps(&self, sk: &ByteArray) -> Result<Vec<u8>, String> {
+        let c = ByteArray::from_bytes(&self.kemct);
+        let ss = self.kem.decaps(&c, sk)?;
+        let kek = derive_kek(&ss, self.wrap, self.ukm.as_deref());
+
+        self.wrap.unwrap(&kek, &self.encrypted_key)
+    }
+
+    /// DER encoding of the `KEMRecipientInfo`
+    pub fn to_der(&self) -> Vec<u8> {
+        let ukm = self.ukm.as_ref().map(|ukm| {
+            der::tlv(
+                der::context_constructed(0),
+                &der::tlv(der::OCTET_STRING, ukm),
+            )
+        });
+
+        der::sequence(&[
+            &der::integer(0),
+            &der::tlv(der::context(0), &self.rid),
+            &self.kem.identifier(),
+            &der::tlv(der::OCTET_STRING, &self.kemct),
+            &der::sequence(&[&der::tlv(der::OID, &OID_HKDF_SHA256)]),
+            &der::integer(self.wrap.kek_bytes() as u8),
+            &ukm.unwrap_or_default(),
+            &self.wrap.identifier(),
+            &der::tlv(der::OCTET_STRING, &self.encrypted_key),
+        ])
+    }
+
+    /// Decode a DER `KEMRecipientInfo`
+    pub fn from_der(data: &[u8]) -> Result<Self, String> {
+        let mut outer = der::Reader::new(data);
+        let mut reader = outer.sequence()?;
+        outer.finish()?;
+
+        if reader.integer()? != 0 {
+            return Err("unsupported KEMRecipientInfo version".to_string());
+        }
+        if reader.peek_tag() != Some(der::context(0)) {
+            return Err("only subject key identifiers are supported".to_string());
+        }
+        let rid = reader.read(der::context(0))?.to_vec();
+        let kem = Algorithm::read_identifier(&mut reader)?;
+        let kemct = reader.read(der::OCTET_STRING)?.to_vec();
+        if kemct.len() != kem.ct_bytes() {
+            return Err("invalid KEM ciphertext size".to_string());
+        }
+
+        let mut kdf = reader.sequence()?;
+        match kdf.read(der::OID)? {
+            oid if oid == OID_HKDF_SHA256 => kdf.finish()?,
+            oid if oid == OID_KDF3 => {
+                return Err("KDF3 is not supported, only HKDF-SHA256".to_string())
+            }
+            _ => return Err("unsupported key derivation algorithm".to_string()),
+        }
+        let kek_length = reader.integer()? as usize;
+
+        let ukm = if reader.peek_tag() == Some(der::context_constructed(0)) {
+            let mut explicit = der::Reader::new(reader.read(der::context_constructed(0))?);
+            let ukm = explicit.read(der::OCTET_STRING)?.to_vec();
+            explicit.finish()?;
+            Some(ukm)
+        } else {
+            None
+        };
+
+        let wrap = KeyWrap::read_identifier(&mut reader)?;
+        if kek_length != wrap.kek_bytes() {
+            return Err("kekLength does not match the key wrap algorithm".to_string());
+        }
+        let encrypted_key = reader.read(der::OCTET_STRING)?.to_vec();
+        reader.finish()?;
+
+        Ok(Self {
+            rid,
+            kem,
+            kemct,
+            ukm,
+            wrap,
+            encrypted_key,
+        })
+    }
+}
+
+/// Encrypt `content` for the recipient `pk` => DER `ContentInfo` of an `EnvelopedData`
+pub fn encrypt(
+    kem: Algorithm,
+    pk: &ByteArray,
+    rid: &[u8],
+    content: &[u8],
+) -> Result<Vec<u8>, String> {
+    let cek = ByteArray::random(CEK_BYTES);
+    let iv = ByteArray::random(IV_BYTES);
+    let recipient = KemRecipientInfo::encaps(kem, pk, rid, &cek.data, KeyWrap::Aes256, None)?;
+
+    // Unwraps safely since the key and IV have the right sizes
+    let encrypted = cbc::Encryptor::<Aes256>::new_from_slices(&cek.data, &iv.data)
+        .unwrap()
+        .encrypt_padded_vec_mut::<Pkcs7>(content);
+
+    // RecipientInfo ori [4] IMPLICIT OtherRecipientInfo
+    let ori = der::tlv(
+        der::context_constructed(4),
+        &[der::tlv(der::OID, &OID_ORI_KEM), recipient.to_der()].concat(),
+    );
+    let encrypted_content_info = der::sequence(&[
+        &der::tlv(der::OID, &OID_DATA),
+        &der::sequence(&[
+            &der::tlv(der::OID, &OID_AES256_CBC),
+            &der::tlv(der::OCTET_STRING, &iv.data),
+        ]),
+        &der::tlv(der::context(0), &encrypted),
+    ]);
+    // The version is 3 with other recipient infos
+    let enveloped_data = der::sequence(&[
+        &der::integer(3),
+        &der::tlv(der::SET, &ori),
+        &encrypted_content_info,
+    ]);
+
+    Ok(der::sequence(&[
+        &der::tlv(der::OID, &OID_ENVELOPED_DATA),
+        &der::tlv(der::context_constructed(0), &enveloped_data),
+    ]))
+}
+
+/// Decode an `OtherRecipientInfo` => its `KEMRecipientInfo`, if it is one
+fn kem_recipient(ori: &[u8]) -> Result<Option<KemRecipientInfo>, String> {
+    let mut ori = der::Reader::new(ori);
+    if ori.read(der::OID)? != OID_ORI_KEM {
+        return Ok(None);
+    }
+    let recipient = KemRecipientInfo::from_der(&der::sequence(&[ori.read(der::SEQUENCE)?]))?;
+    ori.finish()?;
+
+    Ok(Some(recipient))
+}
+
+/// Decrypt a DER `ContentInfo` of an `EnvelopedData` with the recipient identified by
+/// `rid` and its expanded secret key `sk`
+pub fn decrypt(sk: &ByteArray, rid: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut outer = der::Reader::new(data);
+    let mut content_info = outer.sequence()?;
+    outer.finish()?;
+    if content_info.read(der::OID)? != OID_ENVELOPED_DATA {
+        return Err("not an EnvelopedData".to_string());
+    }
+    let mut explicit = der::Reader::new(content_info.read(der::context_constructed(0))?);
+    content_info.finish()?;
+    let mut enveloped_data = explicit.sequence()?;
+    explicit.finish()?;
+
+    if enveloped_data.integer()? != 3 {
+        return Err("unsupported EnvelopedData version".to_string());
+    }
+    let mut recipients = der::Reader::new(enveloped_data.read(der::SET)?);
+    let mut cek = None;
+    while recipients.peek_tag().is_some() {
+        if recipients.peek_tag() != Some(der::context_constructed(4)) {
+            // Skip the other kinds of recipients
+            let tag = recipients.peek_tag().unwrap_or_default();
+            recipients.read(tag)?;
+            continue;
+        }
+        let ori = recipients.read(der::context_constructed(4))?;
+        // Skip the recipients that do not parse, they may be for another implementation
+        if let Ok(Some(recipient)) = kem_recipient(ori) {
+            if recipient.rid == rid {
+                cek = Some(recipient.decaps(sk)?);
+                break;
+            }
+        }
+    }
+    let cek = cek.ok_or("no KEM recipient matches the key identifier")?;
+
+    let mut encrypted_content_info = enveloped_data.sequence()?;
+    if encrypted_content_info.read(der::OID)? != OID_DATA {
+        return Err("unsupported content type".to_string());
+    }
+    let mut algorithm = encrypted_content_info.sequence()?;
+    if algorithm.read(der::OID)? != OID_AES256_CBC {
+        return Err("unsupported content encryption algorithm".to_string());
+    }
+    let iv = algorithm.read(der::OCTET_STRING)?;
+    algorithm.finish()?;
+    let encrypted = encrypted_content_info.read(der::context(0))?;
+    encrypted_content_info.finish()?;
+
+    let decryptor = cbc::Decryptor::<Aes256>::new_from_slices(&cek, iv)
+        .map_err(|_| "invalid content-encryption key or IV".to_string())?;
+    decryptor
+        .decrypt_padded_vec_mut::<Pkcs7>(encrypted)
+        .map_err(|_| "content decryption failed".to_string())
+}
+
+#[test]
+fn cms_key_wrap_rfc3394() {
+    use crate::formats::hex;
+
+    let kek: Vec<u8> = (0..32).collect();
+    let cek = hex::decode("00112233445566778899aabbccddeeff").unwrap();
+
+    // Sections 4.1 and 4.3
+    let wrapped = KeyWrap::Aes128.wrap(&kek[..16], &cek).unwrap();
+    assert_eq!(
+        hex::encode(&wrapped),
+        "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5"
+    );
+    assert_eq!(KeyWrap::Aes128.unwrap(&kek[..16], &wrapped).unwrap(), cek);
+    let wrapped = KeyWrap::Aes256.wrap(&kek, &cek).unwrap();
+    assert_eq!(
+        hex::encode(&wrapped),
+        "64e8c3f9ce0f5ba263e9777905818a2a93c8191e7d6e8ae7"
+    );
+
+    let mut tampered = wrapped.clone();
+    tampered[0] ^= 1;
+    assert!(KeyWrap::Aes256.unwrap(&kek, &tampered).is_err());
+    assert!(KeyWrap::Aes256.unwrap(&kek[..16], &wrapped).is_err());
+}
+
+#[test]
+fn cms_kem_recipient_info() {
+    let cek = [7; CEK_BYTES];
+
+    for kem in [
+        Algorithm::Kyber512,
+        Algorithm::Kyber768,
+        Algorithm::Kyber1024,
+    ]
+    .iter()
+    {
+        let (sk, pk) = kem.expand_seed(&ByteArray::random(64)).unwrap();
+        for (wrap, ukm) in [
+            (KeyWrap::Aes128, None),
+            (KeyWrap::Aes256, Some(&b"ukm"[..])),
+        ]
+        .iter()
+        {
+            let ri = KemRecipientInfo::encaps(*kem, &pk, b"ski", &cek, *wrap, *ukm).unwrap();
+            assert_eq!(ri.kemct.len(), kem.ct_bytes());
+            assert_eq!(ri.encrypted_key.len(), CEK_BYTES + 8);
+
+            let decoded = KemRecipientInfo::from_der(&ri.to_der()).unwrap();
+            assert_eq!(decoded, ri);
+            assert_eq!(decoded.decaps(&sk).unwrap(), cek);
+        }
+    }
+
+    // The user keying material is bound to the key-encryption key
+    let kem = Algorithm::Kyber768;
+    let (sk, pk) = kem.expand_seed(&ByteArray::random(64)).unwrap();
+    let mut ri =
+        KemRecipientInfo::encaps(kem, &pk, b"ski", &cek, KeyWrap::Aes256, Some(b"a")).unwrap();
+    ri.ukm = Some(b"b".to_vec());
+    assert!(ri.decaps(&sk).is_err());
+
+    // CMSORIforKEMOtherInfo
+    assert_eq!(
+        other_info(KeyWrap::Aes128, Some(b"u")),
+        [
+            &[0x30, 0x15, 0x30, 0x0b, 0x06, 0x09][..],
+            &OID_AES128_WRAP,
+            &[0x02, 0x01, 0x10, 0xa0, 0x03, 0x04, 0x01, b'u'],
+        ]
+        .concat()
+    );
+
+    // Inconsistent kekLength
+    let mut der = KemRecipientInfo::encaps(kem, &pk, b"ski", &cek, KeyWrap::Aes256, None)
+        .unwrap()
+        .to_der();
+    let pos = der
+        .windows(3)
+        .rposition(|w| w == [0x02, 0x01, 0x20])
+        .unwrap();
+    der[pos + 2] = 0x10;
+    assert!(KemRecipientInfo::from_der(&der).is_err());
+
+    // KDF3 with SHA-256
+    let der = KemRecipientInfo::encaps(kem, &pk, b"ski", &cek, KeyWrap::Aes256, None)
+        .unwrap()
+        .to_der();
+    let hkdf = der::sequence(&[&der::tlv(der::OID, &OID_HKDF_SHA256)]);
+    let sha256 = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+    let kdf3 = der::sequence(&[
+        &der::tlv(der::OID, &OID_KDF3),
+        &der::sequence(&[&der::tlv(der::OID, &sha256)]),
+    ]);
+    let pos = der.windows(hkdf.len()).position(|w| w == hkdf).unwrap();
+    // Content of the outer SEQUENCE, with a two-byte length
+    let content = [&der[4..pos], &kdf3[..], &der[pos + hkdf.len()..]].concat();
+    assert_eq!(
+        KemRecipientInfo::from_der(&der::sequence(&[&content])).unwrap_err(),
+        "KDF3 is not supported, only HKDF-SHA256"
+    );
+}
+
+#[test]
+fn cms_enveloped_data() {
+    let content = b"Attack at dawn, with a message longer than one AES block";
+
+    for kem in [
+        Algorithm::Kyber512,
+        Algorithm::Kyber768,
+        Algorithm::Kyber1024,
+    ]
+    .iter()
+    {
+        let (sk, pk) = kem.expand_seed(&ByteArray::random(64)).unwrap();
+        let der = encrypt(*kem, &pk, b"recipient", content).unwrap();
+        assert_eq!(decrypt(&sk, b"recipient", &der).unwrap(), &content[..]);
+    }
+
+    let kem = Algorithm::Kyber768;
+    let (sk, pk) = kem.expand_seed(&ByteArray::random(64)).unwrap();
+    let (other_sk, _) = kem.expand_seed(&ByteArray::random(64)).unwrap();
+    let der = encrypt(kem, &pk, b"recipient", b"").unwrap();
+    assert_eq!(decrypt(&sk, b"recipient", &der).unwrap(), b"");
+
+    // ContentInfo with the EnvelopedData OID, and the KEM recipient as an ORI
+    assert_eq!(der[0], der::SEQUENCE);
+    assert!(der
+        .windows(OID_ENVELOPED_DATA.len())
+        .any(|w| w == OID_ENVELOPED_DATA));
+    assert!(der.windows(OID_ORI_KEM.len()).any(|w| w == OID_ORI_KEM));
+
+    assert!(decrypt(&sk, b"someone else", &der).is_err());
+    assert!(decrypt(&other_sk, b"recipient", &der).is_err());
+    assert!(decrypt(&sk, b"recipient", &der[..der.len() - 1]).is_err());
+    // Flipping a byte of the IV corrupts the padding of the single block
+    let mut tampered = der.clone();
+    let last = tampered.len() - 20;
+    tampered[last] ^= 1;
+    assert!(decrypt(&sk, b"recipient", &tampered).is_err());
+
+    // A malformed KEM recipient before ours is skipped
+    let mut outer = der::Reader::new(&der);
+    let mut content_info = outer.sequence().unwrap();
+    content_info.read(der::OID).unwrap();
+    let mut explicit = der::Reader::new(content_info.read(der::context_constructed(0)).unwrap());
+    let mut enveloped_data = explicit.sequence().unwrap();
+    enveloped_data.integer().unwrap();
+    let ori = enveloped_data.read(der::SET).unwrap();
+    let encrypted_content_info = enveloped_data.read(der::SEQUENCE).unwrap();
+    let malformed = der::tlv(
+        der::context_constructed(4),
+        &[
+            der::tlv(der::OID, &OID_ORI_KEM),
+            der::sequence(&[&der::integer(1)]),
+        ]
+        .concat(),
+    );
+    let enveloped_data = der::sequence(&[
+        &der::integer(3),
+        &der::tlv(der::SET, &[&malformed[..], ori].concat()),
+        &der::tlv(der::SEQUENCE, encrypted_content_info),
+    ]);
+    let der = der::sequence(&[
+        &der::tlv(der::OID, &OID_ENVELOPED_DATA),
+        &der::tlv(der::context_constructed(0), &enveloped_data),
+    ]);
+    assert_eq!(decrypt(&sk, b"recipient", &der).unwrap(), b"");
+}
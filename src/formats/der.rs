@@ -0,0 +1,183 @@
+//! DER
+//!
+//! Minimal DER reader and writer for the ASN.1 structures used by the key formats
+
+/// SEQUENCE tag
+pub const SEQUENCE: u8 = 0x30;
+/// SET tag
+pub const SET: u8 = 0x31;
+/// INTEGER tag
+pub const INTEGER: u8 = 0x02;
+/// BIT STRING tag
+pub const BIT_STRING: u8 = 0x03;
+/// OCTET STRING tag
+pub const OCTET_STRING: u8 = 0x04;
+/// OBJECT IDENTIFIER tag
+pub const OID: u8 = 0x06;
+
+/// Context-specific primitive tag `[n] IMPLICIT`
+pub const fn context(n: u8) -> u8 {
+    0x80 | n
+}
+
+/// Context-specific constructed tag `[n]`
+pub const fn context_constructed(n: u8) -> u8 {
+    0xa0 | n
+}
+
+/// Encode a TLV with the given tag and content
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let len = content.len();
+    let mut out = vec![tag];
+
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len
+            .to_be_bytes()
+            .iter()
+            .copied()
+            .skip_while(|b| *b == 0)
+            .collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend_from_slice(&bytes);
+    }
+    out.extend_from_slice(content);
+
+    out
+}
+
+/// Encode a SEQUENCE of already encoded elements
+pub fn sequence(items: &[&[u8]]) -> Vec<u8> {
+    tlv(SEQUENCE, &items.concat())
+}
+
+/// Encode a small non-negative INTEGER
+pub fn integer(value: u8) -> Vec<u8> {
+    if value < 0x80 {
+        tlv(INTEGER, &[value])
+    } else {
+        tlv(INTEGER, &[0, value])
+    }
+}
+
+/// Encode a BIT STRING without unused bits
+pub fn bit_string(data: &[u8]) -> Vec<u8> {
+    tlv(BIT_STRING, &[&[0], data].concat())
+}
+
+/// Sequential DER reader
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Start reading a buffer
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Tag of the next element, if any
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Read the next element, which must have the given tag, and return its content
+    pub fn read(&mut self, tag: u8) -> Result<&'a [u8], String> {
+        let (&t, rest) = self.data.split_first().ok_or("unexpected end of DER")?;
+        if t != tag {
+            return Err(format!("expected DER tag {:#04x}, found {:#04x}", tag, t));
+        }
+
+        let (&first, rest) = rest.split_first().ok_or("truncated DER length")?;
+        let (len, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > std::mem::size_of::<usize>() || rest.len() < n {
+                return Err("invalid DER length".to_string());
+            }
+            let len = rest[..n].iter().fold(0, |acc, b| (acc << 8) | *b as usize);
+            // DER requires the minimal encoding
+            if rest[0] == 0 || len < 0x80 {
+                return Err("non-minimal DER length".to_string());
+            }
+            (len, &rest[n..])
+        };
+
+        if rest.len() < len {
+            return Err("truncated DER content".to_string());
+        }
+        let (content, rest) = rest.split_at(len);
+        self.data = rest;
+
+        Ok(content)
+    }
+
+    /// Read a SEQUENCE and return a reader on its content
+    pub fn sequence(&mut self) -> Result<Reader<'a>, String> {
+        Ok(Reader::new(self.read(SEQUENCE)?))
+    }
+
+    /// Read a small non-negative INTEGER
+    pub fn integer(&mut self) -> Result<u8, String> {
+        match self.read(INTEGER)? {
+            [v] if *v < 0x80 => Ok(*v),
+            [0, v] if *v >= 0x80 => Ok(*v),
+            _ => Err("unsupported DER integer".to_string()),
+        }
+    }
+
+    /// Read a BIT STRING without unused bits
+    pub fn bit_string(&mut self) -> Result<&'a [u8], String> {
+        match self.read(BIT_STRING)?.split_first() {
+            Some((0, data)) => Ok(data),
+            _ => Err("invalid DER bit string".to_string()),
+        }
+    }
+
+    /// Check that all the data has been read
+    pub fn finish(&self) -> Result<(), String> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err("trailing DER data".to_string())
+        }
+    }
+}
+
+#[test]
+fn der_lengths() {
+    for len in [0, 1, 127, 128, 255, 256, 4000].iter() {
+        let content = vec![7; *len];
+        let encoded = tlv(OCTET_STRING, &content);
+
+        let mut reader = Reader::new(&encoded);
+        assert_eq!(reader.read(OCTET_STRING).unwrap(), &content[..]);
+        reader.finish().unwrap();
+    }
+}
+
+#[test]
+fn der_malformed() {
+    // Wrong tag
+    assert!(Reader::new(&[0x04, 0x00]).read(SEQUENCE).is_err());
+    // Truncated
+    assert!(Reader::new(&[0x04, 0x02, 0x00]).read(OCTET_STRING).is_err());
+    assert!(Reader::new(&[0x04]).read(OCTET_STRING).is_err());
+    // Non-minimal lengths
+    assert!(Reader::new(&[0x04, 0x81, 0x01, 0x00])
+        .read(OCTET_STRING)
+        .is_err());
+    assert!(Reader::new(&[0x04, 0x82, 0x00, 0x80])
+        .read(OCTET_STRING)
+        .is_err());
+    // Indefinite length
+    assert!(Reader::new(&[0x04, 0x80]).read(OCTET_STRING).is_err());
+    // Trailing data
+    let mut reader = Reader::new(&[0x04, 0x00, 0x00]);
+    reader.read(OCTET_STRING).unwrap();
+    assert!(reader.finish().is_err());
+    // Unused bits
+    assert!(Reader::new(&[0x03, 0x02, 0x01, 0x00]).bit_string().is_err());
+}
@@ -0,0 +1,37 @@
+//! Hex
+//!
+//! Lowercase hexadecimal encoding
+
+/// Encode bytes as a lowercase hex string
+pub fn encode(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode a hex string, ignoring whitespace
+pub fn decode(s: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = s
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|c| match c {
+            b'0'..=b'9' => Ok(c - b'0'),
+            b'a'..=b'f' => Ok(c - b'a' + 10),
+            b'A'..=b'F' => Ok(c - b'A' + 10),
+            _ => Err(format!("invalid hex character {:?}", c as char)),
+        })
+        .collect::<Result<_, _>>()?;
+
+    if !digits.len().is_multiple_of(2) {
+        return Err("odd number of hex digits".to_string());
+    }
+
+    Ok(digits.chunks(2).map(|d| (d[0] << 4) | d[1]).collect())
+}
+
+#[test]
+fn hex_roundtrip() {
+    let data = [0x00, 0x01, 0xab, 0xff];
+    assert_eq!(encode(&data), "0001abff");
+    assert_eq!(decode("0001ABff\n").unwrap(), data);
+    assert!(decode("abc").is_err());
+    assert!(decode("zz").is_err());
+}
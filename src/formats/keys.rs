@@ -0,0 +1,505 @@
+//! Keys
+//!
+//! X.509 `SubjectPublicKeyInfo` and PKCS#8 `OneAsymmetricKey` encodings, following the
+//! IETF LAMPS draft for ML-KEM in certificates.
+//!
+//! The keys have the ML-KEM sizes but `kybe-rs` follows the round 2 Kyber specification,
+//! they are not interoperable with FIPS 203 implementations. So that X.509 and PKCS#8
+//! consumers do not take them for ML-KEM keys, the algorithms use a private OID arc,
+//! 2.25.315166321387561224851792872877338912351 (a UUID OID, ITU-T X.667), instead of the
+//! `id-alg-ml-kem-*` OIDs, which are rejected.
+
+use crate::formats::{der, pem};
+use crate::kem::SEED_BYTES;
+use crate::params::{Kyber1024, Kyber512, Kyber768, ParameterSet};
+use crate::structures::ByteArray;
+use crate::validate::validate_public_key;
+use crate::{kyber1024kem, kyber512kem, kyber768kem};
+
+/// PEM label of a `SubjectPublicKeyInfo`
+pub const PUBLIC_KEY_LABEL: &str = "PUBLIC KEY";
+
+/// PEM label of a PKCS#8 private key
+pub const PRIVATE_KEY_LABEL: &str = "PRIVATE KEY";
+
+/// DER encoding of the private OID arc: 2.25.315166321387561224851792872877338912351
+const OID_PREFIX: [u8; 20] = [
+    0x69, 0x83, 0xda, 0x9a, 0xe9, 0xd6, 0xab, 0xc0, 0xba, 0x9f, 0xe9, 0x89, 0xf2, 0xd0, 0xd7, 0xd6,
+    0xa1, 0xff, 0xcc, 0x5f,
+];
+
+/// DER encoding of the `id-alg-ml-kem-*` OIDs prefix: 2.16.840.1.101.3.4.4
+const OID_ML_KEM_PREFIX: [u8; 8] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x04];
+
+/// Kyber algorithms, with the ML-KEM sizes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Kyber 512 parameters, arc 1
+    Kyber512,
+    /// Kyber 768 parameters, arc 2
+    Kyber768,
+    /// Kyber 1024 parameters, arc 3
+    Kyber1024,
+}
+
+impl Algorithm {
+    /// Content of the DER encoded OID
+    pub fn oid(self) -> [u8; 21] {
+        let mut oid = [0; 21];
+        oid[..20].copy_from_slice(&OID_PREFIX);
+        oid[20] = match self {
+            Algorithm::Kyber512 => 1,
+            Algorithm::Kyber768 => 2,
+            Algorithm::Kyber1024 => 3,
+        };
+        oid
+    }
+
+    /// Algorithm of a DER encoded OID
+    pub fn from_oid(oid: &[u8]) -> Result<Self, String> {
+        match oid.split_last() {
+            Some((1, prefix)) if prefix == OID_PREFIX => Ok(Algorithm::Kyber512),
+            Some((2, prefix)) if prefix == OID_PREFIX => Ok(Algorithm::Kyber768),
+            Some((3, prefix)) if prefix == OID_PREFIX => Ok(Algorithm::Kyber1024),
+            Some((_, prefix)) if prefix == OID_ML_KEM_PREFIX => {
+                Err("ML-KEM (FIPS 203) keys are not supported, only round 2 Kyber".to_string())
+            }
+            _ => Err("unsupported algorithm OID".to_string()),
+        }
+    }
+
+    /// Size of the public key
+    pub fn pk_bytes(self) -> usize {
+        match self {
+            Algorithm::Kyber512 => Kyber512::PK_BYTES,
+            Algorithm::Kyber768 => Kyber768::PK_BYTES,
+            Algorithm::Kyber1024 => Kyber1024::PK_BYTES,
+        }
+    }
+
+    /// Size of the expanded secret key
+    pub fn sk_bytes(self) -> usize {
+        match self {
+            Algorithm::Kyber512 => Kyber512::SK_BYTES,
+            Algorithm::Kyber768 => Kyber768::SK_BYTES,
+            Algorithm::Kyber1024 => Kyber1024::SK_BYTES,
+        }
+    }
+
+    /// Size of a ciphertext
+    pub fn ct_bytes(self) -> usize {
+        match self {
+            Algorithm::Kyber512 => Kyber512::CT_BYTES,
+            Algorithm::Kyber768 => Kyber768::CT_BYTES,
+            Algorithm::Kyber1024 => Kyber1024::CT_BYTES,
+        }
+    }
+
+    /// Encapsulation to a public key => (ciphertext, shared secret)
+    pub fn encaps(self, pk: &ByteArray) -> Result<(ByteArray, ByteArray), String> {
+        match self {
+            Algorithm::Kyber512 => kyber512kem().encaps_checked(pk),
+            Algorithm::Kyber768 => kyber768kem().encaps_checked(pk),
+            Algorithm::Kyber1024 => kyber1024kem().encaps_checked(pk),
+        }
+    }
+
+    /// Deterministic encapsulation with a 32-byte message `m` => (ciphertext, shared secret)
+    pub fn encaps_derand(
+        self,
+        pk: &ByteArray,
+        m: &ByteArray,
+    ) -> Result<(ByteArray, ByteArray), String> {
+        if m.data.len() != 32 {
+            return Err("encapsulation randomness must be 32 bytes".to_string());
+        }
+        match self {
+            Algorithm::Kyber512 => {
+                validate_public_key::<Kyber512>(pk)?;
+                Ok(kyber512kem().encaps_derand(pk, m))
+            }
+            Algorithm::Kyber768 => {
+                validate_public_key::<Kyber768>(pk)?;
+                Ok(kyber768kem().encaps_derand(pk, m))
+            }
+            Algorithm::Kyber1024 => {
+                validate_public_key::<Kyber1024>(pk)?;
+                Ok(kyber1024kem().encaps_derand(pk, m))
+            }
+        }
+    }
+
+    /// Decapsulation of a ciphertext with an expanded secret key => shared secret
+    pub fn decaps(self, c: &ByteArray, sk: &ByteArray) -> Result<ByteArray, String> {
+        match self {
+            Algorithm::Kyber512 => kyber512kem().decaps_checked(c, sk),
+            Algorithm::Kyber768 => kyber768kem().decaps_checked(c, sk),
+            Algorithm::Kyber1024 => kyber1024kem().decaps_checked(c, sk),
+        }
+    }
+
+    /// Expand a 64-byte seed `d || z` into (secret key, public key)
+    pub fn expand_seed(self, seed: &ByteArray) -> Result<(ByteArray, ByteArray), String> {
+        match self {
+            Algorithm::Kyber512 => kyber512kem().expand_seed(seed),
+            Algorithm::Kyber768 => kyber768kem().expand_seed(seed),
+            Algorithm::Kyber1024 => kyber1024kem().expand_seed(seed),
+        }
+    }
+
+    /// `AlgorithmIdentifier` with absent parameters
+    pub(crate) fn identifier(self) -> Vec<u8> {
+        der::sequence(&[&der::tlv(der::OID, &self.oid())])
+    }
+
+    /// Read an `AlgorithmIdentifier` with absent parameters
+    pub(crate) fn read_identifier(reader: &mut der::Reader) -> Result<Self, String> {
+        let mut identifier = reader.sequence()?;
+        let algorithm = Self::from_oid(identifier.read(der::OID)?)?;
+        // Parameters must be absent
+        identifier.finish()?;
+        Ok(algorithm)
+    }
+}
+
+/// ML-KEM private key, as the `ML-KEM-PrivateKey` CHOICE of the draft
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PrivateKey {
+    /// 64-byte seed `d || z`
+    Seed(ByteArray),
+    /// Expanded decapsulation key `sk' || pk || H(pk) || z`
+    Expanded(ByteArray),
+    /// Both the seed and the corresponding expanded key
+    Both {
+        /// 64-byte seed `d || z`
+        seed: ByteArray,
+        /// Expanded decapsulation key
+        expanded: ByteArray,
+    },
+}
+
+impl PrivateKey {
+    /// Expanded decapsulation key, deriving it from the seed if needed
+    ///
+    /// When both are present, they must be consistent.
+    pub fn expanded(&self, algorithm: Algorithm) -> Result<ByteArray, String> {
+        match self {
+            PrivateKey::Seed(seed) => Ok(algorithm.expand_seed(seed)?.0),
+            PrivateKey::Expanded(expanded) => {
+                check_len(expanded, algorithm.sk_bytes(), "expanded key")?;
+                Ok(expanded.clone())
+            }
+            PrivateKey::Both { seed, expanded } => {
+                let (sk, _) = algorithm.expand_seed(seed)?;
+                if sk != *expanded {
+                    return Err("seed and expanded key do not match".to_string());
+                }
+                Ok(sk)
+            }
+        }
+    }
+
+    fn check(&self, algorithm: Algorithm) -> Result<(), String> {
+        match self {
+            PrivateKey::Seed(seed) => check_len(seed, SEED_BYTES, "seed"),
+            PrivateKey::Expanded(expanded) => {
+                check_len(expanded, algorithm.sk_bytes(), "expanded key")
+            }
+            PrivateKey::Both { .. } => self.expanded(algorithm).map(|_| ()),
+        }
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        match self {
+            PrivateKey::Seed(seed) => der::tlv(der::context(0), &seed.data),
+            PrivateKey::Expanded(expanded) => der::tlv(der::OCTET_STRING, &expanded.data),
+            PrivateKey::Both { seed, expanded } => der::sequence(&[
+                &der::tlv(der::OCTET_STRING, &seed.data),
+                &der::tlv(der::OCTET_STRING, &expanded.data),
+            ]),
+        }
+    }
+
+    fn from_der(data: &[u8]) -> Result<Self, String> {
+        let mut reader = der::Reader::new(data);
+        let key = match reader.peek_tag() {
+            Some(tag) if tag == der::context(0) => {
+                PrivateKey::Seed(ByteArray::from_bytes(reader.read(tag)?))
+            }
+            Some(der::OCTET_STRING) => {
+                PrivateKey::Expanded(ByteArray::from_bytes(reader.read(der::OCTET_STRING)?))
+            }
+            Some(der::SEQUENCE) => {
+                let mut both = reader.sequence()?;
+                let seed = ByteArray::from_bytes(both.read(der::OCTET_STRING)?);
+                let expanded = ByteArray::from_bytes(both.read(der::OCTET_STRING)?);
+                both.finish()?;
+                PrivateKey::Both { seed, expanded }
+            }
+            _ => return Err("invalid private key".to_string()),
+        };
+        reader.finish()?;
+
+        Ok(key)
+    }
+}
+
+fn check_len(data: &ByteArray, expected: usize, what: &str) -> Result<(), String> {
+    if data.data.len() != expected {
+        return Err(format!(
+            "{} has {} bytes, expected {}",
+            what,
+            data.data.len(),
+            expected
+        ));
+    }
+    Ok(())
+}
+
+/// DER encoding of a `SubjectPublicKeyInfo`
+pub fn encode_public_key(algorithm: Algorithm, pk: &ByteArray) -> Result<Vec<u8>, String> {
+    check_len(pk, algorithm.pk_bytes(), "public key")?;
+
+    Ok(der::sequence(&[
+        &algorithm.identifier(),
+        &der::bit_string(&pk.data),
+    ]))
+}
+
+/// Decode a DER `SubjectPublicKeyInfo` => (algorithm, public key)
+pub fn decode_public_key(data: &[u8]) -> Result<(Algorithm, ByteArray), String> {
+    let mut reader = der::Reader::new(data);
+    let mut spki = reader.sequence()?;
+    reader.finish()?;
+
+    let algorithm = Algorithm::read_identifier(&mut spki)?;
+    let pk = ByteArray::from_bytes(spki.bit_string()?);
+    spki.finish()?;
+    check_len(&pk, algorithm.pk_bytes(), "public key")?;
+
+    Ok((algorithm, pk))
+}
+
+/// DER encoding of a PKCS#8 `OneAsymmetricKey` (version 0, without attributes)
+pub fn encode_private_key(algorithm: Algorithm, key: &PrivateKey) -> Result<Vec<u8>, String> {
+    key.check(algorithm)?;
+
+    Ok(der::sequence(&[
+        &der::integer(0),
+        &algorithm.identifier(),
+        &der::tlv(der::OCTET_STRING, &key.to_der()),
+    ]))
+}
+
+/// Decode a DER PKCS#8 `OneAsymmetricKey` => (algorithm, private key)
+///
+/// Attributes and public key fields are ignored.
+pub fn decode_private_key(data: &[u8]) -> Result<(Algorithm, PrivateKey), String> {
+    let mut reader = der::Reader::new(data);
+    let mut pkcs8 = reader.sequence()?;
+    reader.finish()?;
+
+    let version = pkcs8.integer()?;
+    if version > 1 {
+        return Err(format!("unsupported PKCS#8 version {}", version));
+    }
+    let algorithm = Algorithm::read_identifier(&mut pkcs8)?;
+    let key = PrivateKey::from_der(pkcs8.read(der::OCTET_STRING)?)?;
+
+    if pkcs8.peek_tag() == Some(der::context_constructed(0)) {
+        pkcs8.read(der::context_constructed(0))?;
+    }
+    if pkcs8.peek_tag() == Some(der::context(1)) {
+        pkcs8.read(der::context(1))?;
+    }
+    pkcs8.finish()?;
+    key.check(algorithm)?;
+
+    Ok((algorithm, key))
+}
+
+/// PEM encoding of a `SubjectPublicKeyInfo`
+pub fn public_key_to_pem(algorithm: Algorithm, pk: &ByteArray) -> Result<String, String> {
+    Ok(pem::encode(
+        PUBLIC_KEY_LABEL,
+        &encode_public_key(algorithm, pk)?,
+    ))
+}
+
+/// Decode a PEM `SubjectPublicKeyInfo`
+pub fn public_key_from_pem(s: &str) -> Result<(Algorithm, ByteArray), String> {
+    let (label, data) = pem::decode(s)?;
+    if label != PUBLIC_KEY_LABEL {
+        return Err(format!("unexpected PEM label {}", label));
+    }
+    decode_public_key(&data)
+}
+
+/// PEM encoding of a PKCS#8 private key
+pub fn private_key_to_pem(algorithm: Algorithm, key: &PrivateKey) -> Result<String, String> {
+    Ok(pem::encode(
+        PRIVATE_KEY_LABEL,
+        &encode_private_key(algorithm, key)?,
+    ))
+}
+
+/// Decode a PEM PKCS#8 private key
+pub fn private_key_from_pem(s: &str) -> Result<(Algorithm, PrivateKey), String> {
+    let (label, data) = pem::decode(s)?;
+    if label != PRIVATE_KEY_LABEL {
+        return Err(format!("unexpected PEM label {}", label));
+    }
+    decode_private_key(&data)
+}
+
+#[cfg(test)]
+const ALGORITHMS: [Algorithm; 3] = [
+    Algorithm::Kyber512,
+    Algorithm::Kyber768,
+    Algorithm::Kyber1024,
+];
+
+#[test]
+fn public_key_roundtrip() {
+    for algorithm in ALGORITHMS.iter().copied() {
+        let seed = ByteArray::random(SEED_BYTES);
+        let (_, pk) = algorithm.expand_seed(&seed).unwrap();
+
+        let pem = public_key_to_pem(algorithm, &pk).unwrap();
+        assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----"));
+        assert_eq!(public_key_from_pem(&pem).unwrap(), (algorithm, pk));
+    }
+}
+
+#[test]
+fn private_key_roundtrip() {
+    for algorithm in ALGORITHMS.iter().copied() {
+        let seed = ByteArray::random(SEED_BYTES);
+        let (sk, _) = algorithm.expand_seed(&seed).unwrap();
+
+        let keys = [
+            PrivateKey::Seed(seed.clone()),
+            PrivateKey::Expanded(sk.clone()),
+            PrivateKey::Both {
+                seed: seed.clone(),
+                expanded: sk.clone(),
+            },
+        ];
+        for key in keys.iter() {
+            let pem = private_key_to_pem(algorithm, key).unwrap();
+            let (decoded_algorithm, decoded) = private_key_from_pem(&pem).unwrap();
+
+            assert_eq!(decoded_algorithm, algorithm);
+            assert_eq!(decoded, *key);
+            assert_eq!(decoded.expanded(algorithm).unwrap(), sk);
+        }
+    }
+}
+
+#[test]
+fn known_encodings() {
+    // SPKI prefix for Kyber512: SEQUENCE, AlgorithmIdentifier, BIT STRING header
+    let pk = ByteArray::from_bytes(&[0; 800]);
+    let der = encode_public_key(Algorithm::Kyber512, &pk).unwrap();
+    assert_eq!(
+        der[..34].to_vec(),
+        [
+            &[0x30, 0x82, 0x03, 0x3e, 0x30, 0x17, 0x06, 0x15][..],
+            &OID_PREFIX,
+            &[0x01, 0x03, 0x82, 0x03, 0x21, 0x00],
+        ]
+        .concat()
+    );
+
+    // Seed-only PKCS#8 for Kyber768
+    let seed = ByteArray::from_bytes(&[0x2a; 64]);
+    let der = encode_private_key(Algorithm::Kyber768, &PrivateKey::Seed(seed)).unwrap();
+    assert_eq!(
+        der[..34].to_vec(),
+        [
+            &[0x30, 0x60, 0x02, 0x01, 0x00, 0x30, 0x17, 0x06, 0x15][..],
+            &OID_PREFIX,
+            &[0x02, 0x04, 0x42, 0x80, 0x40],
+        ]
+        .concat()
+    );
+    assert_eq!(der.len(), 98);
+}
+
+#[test]
+fn malformed_public_keys() {
+    let pk = ByteArray::from_bytes(&[0; 1184]);
+    let der = encode_public_key(Algorithm::Kyber768, &pk).unwrap();
+
+    // Wrong size for the algorithm
+    assert!(encode_public_key(Algorithm::Kyber512, &pk).is_err());
+    // Truncated
+    assert!(decode_public_key(&der[..der.len() - 1]).is_err());
+    // Trailing data
+    assert!(decode_public_key(&[&der[..], &[0]].concat()).is_err());
+    // Unknown OID: last arc of the algorithm
+    let mut bad = der.clone();
+    bad[28] = 9;
+    assert!(decode_public_key(&bad).is_err());
+    // ML-KEM OID
+    let bad = der::sequence(&[
+        &der::sequence(&[&der::tlv(
+            der::OID,
+            &[&OID_ML_KEM_PREFIX[..], &[2]].concat(),
+        )]),
+        &der::bit_string(&pk.data),
+    ]);
+    assert!(decode_public_key(&bad).unwrap_err().contains("FIPS 203"));
+    // Parameters present
+    let bad = der::sequence(&[
+        &der::sequence(&[
+            &der::tlv(der::OID, &Algorithm::Kyber768.oid()),
+            &[0x05, 0x00],
+        ]),
+        &der::bit_string(&pk.data),
+    ]);
+    assert!(decode_public_key(&bad).is_err());
+    // Key size does not match the OID
+    let bad = der::sequence(&[
+        &Algorithm::Kyber512.identifier(),
+        &der::bit_string(&pk.data),
+    ]);
+    assert!(decode_public_key(&bad).is_err());
+    // Wrong PEM label
+    assert!(public_key_from_pem(&pem::encode("PRIVATE KEY", &der)).is_err());
+}
+
+#[test]
+fn malformed_private_keys() {
+    let algorithm = Algorithm::Kyber512;
+    let seed = ByteArray::random(SEED_BYTES);
+    let (sk, _) = algorithm.expand_seed(&seed).unwrap();
+
+    // Seed of the wrong size
+    let short = PrivateKey::Seed(ByteArray::random(32));
+    assert!(encode_private_key(algorithm, &short).is_err());
+    // Expanded key of the wrong size
+    let expanded = PrivateKey::Expanded(sk.clone());
+    assert!(encode_private_key(Algorithm::Kyber768, &expanded).is_err());
+    // Inconsistent seed and expanded key
+    let both = PrivateKey::Both {
+        seed: ByteArray::random(SEED_BYTES),
+        expanded: sk,
+    };
+    assert!(encode_private_key(algorithm, &both).is_err());
+
+    let der = encode_private_key(algorithm, &PrivateKey::Seed(seed)).unwrap();
+    // Unsupported version
+    let mut bad = der.clone();
+    bad[4] = 2;
+    assert!(decode_private_key(&bad).is_err());
+    // Truncated
+    assert!(decode_private_key(&der[..der.len() - 1]).is_err());
+    // Unknown CHOICE tag
+    let bad = der::sequence(&[
+        &der::integer(0),
+        &algorithm.identifier(),
+        &der::tlv(der::OCTET_STRING, &der::tlv(der::context(1), &[0; 64])),
+    ]);
+    assert!(decode_private_key(&bad).is_err());
+}
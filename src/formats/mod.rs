@@ -0,0 +1,12 @@
+//! Formats
+//!
+//! Textual and ASN.1 encodings of keys and ciphertexts
+
+#[cfg(feature = "age")]
+pub mod bech32;
+#[cfg(feature = "cms")]
+pub mod cms;
+pub mod der;
+pub mod hex;
+pub mod keys;
+pub mod pem;
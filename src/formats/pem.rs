@@ -0,0 +1,126 @@
+//! PEM
+//!
+//! Base64 armor with `-----BEGIN label-----` / `-----END label-----` boundaries (RFC 7468)
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Number of base64 characters per line
+const LINE_LEN: usize = 64;
+
+/// Standard base64 encoding with padding
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(4 * data.len().div_ceil(3));
+
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = ((b[0] as usize) << 16) | ((b[1] as usize) << 8) | b[2] as usize;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+/// Standard base64 decoding, ignoring whitespace
+pub fn base64_decode(s: &str) -> Result<Vec<u8>, String> {
+    let chars: Vec<u8> = s.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    if !chars.len().is_multiple_of(4) {
+        return Err("invalid base64 length".to_string());
+    }
+
+    let mut out = Vec::with_capacity(chars.len() / 4 * 3);
+    for (index, chunk) in chars.chunks(4).enumerate() {
+        let last = index == chars.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|c| **c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return Err("invalid base64 padding".to_string());
+        }
+
+        let mut n = 0;
+        for c in chunk[..4 - padding].iter() {
+            let v = ALPHABET
+                .iter()
+                .position(|a| a == c)
+                .ok_or(format!("invalid base64 character {:?}", *c as char))?;
+            n = (n << 6) | v;
+        }
+        n <<= 6 * padding;
+
+        out.extend_from_slice(&[(n >> 16) as u8, (n >> 8) as u8, n as u8][..3 - padding]);
+    }
+
+    Ok(out)
+}
+
+/// Armor data with the given label
+pub fn encode(label: &str, data: &[u8]) -> String {
+    let b64 = base64_encode(data);
+    let mut out = format!("-----BEGIN {}-----\n", label);
+
+    for line in b64.as_bytes().chunks(LINE_LEN) {
+        // Base64 output is ASCII
+        out.push_str(std::str::from_utf8(line).unwrap());
+        out.push('\n');
+    }
+    out.push_str(&format!("-----END {}-----\n", label));
+
+    out
+}
+
+/// Decode the first PEM block of a string => (label, data)
+pub fn decode(s: &str) -> Result<(String, Vec<u8>), String> {
+    let begin = s.find("-----BEGIN ").ok_or("missing PEM header")?;
+    let rest = &s[begin + 11..];
+    let end_label = rest.find("-----").ok_or("invalid PEM header")?;
+    let label = &rest[..end_label];
+    let body = &rest[end_label + 5..];
+
+    let footer = format!("-----END {}-----", label);
+    let end = body.find(&footer).ok_or("missing PEM footer")?;
+
+    Ok((label.to_string(), base64_decode(&body[..end])?))
+}
+
+#[test]
+fn base64_vectors() {
+    // RFC 4648 section 10
+    let vectors = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+    for (plain, encoded) in vectors.iter() {
+        assert_eq!(base64_encode(plain.as_bytes()), *encoded);
+        assert_eq!(base64_decode(encoded).unwrap(), plain.as_bytes());
+    }
+    assert!(base64_decode("Zm9").is_err());
+    assert!(base64_decode("Zg==Zm8=").is_err());
+    assert!(base64_decode("Z!==").is_err());
+}
+
+#[test]
+fn pem_roundtrip() {
+    let data: Vec<u8> = (0..100).collect();
+    let pem = encode("TEST DATA", &data);
+    assert!(pem.starts_with("-----BEGIN TEST DATA-----\n"));
+
+    let (label, decoded) = decode(&pem).unwrap();
+    assert_eq!(label, "TEST DATA");
+    assert_eq!(decoded, data);
+
+    assert!(decode("-----BEGIN A-----\nAAAA\n-----END B-----\n").is_err());
+}
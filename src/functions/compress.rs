@@ -5,7 +5,7 @@
 use crate::structures::{Poly3329, PolyVec3329, F3329};
 
 /// Compress function on coefficients, p. 6
-fn compress_integer(x: usize, d: usize, q: usize) -> usize {
+pub(crate) fn compress_integer(x: usize, d: usize, q: usize) -> usize {
     let m = 1 << d;
     let f = (m as f64) / (q as f64);
     let f = f * (x as f64);
@@ -14,7 +14,7 @@ fn compress_integer(x: usize, d: usize, q: usize) -> usize {
 }
 
 /// Decompress function on coefficients, p. 6
-fn decompress_integer(x: usize, d: usize, q: usize) -> usize {
+pub(crate) fn decompress_integer(x: usize, d: usize, q: usize) -> usize {
     let m = 1 << d;
     let f = (q as f64 * x as f64) / (m as f64);
 
@@ -46,8 +46,8 @@ pub fn compress_polyvec<const N: usize, const D: usize>(
     q: usize,
 ) -> PolyVec3329<N, D> {
     let mut coeffs = [Default::default(); D];
-    for i in 0..D {
-        coeffs[i] = compress_poly(x.coefficients[i], d, q);
+    for (c, p) in coeffs.iter_mut().zip(x.coefficients.iter()) {
+        *c = compress_poly(*p, d, q);
     }
     PolyVec3329::from_vec(coeffs)
 }
@@ -59,8 +59,8 @@ pub fn decompress_polyvec<const N: usize, const D: usize>(
     q: usize,
 ) -> PolyVec3329<N, D> {
     let mut coeffs = [Default::default(); D];
-    for i in 0..D {
-        coeffs[i] = decompress_poly(x.coefficients[i], d, q);
+    for (c, p) in coeffs.iter_mut().zip(x.coefficients.iter()) {
+        *c = decompress_poly(*p, d, q);
     }
     PolyVec3329::from_vec(coeffs)
 }
@@ -68,7 +68,7 @@ pub fn decompress_polyvec<const N: usize, const D: usize>(
 #[test]
 fn compress_decompress_poly() {
     let original = Poly3329::from_vec([Default::default(); 256]);
-    let encoded = compress_poly(original.clone(), 12, 3329);
+    let encoded = compress_poly(original, 12, 3329);
     let decoded = decompress_poly(encoded, 12, 3329);
     assert!(decoded == original);
 }
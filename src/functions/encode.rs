@@ -12,10 +12,10 @@ use crate::structures::{
 pub fn decode_to_poly<const N: usize>(bs: ByteArray, ell: usize) -> Poly3329<N> {
     let mut f = [F3329::zero(); N];
 
-    for i in 0..N {
+    for (i, c) in f.iter_mut().enumerate() {
         for j in 0..ell {
             if bs.get_bit(i * ell + j) {
-                f[i] = f[i].add(&F3329::from_int(1 << j));
+                *c = c.add(&F3329::from_int(1 << j));
             }
         }
     }
@@ -51,7 +51,6 @@ pub fn decode_to_polyvec<const N: usize, const D: usize>(
     bs: ByteArray,
     ell: usize,
 ) -> PolyVec3329<N, D> {
-    let k = bs.data.len() / (32 * ell);
     let mut b = bs;
     let mut p_vec = PolyVec3329::from_vec([Poly3329::init(); D]);
 
@@ -82,7 +81,7 @@ pub fn encode_polyvec<const N: usize, const D: usize>(
 #[test]
 fn encode_decode_poly() {
     let original = Poly3329::from_vec([Default::default(); 256]);
-    let encoded = encode_poly(original.clone(), 12);
+    let encoded = encode_poly(original, 12);
     let decoded = decode_to_poly(encoded, 12);
     assert!(decoded == original);
 }
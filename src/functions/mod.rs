@@ -4,6 +4,6 @@
 
 pub mod compress;
 pub mod encode;
-mod hash;
+pub mod hash;
 pub mod ntt;
 pub mod utils;
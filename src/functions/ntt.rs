@@ -76,17 +76,12 @@ pub fn bcm_matrix_vec<const N: usize, const X: usize, const Y: usize>(
     let mut v = PolyVec3329::init();
 
     for i in 0..Y {
-        v.set(i, bcm_vec(&a.row(i), &b))
+        v.set(i, bcm_vec(&a.row(i), b))
     }
 
     v
 }
 
-/// Computes a.b as NTT^-1(a_hat o b_hat)
-fn ntt_product<const N: usize>(a_hat: &Poly3329<N>, b_hat: &Poly3329<N>) -> Poly3329<N> {
-    rev_ntt(&bcm(a_hat, b_hat))
-}
-
 /// Computes a^T.b as NTT^-1(a_hat^T o b_hat)
 pub fn ntt_product_vec<const N: usize, const D: usize>(
     a_hat: &PolyVec3329<N, D>,
@@ -106,8 +101,8 @@ pub fn ntt_product_matvec<const N: usize, const X: usize, const Y: usize>(
 /// Number theoretic Transform on vectors
 pub fn ntt_vec<const N: usize, const D: usize>(p: &PolyVec3329<N, D>) -> PolyVec3329<N, D> {
     let mut coeffs = [Default::default(); D];
-    for i in 0..D {
-        coeffs[i] = base_ntt(&p.coefficients[i]);
+    for (c, p) in coeffs.iter_mut().zip(p.coefficients.iter()) {
+        *c = base_ntt(p);
     }
     PolyVec3329::from_vec(coeffs)
 }
@@ -115,8 +110,8 @@ pub fn ntt_vec<const N: usize, const D: usize>(p: &PolyVec3329<N, D>) -> PolyVec
 /// Reverse NTT on vectors
 fn rev_ntt_vec<const N: usize, const D: usize>(p_hat: &PolyVec3329<N, D>) -> PolyVec3329<N, D> {
     let mut coeffs = [Default::default(); D];
-    for i in 0..D {
-        coeffs[i] = rev_ntt(&p_hat.coefficients[i]);
+    for (c, p) in coeffs.iter_mut().zip(p_hat.coefficients.iter()) {
+        *c = rev_ntt(p);
     }
     PolyVec3329::from_vec(coeffs)
 }
@@ -170,7 +165,7 @@ fn rev_ntt<const N: usize>(p_hat: &Poly3329<N>) -> Poly3329<N> {
     for i in 0..=(N - 1) / 2 {
         let mut p0 = p_hat[0];
         let mut p1 = p_hat[1];
-        let z = F3329::from_int(ZETAS_256[((256 - i) % 256)]);
+        let z = F3329::from_int(ZETAS_256[(256 - i) % 256]);
 
         for j in 1..=(N - 1) / 2 {
             let index = (2 * byte_rev(i) * j) % 256;
@@ -15,7 +15,7 @@ pub fn parse<const N: usize>(bs: &ByteArray, q: usize) -> Poly3329<N> {
     let mut p = Poly3329::init();
 
     while j < N {
-        let d = (bs.data[i] as usize) + (bs.data[i + 1] as usize) << 8;
+        let d = ((bs.data[i] as usize) + (bs.data[i + 1] as usize)) << 8;
         if d < 19 * q {
             p.set_coeff(j, F3329::from_int(d));
             j += 1;
@@ -76,7 +76,7 @@ pub fn h(r: &ByteArray) -> (ByteArray, ByteArray) {
     let hash = hash::sha3_256(&r.data);
     let (part0, part1) = hash.split_at(16);
 
-    (ByteArray::from_bytes(&part0), ByteArray::from_bytes(&part1))
+    (ByteArray::from_bytes(part0), ByteArray::from_bytes(part1))
 }
 
 /// Hash function => SHA3-512
@@ -84,7 +84,7 @@ pub fn g(r: &ByteArray) -> (ByteArray, ByteArray) {
     let hash = hash::sha3_512(&r.data);
     let (part0, part1) = hash.split_at(32);
 
-    (ByteArray::from_bytes(&part0), ByteArray::from_bytes(&part1))
+    (ByteArray::from_bytes(part0), ByteArray::from_bytes(part1))
 }
 
 /// Key Derivation function => SHAKE-256
@@ -0,0 +1,99 @@
+//! AEADs
+//!
+//! AEAD suites of RFC 9180 section 7.3
+
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use chacha20poly1305::aead::{Aead as _, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305 as ChaCha;
+
+/// Authenticated encryption of an HPKE suite
+pub trait Aead {
+    /// AEAD identifier
+    const ID: u16;
+    /// Key size
+    const NK: usize;
+    /// Nonce size
+    const NN: usize;
+    /// Tag size
+    const NT: usize;
+
+    /// Encrypt and authenticate `pt` with the associated data `aad`
+    fn seal(key: &[u8], nonce: &[u8], aad: &[u8], pt: &[u8]) -> Result<Vec<u8>, String>;
+
+    /// Decrypt and verify `ct` with the associated data `aad`
+    fn open(key: &[u8], nonce: &[u8], aad: &[u8], ct: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+macro_rules! aead_suite {
+    ($name:ident, $cipher:ty, $id:expr, $nk:expr, $doc:expr) => {
+        #[doc = $doc]
+        pub struct $name;
+
+        impl Aead for $name {
+            const ID: u16 = $id;
+            const NK: usize = $nk;
+            const NN: usize = 12;
+            const NT: usize = 16;
+
+            fn seal(key: &[u8], nonce: &[u8], aad: &[u8], pt: &[u8]) -> Result<Vec<u8>, String> {
+                let cipher = <$cipher>::new_from_slice(key).map_err(|_| "invalid AEAD key")?;
+                if nonce.len() != Self::NN {
+                    return Err("invalid AEAD nonce".to_string());
+                }
+                cipher
+                    .encrypt(nonce.into(), Payload { msg: pt, aad })
+                    .map_err(|_| "AEAD encryption failed".to_string())
+            }
+
+            fn open(key: &[u8], nonce: &[u8], aad: &[u8], ct: &[u8]) -> Result<Vec<u8>, String> {
+                let cipher = <$cipher>::new_from_slice(key).map_err(|_| "invalid AEAD key")?;
+                if nonce.len() != Self::NN {
+                    return Err("invalid AEAD nonce".to_string());
+                }
+                cipher
+                    .decrypt(nonce.into(), Payload { msg: ct, aad })
+                    .map_err(|_| "AEAD decryption failed".to_string())
+            }
+        }
+    };
+}
+
+aead_suite!(Aes128GcmAead, Aes128Gcm, 0x0001, 16, "AES-128-GCM");
+aead_suite!(Aes256GcmAead, Aes256Gcm, 0x0002, 32, "AES-256-GCM");
+aead_suite!(ChaCha20Poly1305, ChaCha, 0x0003, 32, "ChaCha20Poly1305");
+
+/// Export-only suite: the context can only be used to export secrets
+pub struct ExportOnly;
+
+impl Aead for ExportOnly {
+    const ID: u16 = 0xffff;
+    const NK: usize = 0;
+    const NN: usize = 0;
+    const NT: usize = 0;
+
+    fn seal(_: &[u8], _: &[u8], _: &[u8], _: &[u8]) -> Result<Vec<u8>, String> {
+        Err("export-only context".to_string())
+    }
+
+    fn open(_: &[u8], _: &[u8], _: &[u8], _: &[u8]) -> Result<Vec<u8>, String> {
+        Err("export-only context".to_string())
+    }
+}
+
+#[test]
+fn aead_roundtrip() {
+    fn check<A: Aead>() {
+        let key = vec![7; A::NK];
+        let nonce = vec![1; A::NN];
+        let ct = A::seal(&key, &nonce, b"aad", b"message").unwrap();
+        assert_eq!(ct.len(), 7 + A::NT);
+        assert_eq!(A::open(&key, &nonce, b"aad", &ct).unwrap(), b"message");
+        assert!(A::open(&key, &nonce, b"other", &ct).is_err());
+        assert!(A::seal(&key[1..], &nonce, b"aad", b"message").is_err());
+    }
+
+    check::<Aes128GcmAead>();
+    check::<Aes256GcmAead>();
+    check::<ChaCha20Poly1305>();
+    assert!(ExportOnly::seal(&[], &[], &[], b"message").is_err());
+}
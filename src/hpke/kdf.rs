@@ -0,0 +1,72 @@
+//! KDFs
+//!
+//! HKDF suites of RFC 9180 section 7.2
+
+use hkdf::Hkdf;
+use sha2::{Sha256, Sha384, Sha512};
+
+/// Key derivation function of an HPKE suite
+pub trait Kdf {
+    /// KDF identifier
+    const ID: u16;
+    /// Output size of the extraction
+    const NH: usize;
+
+    /// Extract a pseudorandom key from `ikm` and `salt`
+    fn extract(salt: &[u8], ikm: &[u8]) -> Vec<u8>;
+
+    /// Expand `prk` into `len` bytes bound to `info`
+    fn expand(prk: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, String>;
+}
+
+macro_rules! hkdf_suite {
+    ($name:ident, $hash:ty, $id:expr, $nh:expr, $doc:expr) => {
+        #[doc = $doc]
+        pub struct $name;
+
+        impl Kdf for $name {
+            const ID: u16 = $id;
+            const NH: usize = $nh;
+
+            fn extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+                let (prk, _) = Hkdf::<$hash>::extract(Some(salt), ikm);
+                prk.to_vec()
+            }
+
+            fn expand(prk: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, String> {
+                let hkdf = Hkdf::<$hash>::from_prk(prk).map_err(|_| "invalid HKDF key")?;
+                let mut out = vec![0; len];
+                hkdf.expand(info, &mut out)
+                    .map_err(|_| "HKDF output too long")?;
+                Ok(out)
+            }
+        }
+    };
+}
+
+hkdf_suite!(HkdfSha256, Sha256, 0x0001, 32, "HKDF-SHA256");
+hkdf_suite!(HkdfSha384, Sha384, 0x0002, 48, "HKDF-SHA384");
+hkdf_suite!(HkdfSha512, Sha512, 0x0003, 64, "HKDF-SHA512");
+
+#[test]
+fn hkdf_rfc5869() {
+    use crate::formats::hex;
+
+    // RFC 5869 test case 1
+    let ikm = [0x0b; 22];
+    let salt = hex::decode("000102030405060708090a0b0c").unwrap();
+    let info = hex::decode("f0f1f2f3f4f5f6f7f8f9").unwrap();
+
+    let prk = HkdfSha256::extract(&salt, &ikm);
+    assert_eq!(
+        hex::encode(&prk),
+        "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5"
+    );
+    let okm = HkdfSha256::expand(&prk, &info, 42).unwrap();
+    assert_eq!(
+        hex::encode(&okm),
+        "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+    );
+
+    assert!(HkdfSha256::expand(&prk, &info, 255 * 32 + 1).is_err());
+}
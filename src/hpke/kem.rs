@@ -0,0 +1,214 @@
+//! KEMs
+//!
+//! KEM interface of RFC 9180 section 4 on top of the Kyber KEM and the hybrids.
+//!
+//! The KEMs of this crate implement the round 2 specification, not ML-KEM (FIPS 203),
+//! so they do not use the ML-KEM or X-Wing code points, which would announce an
+//! algorithm they are not compatible with: their identifiers are in the 0xff00 range.
+
+use crate::functions::hash::shake_256;
+use crate::kem::{KEM, SEED_BYTES};
+use crate::params::{Kyber1024, Kyber512, Kyber768, ParameterSet};
+use crate::pke::PKE;
+use crate::structures::ByteArray;
+use crate::validate::validate_public_key;
+
+use std::marker::PhantomData;
+
+/// Key encapsulation mechanism of an HPKE suite
+pub trait Kem {
+    /// KEM identifier
+    const ID: u16;
+    /// Size of the shared secret
+    const N_SECRET: usize;
+    /// Size of an encapsulated key
+    const N_ENC: usize;
+    /// Size of a public key
+    const N_PK: usize;
+    /// Size of a secret key
+    const N_SK: usize;
+
+    /// Random key pair => (secret key, public key)
+    fn generate_key_pair() -> (ByteArray, ByteArray);
+
+    /// Key pair derived from the input keying material `ikm` => (secret key, public key)
+    fn derive_key_pair(ikm: &[u8]) -> Result<(ByteArray, ByteArray), String>;
+
+    /// Encoding of a public key
+    fn serialize_public_key(pk: &ByteArray) -> Vec<u8> {
+        pk.data.clone()
+    }
+
+    /// Decoding and validation of a public key
+    fn deserialize_public_key(pk: &[u8]) -> Result<ByteArray, String>;
+
+    /// Encapsulation to a public key => (shared secret, encapsulated key)
+    fn encap(pk: &ByteArray) -> Result<(ByteArray, ByteArray), String>;
+
+    /// Decapsulation of an encapsulated key => shared secret
+    fn decap(enc: &[u8], sk: &ByteArray) -> Result<ByteArray, String>;
+}
+
+/// Seed of `len` bytes derived from `ikm` with SHAKE256, bound to the KEM identifier
+fn derive_seed(id: u16, ikm: &[u8], len: usize) -> Result<ByteArray, String> {
+    if ikm.len() < 32 {
+        return Err("input keying material must be at least 32 bytes".to_string());
+    }
+    let input = [b"HPKE-v1KEM", &id.to_be_bytes()[..], b"DeriveKeyPair", ikm].concat();
+
+    Ok(ByteArray::from_bytes(&shake_256(&input, len)))
+}
+
+/// Kyber KEM for the parameter set `P`
+pub struct KyberKem<P: ParameterSet, const N: usize, const K: usize> {
+    params: PhantomData<P>,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> KyberKem<P, N, K> {
+    fn kem() -> KEM<P, N, K> {
+        KEM::init(PKE::init())
+    }
+}
+
+macro_rules! kyber_kem {
+    ($params:ty, $k:expr, $id:expr) => {
+        impl Kem for KyberKem<$params, 256, $k> {
+            const ID: u16 = $id;
+            const N_SECRET: usize = <$params>::SS_BYTES;
+            const N_ENC: usize = <$params>::CT_BYTES;
+            const N_PK: usize = <$params>::PK_BYTES;
+            const N_SK: usize = <$params>::SK_BYTES;
+
+            fn generate_key_pair() -> (ByteArray, ByteArray) {
+                Self::kem().keygen()
+            }
+
+            fn derive_key_pair(ikm: &[u8]) -> Result<(ByteArray, ByteArray), String> {
+                Self::kem().expand_seed(&derive_seed(Self::ID, ikm, SEED_BYTES)?)
+            }
+
+            fn deserialize_public_key(pk: &[u8]) -> Result<ByteArray, String> {
+                let pk = ByteArray::from_bytes(pk);
+                validate_public_key::<$params>(&pk)?;
+                Ok(pk)
+            }
+
+            fn encap(pk: &ByteArray) -> Result<(ByteArray, ByteArray), String> {
+                let (enc, ss) = Self::kem().encaps_checked(pk)?;
+                Ok((ss, enc))
+            }
+
+            fn decap(enc: &[u8], sk: &ByteArray) -> Result<ByteArray, String> {
+                Self::kem().decaps_checked(&ByteArray::from_bytes(enc), sk)
+            }
+        }
+    };
+}
+
+// Only the presets have an identifier: custom parameter sets would need their own
+kyber_kem!(Kyber512, 2, 0xff40);
+kyber_kem!(Kyber768, 3, 0xff41);
+kyber_kem!(Kyber1024, 4, 0xff42);
+
+#[cfg(feature = "hybrid")]
+impl Kem for crate::hybrid::X25519Kyber768 {
+    const ID: u16 = 0xff43;
+    const N_SECRET: usize = Self::SS_BYTES;
+    const N_ENC: usize = Self::CT_BYTES;
+    const N_PK: usize = Self::PK_BYTES;
+    const N_SK: usize = Self::SK_BYTES;
+
+    fn generate_key_pair() -> (ByteArray, ByteArray) {
+        crate::hybrid::x25519_kyber768().keygen()
+    }
+
+    fn derive_key_pair(ikm: &[u8]) -> Result<(ByteArray, ByteArray), String> {
+        let seed = derive_seed(Self::ID, ikm, Self::SEED_BYTES)?;
+        crate::hybrid::x25519_kyber768().keygen_derand(&seed)
+    }
+
+    fn deserialize_public_key(pk: &[u8]) -> Result<ByteArray, String> {
+        if pk.len() != Self::PK_BYTES {
+            return Err(format!("public key must be {} bytes", Self::PK_BYTES));
+        }
+        let pk = ByteArray::from_bytes(pk);
+        validate_public_key::<crate::Kyber768>(&pk.truncate(crate::Kyber768::PK_BYTES))?;
+        Ok(pk)
+    }
+
+    fn encap(pk: &ByteArray) -> Result<(ByteArray, ByteArray), String> {
+        let (enc, ss) = crate::hybrid::x25519_kyber768().encaps(pk)?;
+        Ok((ss, enc))
+    }
+
+    fn decap(enc: &[u8], sk: &ByteArray) -> Result<ByteArray, String> {
+        crate::hybrid::x25519_kyber768().decaps(&ByteArray::from_bytes(enc), sk)
+    }
+}
+
+#[cfg(feature = "xwing")]
+impl Kem for crate::xwing::XWing {
+    const ID: u16 = 0xff7a;
+    const N_SECRET: usize = crate::xwing::SS_BYTES;
+    const N_ENC: usize = crate::xwing::CT_BYTES;
+    const N_PK: usize = crate::xwing::PK_BYTES;
+    const N_SK: usize = crate::xwing::SK_BYTES;
+
+    fn generate_key_pair() -> (ByteArray, ByteArray) {
+        crate::xwing::xwing().keygen()
+    }
+
+    fn derive_key_pair(ikm: &[u8]) -> Result<(ByteArray, ByteArray), String> {
+        let seed = derive_seed(Self::ID, ikm, crate::xwing::SK_BYTES)?;
+        crate::xwing::xwing().keygen_derand(&seed)
+    }
+
+    fn deserialize_public_key(pk: &[u8]) -> Result<ByteArray, String> {
+        if pk.len() != crate::xwing::PK_BYTES {
+            return Err(format!(
+                "public key must be {} bytes",
+                crate::xwing::PK_BYTES
+            ));
+        }
+        let pk = ByteArray::from_bytes(pk);
+        validate_public_key::<crate::Kyber768>(&pk.truncate(crate::Kyber768::PK_BYTES))?;
+        Ok(pk)
+    }
+
+    fn encap(pk: &ByteArray) -> Result<(ByteArray, ByteArray), String> {
+        let (enc, ss) = crate::xwing::xwing().encaps(pk)?;
+        Ok((ss, enc))
+    }
+
+    fn decap(enc: &[u8], sk: &ByteArray) -> Result<ByteArray, String> {
+        crate::xwing::xwing().decaps(&ByteArray::from_bytes(enc), sk)
+    }
+}
+
+#[test]
+fn kyber_kem_ids() {
+    assert_eq!(KyberKem::<Kyber512, 256, 2>::ID, 0xff40);
+    assert_eq!(KyberKem::<Kyber768, 256, 3>::ID, 0xff41);
+    assert_eq!(KyberKem::<Kyber1024, 256, 4>::ID, 0xff42);
+}
+
+#[test]
+fn derive_key_pair_is_deterministic() {
+    type M = KyberKem<Kyber768, 256, 3>;
+
+    let ikm = [3; 32];
+    let (sk, pk) = M::derive_key_pair(&ikm).unwrap();
+    assert_eq!(M::derive_key_pair(&ikm).unwrap(), (sk.clone(), pk.clone()));
+    assert_ne!(M::derive_key_pair(&[4; 32]).unwrap().1, pk);
+    assert!(M::derive_key_pair(&[3; 31]).is_err());
+
+    assert_eq!(
+        M::deserialize_public_key(&M::serialize_public_key(&pk)).unwrap(),
+        pk
+    );
+    assert!(M::deserialize_public_key(&sk.data).is_err());
+
+    let (ss, enc) = M::encap(&pk).unwrap();
+    assert_eq!(enc.data.len(), M::N_ENC);
+    assert_eq!(M::decap(&enc.data, &sk).unwrap(), ss);
+}
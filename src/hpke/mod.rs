@@ -0,0 +1,381 @@
+//! HPKE
+//!
+//! Hybrid public key encryption (RFC 9180) with the KEMs of this crate, in the base and
+//! PSK modes. The authenticated modes need a Diffie-Hellman KEM and are not supported.
+//!
+//! See `kem` for the KEM identifiers: the KEMs implement the round 2 specification and
+//! do not interoperate with the ML-KEM HPKE suites.
+
+mod aead;
+mod kdf;
+mod kem;
+
+pub use aead::{Aead, Aes128GcmAead, Aes256GcmAead, ChaCha20Poly1305, ExportOnly};
+pub use kdf::{HkdfSha256, HkdfSha384, HkdfSha512, Kdf};
+pub use kem::{Kem, KyberKem};
+
+use crate::structures::ByteArray;
+
+use std::marker::PhantomData;
+
+/// Base mode
+pub const MODE_BASE: u8 = 0x00;
+/// Pre-shared key mode
+pub const MODE_PSK: u8 = 0x01;
+
+/// `LabeledExtract` of RFC 9180 section 4
+fn labeled_extract<F: Kdf>(suite_id: &[u8], salt: &[u8], label: &[u8], ikm: &[u8]) -> Vec<u8> {
+    F::extract(salt, &[b"HPKE-v1", suite_id, label, ikm].concat())
+}
+
+/// `LabeledExpand` of RFC 9180 section 4
+fn labeled_expand<F: Kdf>(
+    suite_id: &[u8],
+    prk: &[u8],
+    label: &[u8],
+    info: &[u8],
+    len: usize,
+) -> Result<Vec<u8>, String> {
+    if len > 0xffff {
+        return Err("requested output too long".to_string());
+    }
+    let info = [
+        &(len as u16).to_be_bytes()[..],
+        b"HPKE-v1",
+        suite_id,
+        label,
+        info,
+    ]
+    .concat();
+
+    F::expand(prk, &info, len)
+}
+
+/// Check the PSK inputs against the mode
+fn verify_psk_inputs(mode: u8, psk: &[u8], psk_id: &[u8]) -> Result<(), String> {
+    let (got_psk, got_psk_id) = (!psk.is_empty(), !psk_id.is_empty());
+
+    if got_psk != got_psk_id {
+        return Err("inconsistent PSK inputs".to_string());
+    }
+    match mode {
+        MODE_BASE if got_psk => Err("PSK input provided when not needed".to_string()),
+        MODE_PSK if !got_psk => Err("missing required PSK input".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Key schedule of RFC 9180 section 5.1
+fn key_schedule<F: Kdf, A: Aead>(
+    suite_id: Vec<u8>,
+    mode: u8,
+    shared_secret: &[u8],
+    info: &[u8],
+    psk: &[u8],
+    psk_id: &[u8],
+) -> Result<Context<F, A>, String> {
+    verify_psk_inputs(mode, psk, psk_id)?;
+
+    let psk_id_hash = labeled_extract::<F>(&suite_id, b"", b"psk_id_hash", psk_id);
+    let info_hash = labeled_extract::<F>(&suite_id, b"", b"info_hash", info);
+    let context = [&[mode][..], &psk_id_hash, &info_hash].concat();
+
+    let secret = labeled_extract::<F>(&suite_id, shared_secret, b"secret", psk);
+    let key = labeled_expand::<F>(&suite_id, &secret, b"key", &context, A::NK)?;
+    let base_nonce = labeled_expand::<F>(&suite_id, &secret, b"base_nonce", &context, A::NN)?;
+    let exporter_secret = labeled_expand::<F>(&suite_id, &secret, b"exp", &context, F::NH)?;
+
+    Ok(Context {
+        suite_id,
+        key,
+        base_nonce,
+        seq: 0,
+        exporter_secret,
+        suites: PhantomData,
+    })
+}
+
+/// Encryption context shared by the sender and the recipient
+pub struct Context<F: Kdf, A: Aead> {
+    suite_id: Vec<u8>,
+    key: Vec<u8>,
+    base_nonce: Vec<u8>,
+    seq: u64,
+    exporter_secret: Vec<u8>,
+    suites: PhantomData<(F, A)>,
+}
+
+impl<F: Kdf, A: Aead> Context<F, A> {
+    /// Nonce of the current message: the base nonce xored with the sequence number
+    fn nonce(&self) -> Vec<u8> {
+        let seq = self.seq.to_be_bytes();
+        let offset = A::NN.saturating_sub(seq.len());
+
+        let mut nonce = self.base_nonce.clone();
+        for (n, s) in nonce[offset..].iter_mut().zip(seq.iter()) {
+            *n ^= s;
+        }
+        nonce
+    }
+
+    fn increment_seq(&mut self) -> Result<(), String> {
+        self.seq = self.seq.checked_add(1).ok_or("message limit reached")?;
+        Ok(())
+    }
+
+    /// Encrypt the next message
+    pub fn seal(&mut self, aad: &[u8], pt: &[u8]) -> Result<Vec<u8>, String> {
+        let ct = A::seal(&self.key, &self.nonce(), aad, pt)?;
+        self.increment_seq()?;
+        Ok(ct)
+    }
+
+    /// Decrypt the next message
+    pub fn open(&mut self, aad: &[u8], ct: &[u8]) -> Result<Vec<u8>, String> {
+        let pt = A::open(&self.key, &self.nonce(), aad, ct)?;
+        self.increment_seq()?;
+        Ok(pt)
+    }
+
+    /// Export a secret of `len` bytes bound to `exporter_context`
+    pub fn export(&self, exporter_context: &[u8], len: usize) -> Result<Vec<u8>, String> {
+        labeled_expand::<F>(
+            &self.suite_id,
+            &self.exporter_secret,
+            b"sec",
+            exporter_context,
+            len,
+        )
+    }
+}
+
+/// HPKE suite of the KEM `M`, the KDF `F` and the AEAD `A`
+pub struct Hpke<M: Kem, F: Kdf, A: Aead> {
+    suite: PhantomData<(M, F, A)>,
+}
+
+impl<M: Kem, F: Kdf, A: Aead> Hpke<M, F, A> {
+    /// Suite identifier `"HPKE" || kem_id || kdf_id || aead_id`
+    fn suite_id() -> Vec<u8> {
+        [
+            &b"HPKE"[..],
+            &M::ID.to_be_bytes(),
+            &F::ID.to_be_bytes(),
+            &A::ID.to_be_bytes(),
+        ]
+        .concat()
+    }
+
+    fn setup_s(
+        mode: u8,
+        pk_r: &ByteArray,
+        info: &[u8],
+        psk: &[u8],
+        psk_id: &[u8],
+    ) -> Result<(ByteArray, Context<F, A>), String> {
+        let (shared_secret, enc) = M::encap(pk_r)?;
+        let ctx = key_schedule(
+            Self::suite_id(),
+            mode,
+            &shared_secret.data,
+            info,
+            psk,
+            psk_id,
+        )?;
+
+        Ok((enc, ctx))
+    }
+
+    fn setup_r(
+        mode: u8,
+        enc: &[u8],
+        sk_r: &ByteArray,
+        info: &[u8],
+        psk: &[u8],
+        psk_id: &[u8],
+    ) -> Result<Context<F, A>, String> {
+        let shared_secret = M::decap(enc, sk_r)?;
+        key_schedule(
+            Self::suite_id(),
+            mode,
+            &shared_secret.data,
+            info,
+            psk,
+            psk_id,
+        )
+    }
+
+    /// Sender setup in base mode => (encapsulated key, context)
+    pub fn setup_base_s(
+        pk_r: &ByteArray,
+        info: &[u8],
+    ) -> Result<(ByteArray, Context<F, A>), String> {
+        Self::setup_s(MODE_BASE, pk_r, info, b"", b"")
+    }
+
+    /// Recipient setup in base mode
+    pub fn setup_base_r(
+        enc: &[u8],
+        sk_r: &ByteArray,
+        info: &[u8],
+    ) -> Result<Context<F, A>, String> {
+        Self::setup_r(MODE_BASE, enc, sk_r, info, b"", b"")
+    }
+
+    /// Sender setup in PSK mode => (encapsulated key, context)
+    pub fn setup_psk_s(
+        pk_r: &ByteArray,
+        info: &[u8],
+        psk: &[u8],
+        psk_id: &[u8],
+    ) -> Result<(ByteArray, Context<F, A>), String> {
+        Self::setup_s(MODE_PSK, pk_r, info, psk, psk_id)
+    }
+
+    /// Recipient setup in PSK mode
+    pub fn setup_psk_r(
+        enc: &[u8],
+        sk_r: &ByteArray,
+        info: &[u8],
+        psk: &[u8],
+        psk_id: &[u8],
+    ) -> Result<Context<F, A>, String> {
+        Self::setup_r(MODE_PSK, enc, sk_r, info, psk, psk_id)
+    }
+
+    /// Single-shot encryption in base mode => (encapsulated key, ciphertext)
+    pub fn seal_base(
+        pk_r: &ByteArray,
+        info: &[u8],
+        aad: &[u8],
+        pt: &[u8],
+    ) -> Result<(ByteArray, Vec<u8>), String> {
+        let (enc, mut ctx) = Self::setup_base_s(pk_r, info)?;
+        Ok((enc, ctx.seal(aad, pt)?))
+    }
+
+    /// Single-shot decryption in base mode
+    pub fn open_base(
+        enc: &[u8],
+        sk_r: &ByteArray,
+        info: &[u8],
+        aad: &[u8],
+        ct: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        Self::setup_base_r(enc, sk_r, info)?.open(aad, ct)
+    }
+}
+
+#[test]
+fn key_schedule_rfc9180() {
+    use crate::formats::hex;
+
+    // RFC 9180 A.1.1: DHKEM(X25519, HKDF-SHA256), HKDF-SHA256, AES-128-GCM, base mode,
+    // starting from the shared secret of the DHKEM
+    let suite_id = hex::decode("48504b45002000010001").unwrap();
+    let shared_secret =
+        hex::decode("fe0e18c9f024ce43799ae393c7e8fe8fce9d218875e8227b0187c04e7d2ea1fc").unwrap();
+    let info = hex::decode("4f6465206f6e2061204772656369616e2055726e").unwrap();
+
+    let mut ctx = key_schedule::<HkdfSha256, Aes128GcmAead>(
+        suite_id,
+        MODE_BASE,
+        &shared_secret,
+        &info,
+        b"",
+        b"",
+    )
+    .unwrap();
+    assert_eq!(hex::encode(&ctx.key), "4531685d41d65f03dc48f6b8302c05b0");
+    assert_eq!(hex::encode(&ctx.base_nonce), "56d890e5accaaf011cff4b7d");
+    assert_eq!(
+        hex::encode(&ctx.exporter_secret),
+        "45ff1c2e220db587171952c0592d5f5ebe103f1561a2614e38f2ffd47e99e3f8"
+    );
+
+    let pt = hex::decode("4265617574792069732074727574682c20747275746820626561757479").unwrap();
+    let ct = ctx
+        .seal(&hex::decode("436f756e742d30").unwrap(), &pt)
+        .unwrap();
+    assert_eq!(
+        hex::encode(&ct),
+        "f938558b5d72f1a23810b4be2ab4f84331acc02fc97babc53a52ae8218a355a96d8770ac83d07bea87e13c512a"
+    );
+    assert_eq!(
+        hex::encode(&ctx.export(b"", 32).unwrap()),
+        "3853fe2b4035195a573ffc53856e77058e15d9ea064de3e59f4961d0095250ee"
+    );
+}
+
+#[cfg(test)]
+fn roundtrip<M: Kem, F: Kdf, A: Aead>() {
+    let (sk_r, pk_r) = M::generate_key_pair();
+    let info = b"kybe-rs hpke test";
+
+    // Base mode, several messages
+    let (enc, mut sender) = Hpke::<M, F, A>::setup_base_s(&pk_r, info).unwrap();
+    assert_eq!(enc.data.len(), M::N_ENC);
+    let mut recipient = Hpke::<M, F, A>::setup_base_r(&enc.data, &sk_r, info).unwrap();
+    for i in 0..3u8 {
+        let ct = sender.seal(&[i], b"message").unwrap();
+        assert_eq!(recipient.open(&[i], &ct).unwrap(), b"message");
+    }
+    assert_eq!(
+        sender.export(b"context", 42).unwrap(),
+        recipient.export(b"context", 42).unwrap()
+    );
+
+    // Another info gives another context
+    let mut other = Hpke::<M, F, A>::setup_base_r(&enc.data, &sk_r, b"other").unwrap();
+    assert!(other
+        .open(&[0], &sender.seal(&[0], b"message").unwrap())
+        .is_err());
+
+    // PSK mode
+    let (psk, psk_id) = ([5; 32], b"psk id");
+    let (enc, mut sender) = Hpke::<M, F, A>::setup_psk_s(&pk_r, info, &psk, psk_id).unwrap();
+    let ct = sender.seal(b"aad", b"message").unwrap();
+    let mut recipient = Hpke::<M, F, A>::setup_psk_r(&enc.data, &sk_r, info, &psk, psk_id).unwrap();
+    assert_eq!(recipient.open(b"aad", &ct).unwrap(), b"message");
+
+    let mut wrong = Hpke::<M, F, A>::setup_psk_r(&enc.data, &sk_r, info, &[6; 32], psk_id).unwrap();
+    assert!(wrong.open(b"aad", &ct).is_err());
+    assert!(Hpke::<M, F, A>::setup_psk_s(&pk_r, info, &psk, b"").is_err());
+    assert!(Hpke::<M, F, A>::setup_psk_s(&pk_r, info, b"", b"").is_err());
+
+    // Single shot
+    let (enc, ct) = Hpke::<M, F, A>::seal_base(&pk_r, info, b"aad", b"message").unwrap();
+    assert_eq!(
+        Hpke::<M, F, A>::open_base(&enc.data, &sk_r, info, b"aad", &ct).unwrap(),
+        b"message"
+    );
+}
+
+#[test]
+fn hpke_roundtrip() {
+    use crate::{Kyber1024, Kyber512, Kyber768};
+
+    roundtrip::<KyberKem<Kyber512, 256, 2>, HkdfSha256, Aes128GcmAead>();
+    roundtrip::<KyberKem<Kyber768, 256, 3>, HkdfSha384, Aes256GcmAead>();
+    roundtrip::<KyberKem<Kyber1024, 256, 4>, HkdfSha512, ChaCha20Poly1305>();
+    #[cfg(feature = "hybrid")]
+    roundtrip::<crate::hybrid::X25519Kyber768, HkdfSha256, ChaCha20Poly1305>();
+    #[cfg(feature = "xwing")]
+    roundtrip::<crate::xwing::XWing, HkdfSha256, Aes128GcmAead>();
+}
+
+#[test]
+fn hpke_export_only() {
+    type Suite = Hpke<KyberKem<crate::Kyber768, 256, 3>, HkdfSha256, ExportOnly>;
+    type M = KyberKem<crate::Kyber768, 256, 3>;
+
+    let (sk_r, pk_r) = M::derive_key_pair(&[1; 32]).unwrap();
+    let (enc, mut sender) = Suite::setup_base_s(&pk_r, b"").unwrap();
+    let recipient = Suite::setup_base_r(&enc.data, &sk_r, b"").unwrap();
+
+    assert!(sender.seal(b"", b"message").is_err());
+    assert_eq!(
+        sender.export(b"", 32).unwrap(),
+        recipient.export(b"", 32).unwrap()
+    );
+}
@@ -0,0 +1,249 @@
+//! Hybrid KEM
+//!
+//! Combination of a classical Diffie-Hellman with a Kyber KEM: public keys and
+//! ciphertexts are concatenated, and the shared secret is
+//!
+//! `SHA3-256(label || ss_kem || ss_dh || ct_kem || ct_dh || pk_kem || pk_dh)`
+//!
+//! so that it stays secure as long as one of the two components is.
+//!
+//! The Kyber side is the round 2 KEM of this crate, which is not ML-KEM (FIPS 203):
+//! an `X25519Kyber768` key does not interoperate with X25519MLKEM768 implementations.
+
+pub(crate) mod x25519;
+mod x448;
+
+pub use x25519::X25519;
+pub use x448::X448;
+
+use crate::functions::hash::sha3_256;
+use crate::kem::{KEM, SEED_BYTES};
+use crate::params::{Kyber1024, Kyber512, Kyber768, ParameterSet};
+use crate::structures::ByteArray;
+use crate::validate::{validate_ciphertext, validate_public_key};
+use crate::{kyber1024kem, kyber512kem, kyber768kem};
+
+use std::marker::PhantomData;
+
+/// Classical Diffie-Hellman group
+pub trait DiffieHellman {
+    /// Name used in the combiner label
+    const NAME: &'static str;
+    /// Size of a secret key, which is also the size of the seed it is derived from
+    const SK_BYTES: usize;
+    /// Size of a public key
+    const PK_BYTES: usize;
+
+    /// Derive a secret key from a seed of `SK_BYTES` bytes
+    fn secret_key(seed: &ByteArray) -> ByteArray;
+
+    /// Public key of a secret key
+    fn public_key(sk: &ByteArray) -> ByteArray;
+
+    /// Shared secret of a secret key and a public key, rejecting degenerate results
+    fn dh(sk: &ByteArray, pk: &ByteArray) -> Result<ByteArray, String>;
+}
+
+/// Hybrid of the Diffie-Hellman `D` and the Kyber KEM for the parameter set `P`
+pub struct HybridKem<D: DiffieHellman, P: ParameterSet, const N: usize, const K: usize> {
+    kem: KEM<P, N, K>,
+    dh: PhantomData<D>,
+}
+
+impl<D: DiffieHellman, P: ParameterSet, const N: usize, const K: usize> HybridKem<D, P, N, K> {
+    /// Size of a public key `pk_kem || pk_dh`
+    pub const PK_BYTES: usize = P::PK_BYTES + D::PK_BYTES;
+    /// Size of a secret key `sk_kem || sk_dh`
+    pub const SK_BYTES: usize = P::SK_BYTES + D::SK_BYTES;
+    /// Size of a ciphertext `ct_kem || ct_dh`
+    pub const CT_BYTES: usize = P::CT_BYTES + D::PK_BYTES;
+    /// Size of a shared secret
+    pub const SS_BYTES: usize = 32;
+    /// Size of the seed of `keygen_derand`
+    pub const SEED_BYTES: usize = SEED_BYTES + D::SK_BYTES;
+    /// Size of the randomness of `encaps_derand`
+    pub const COINS_BYTES: usize = 32 + D::SK_BYTES;
+
+    /// Hybrid Key Generation => (secret key, public key)
+    pub fn keygen(&self) -> (ByteArray, ByteArray) {
+        // Unwraps safely since the seed has the right size
+        self.keygen_derand(&ByteArray::random(Self::SEED_BYTES))
+            .unwrap()
+    }
+
+    /// Deterministic key generation from a seed of `SEED_BYTES` bytes => (secret key, public key)
+    pub fn keygen_derand(&self, seed: &ByteArray) -> Result<(ByteArray, ByteArray), String> {
+        if seed.data.len() != Self::SEED_BYTES {
+            return Err(format!("seed must be {} bytes", Self::SEED_BYTES));
+        }
+        let (kem_seed, dh_seed) = seed.split_at(SEED_BYTES);
+
+        let (sk_kem, pk_kem) = self.kem.expand_seed(&kem_seed)?;
+        let sk_dh = D::secret_key(&dh_seed);
+        let pk_dh = D::public_key(&sk_dh);
+
+        Ok((sk_kem.append(&sk_dh), pk_kem.append(&pk_dh)))
+    }
+
+    /// Encapsulation : public key => (ciphertext, shared secret)
+    pub fn encaps(&self, pk: &ByteArray) -> Result<(ByteArray, ByteArray), String> {
+        self.encaps_derand(pk, &ByteArray::random(Self::COINS_BYTES))
+    }
+
+    /// Deterministic encapsulation with `COINS_BYTES` bytes of randomness => (ciphertext, shared secret)
+    pub fn encaps_derand(
+        &self,
+        pk: &ByteArray,
+        coins: &ByteArray,
+    ) -> Result<(ByteArray, ByteArray), String> {
+        if pk.data.len() != Self::PK_BYTES {
+            return Err(format!("public key must be {} bytes", Self::PK_BYTES));
+        }
+        if coins.data.len() != Self::COINS_BYTES {
+            return Err(format!("randomness must be {} bytes", Self::COINS_BYTES));
+        }
+        let (pk_kem, pk_dh) = pk.split_at(P::PK_BYTES);
+        let (m, eph_seed) = coins.split_at(32);
+        validate_public_key::<P>(&pk_kem)?;
+
+        let (ct_kem, ss_kem) = self.kem.encaps_derand(&pk_kem, &m);
+        let eph = D::secret_key(&eph_seed);
+        let ct_dh = D::public_key(&eph);
+        let ss_dh = D::dh(&eph, &pk_dh)?;
+
+        let ss = Self::combine(&ss_kem, &ss_dh, &ct_kem, &ct_dh, &pk_kem, &pk_dh);
+        Ok((ct_kem.append(&ct_dh), ss))
+    }
+
+    /// Decapsulation : ciphertext, secret key => shared secret
+    pub fn decaps(&self, ct: &ByteArray, sk: &ByteArray) -> Result<ByteArray, String> {
+        if ct.data.len() != Self::CT_BYTES {
+            return Err(format!("ciphertext must be {} bytes", Self::CT_BYTES));
+        }
+        if sk.data.len() != Self::SK_BYTES {
+            return Err(format!("secret key must be {} bytes", Self::SK_BYTES));
+        }
+        let (ct_kem, ct_dh) = ct.split_at(P::CT_BYTES);
+        let (sk_kem, sk_dh) = sk.split_at(P::SK_BYTES);
+        validate_ciphertext::<P>(&ct_kem)?;
+
+        let ss_kem = self.kem.decaps_checked(&ct_kem, &sk_kem)?;
+        let ss_dh = D::dh(&sk_dh, &ct_dh)?;
+
+        // The KEM public key is embedded in its secret key
+        let (_, pk_kem) = sk_kem.split_at(P::PKE_SK_BYTES);
+        let pk_kem = pk_kem.truncate(P::PK_BYTES);
+        let pk_dh = D::public_key(&sk_dh);
+
+        Ok(Self::combine(
+            &ss_kem, &ss_dh, &ct_kem, &ct_dh, &pk_kem, &pk_dh,
+        ))
+    }
+
+    /// Label binding the shared secret to the pair of algorithms
+    fn label() -> String {
+        format!("kybe-rs hybrid {}+kyber{}", D::NAME, P::K * P::N)
+    }
+
+    /// KDF combiner of the two shared secrets
+    fn combine(
+        ss_kem: &ByteArray,
+        ss_dh: &ByteArray,
+        ct_kem: &ByteArray,
+        ct_dh: &ByteArray,
+        pk_kem: &ByteArray,
+        pk_dh: &ByteArray,
+    ) -> ByteArray {
+        let label = ByteArray::from_bytes(Self::label().as_bytes());
+        let input = ByteArray::concat(&[&label, ss_kem, ss_dh, ct_kem, ct_dh, pk_kem, pk_dh]);
+
+        ByteArray::from_bytes(&sha3_256(&input.data))
+    }
+
+    /// Instantiate the hybrid on top of a KEM
+    pub const fn init(kem: KEM<P, N, K>) -> Self {
+        Self {
+            kem,
+            dh: PhantomData,
+        }
+    }
+}
+
+/// X25519 + Kyber512
+pub type X25519Kyber512 = HybridKem<X25519, Kyber512, 256, 2>;
+/// X25519 + Kyber768
+pub type X25519Kyber768 = HybridKem<X25519, Kyber768, 256, 3>;
+/// X25519 + Kyber1024
+pub type X25519Kyber1024 = HybridKem<X25519, Kyber1024, 256, 4>;
+
+/// Instantiate X25519 + Kyber512
+pub const fn x25519_kyber512() -> X25519Kyber512 {
+    HybridKem::init(kyber512kem())
+}
+
+/// Instantiate X25519 + Kyber768
+pub const fn x25519_kyber768() -> X25519Kyber768 {
+    HybridKem::init(kyber768kem())
+}
+
+/// Instantiate X25519 + Kyber1024
+pub const fn x25519_kyber1024() -> X25519Kyber1024 {
+    HybridKem::init(kyber1024kem())
+}
+
+#[cfg(test)]
+fn roundtrip<P: ParameterSet, const N: usize, const K: usize>(hybrid: HybridKem<X25519, P, N, K>) {
+    let (sk, pk) = hybrid.keygen();
+    assert_eq!(pk.data.len(), HybridKem::<X25519, P, N, K>::PK_BYTES);
+    assert_eq!(sk.data.len(), HybridKem::<X25519, P, N, K>::SK_BYTES);
+
+    let (ct, ss) = hybrid.encaps(&pk).unwrap();
+    assert_eq!(ct.data.len(), HybridKem::<X25519, P, N, K>::CT_BYTES);
+    assert_eq!(hybrid.decaps(&ct, &sk).unwrap(), ss);
+
+    // Tampering with either component changes the shared secret
+    for pos in [0, P::CT_BYTES + 1].iter() {
+        let mut tampered = ct.clone();
+        tampered.data[*pos] ^= 1;
+        assert_ne!(hybrid.decaps(&tampered, &sk).unwrap(), ss);
+    }
+
+    assert!(hybrid.encaps(&pk.truncate(10)).is_err());
+    assert!(hybrid.decaps(&ct.truncate(10), &sk).is_err());
+    assert!(hybrid.decaps(&ct, &sk.truncate(10)).is_err());
+}
+
+#[test]
+fn hybrid_roundtrip() {
+    roundtrip(x25519_kyber512());
+    roundtrip(x25519_kyber768());
+    roundtrip(x25519_kyber1024());
+}
+
+#[test]
+fn hybrid_test_vector() {
+    use crate::formats::hex;
+
+    let hybrid = x25519_kyber768();
+    let seed = ByteArray::from_bytes(&(0..96).collect::<Vec<u8>>());
+    let coins = ByteArray::from_bytes(&(96..160).collect::<Vec<u8>>());
+
+    let (sk, pk) = hybrid.keygen_derand(&seed).unwrap();
+    let (ct, ss) = hybrid.encaps_derand(&pk, &coins).unwrap();
+    assert_eq!(hybrid.decaps(&ct, &sk).unwrap(), ss);
+
+    // Regression values generated by this implementation: digests of the public key and
+    // ciphertext, and the shared secret
+    assert_eq!(
+        hex::encode(&sha3_256(&pk.data)),
+        "bcf5bb14ab6b566a16376b5d65eedb254b5f9178356ce217092b384acac74b54"
+    );
+    assert_eq!(
+        hex::encode(&sha3_256(&ct.data)),
+        "882aaa2a8c3c781a0ddba85785dca1aaf47b53e6b99380c5315c7f69fd2541d9"
+    );
+    assert_eq!(
+        hex::encode(&ss.data),
+        "8cf6b90baec225dc29286dd52079ea1e2fdd8eb13ccb7cc750a1b05e7da83f0f"
+    );
+}
@@ -0,0 +1,97 @@
+//! X25519
+//!
+//! Diffie-Hellman on Curve25519 (RFC 7748)
+
+use crate::hybrid::DiffieHellman;
+use crate::structures::ByteArray;
+
+use std::convert::TryInto;
+use x25519_dalek::{x25519, X25519_BASEPOINT_BYTES};
+
+/// X25519 Diffie-Hellman
+pub struct X25519;
+
+/// Read a 32-byte value, inputs of another size are rejected by the callers
+pub(crate) fn bytes32(x: &[u8]) -> [u8; 32] {
+    let mut out = [0; 32];
+    let len = x.len().min(32);
+    out[..len].copy_from_slice(&x[..len]);
+    out
+}
+
+impl DiffieHellman for X25519 {
+    const NAME: &'static str = "x25519";
+    const SK_BYTES: usize = 32;
+    const PK_BYTES: usize = 32;
+
+    fn secret_key(seed: &ByteArray) -> ByteArray {
+        // Scalars are clamped by the scalar multiplication
+        seed.clone()
+    }
+
+    fn public_key(sk: &ByteArray) -> ByteArray {
+        ByteArray::from_bytes(&x25519(bytes32(&sk.data), X25519_BASEPOINT_BYTES))
+    }
+
+    fn dh(sk: &ByteArray, pk: &ByteArray) -> Result<ByteArray, String> {
+        let pk: [u8; 32] = pk.data[..]
+            .try_into()
+            .map_err(|_| "X25519 public key must be 32 bytes".to_string())?;
+
+        let ss = x25519(bytes32(&sk.data), pk);
+        // Low order points give the all-zero shared secret (RFC 7748 section 6.1)
+        if ss.iter().all(|b| *b == 0) {
+            return Err("X25519 shared secret is zero".to_string());
+        }
+        Ok(ByteArray::from_bytes(&ss))
+    }
+}
+
+/// X25519 key pairs of RFC 7748 section 6.1 and their shared secret
+///
+/// The test vectors of the hybrid drafts use ML-KEM and cannot be reproduced, so the
+/// tests built on these keys compare with regression values of this implementation.
+#[cfg(test)]
+pub(crate) struct Rfc7748 {
+    pub alice: ByteArray,
+    pub alice_pk: ByteArray,
+    pub bob: ByteArray,
+    pub bob_pk: ByteArray,
+    pub shared: ByteArray,
+}
+
+#[cfg(test)]
+impl Rfc7748 {
+    pub fn keys() -> Self {
+        use crate::formats::hex;
+
+        let key = |s: &str| ByteArray::from_bytes(&hex::decode(s).unwrap());
+        Self {
+            alice: key("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a"),
+            alice_pk: key("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a"),
+            bob: key("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb"),
+            bob_pk: key("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f"),
+            shared: key("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742"),
+        }
+    }
+}
+
+#[test]
+fn x25519_rfc7748() {
+    let Rfc7748 {
+        alice,
+        alice_pk,
+        bob,
+        bob_pk,
+        shared,
+    } = Rfc7748::keys();
+
+    assert_eq!(X25519::public_key(&alice), alice_pk);
+    assert_eq!(X25519::public_key(&bob), bob_pk);
+    assert_eq!(X25519::dh(&alice, &bob_pk).unwrap(), shared);
+    assert_eq!(X25519::dh(&bob, &alice_pk).unwrap(), shared);
+
+    // Low order point
+    assert!(X25519::dh(&alice, &ByteArray::from_bytes(&[0; 32])).is_err());
+    assert!(X25519::dh(&alice, &ByteArray::from_bytes(&[9; 31])).is_err());
+}
@@ -0,0 +1,109 @@
+//! X448
+//!
+//! Diffie-Hellman on Curve448 (RFC 7748), backed by the `x448` crate.
+
+use crate::hybrid::DiffieHellman;
+use crate::structures::ByteArray;
+
+use x448::{x448, X448_BASEPOINT_BYTES};
+
+/// Read a 56-byte value, inputs of another size are rejected by the callers
+fn bytes56(x: &ByteArray) -> [u8; 56] {
+    let mut out = [0; 56];
+    let len = x.data.len().min(56);
+    out[..len].copy_from_slice(&x.data[..len]);
+    out
+}
+
+/// X448 Diffie-Hellman
+pub struct X448;
+
+impl DiffieHellman for X448 {
+    const NAME: &'static str = "x448";
+    const SK_BYTES: usize = 56;
+    const PK_BYTES: usize = 56;
+
+    fn secret_key(seed: &ByteArray) -> ByteArray {
+        // Scalars are clamped by the scalar multiplication
+        seed.clone()
+    }
+
+    fn public_key(sk: &ByteArray) -> ByteArray {
+        // Unwraps safely since the base point does not have a low order
+        ByteArray::from_bytes(&x448(bytes56(sk), X448_BASEPOINT_BYTES).unwrap())
+    }
+
+    fn dh(sk: &ByteArray, pk: &ByteArray) -> Result<ByteArray, String> {
+        if pk.data.len() != 56 {
+            return Err("X448 public key must be 56 bytes".to_string());
+        }
+
+        // Low order points give the all-zero shared secret (RFC 7748 section 6.2)
+        match x448(bytes56(sk), bytes56(pk)) {
+            Some(ss) if ss.iter().any(|b| *b != 0) => Ok(ByteArray::from_bytes(&ss)),
+            _ => Err("X448 shared secret is zero".to_string()),
+        }
+    }
+}
+
+#[test]
+fn x448_rfc7748() {
+    use crate::formats::hex;
+
+    let key = |s: &str| ByteArray::from_bytes(&hex::decode(s).unwrap());
+
+    // Section 5.2
+    let scalar = key("3d262fddf9ec8e88495266fea19a34d28882acef045104d0d1aae121700a779c984c24f8cdd78fbff44943eba368f54b29259a4f1c600ad3");
+    let u = key("06fce640fa3487bfda5f6cf2d5263f8aad88334cbd07437f020f08f9814dc031ddbdc38c19c6da2583fa5429db94ada18aa7a7fb4ef8a086");
+    assert_eq!(
+        X448::dh(&scalar, &u).unwrap(),
+        key("ce3e4ff95a60dc6697da1db1d85e6afbdf79b50a2412d7546d5f239fe14fbaadeb445fc66a01b0779d98223961111e21766282f73dd96b6f")
+    );
+
+    // Section 6.2
+    let alice = key("9a8f4925d1519f5775cf46b04b5800d4ee9ee8bae8bc5565d498c28dd9c9baf574a9419744897391006382a6f127ab1d9ac2d8c0a598726b");
+    let bob = key("1c306a7ac2a0e2e0990b294470cba339e6453772b075811d8fad0d1d6927c120bb5ee8972b0d3e21374c9c921b09d1b0366f10b65173992d");
+    let alice_pk = X448::public_key(&alice);
+    let bob_pk = X448::public_key(&bob);
+    assert_eq!(
+        alice_pk,
+        key("9b08f7cc31b7e3e67d22d5aea121074a273bd2b83de09c63faa73d2c22c5d9bbc836647241d953d40c5b12da88120d53177f80e532c41fa0")
+    );
+    assert_eq!(
+        bob_pk,
+        key("3eb7a829b0cd20f5bcfc0b599b6feccf6da4627107bdb0d4f345b43027d8b972fc3e34fb4232a13ca706dcb57aec3dae07bdc1c67bf33609")
+    );
+
+    let shared = key("07fff4181ac6cc95ec1c16a94a0f74d12da232ce40a77552281d282bb60c0b56fd2464c335543936521c24403085d59a449a5037514a879d");
+    assert_eq!(X448::dh(&alice, &bob_pk).unwrap(), shared);
+    assert_eq!(X448::dh(&bob, &alice_pk).unwrap(), shared);
+
+    // Low order point and wrong size
+    assert!(X448::dh(&alice, &ByteArray::from_bytes(&[0; 56])).is_err());
+    assert!(X448::dh(&alice, &ByteArray::from_bytes(&[5; 32])).is_err());
+}
+
+#[test]
+fn x448_iterated() {
+    use crate::formats::hex;
+
+    // Section 5.2: k and u start at 5, then k takes the output and u the previous k
+    let mut k = [0; 56];
+    k[0] = 5;
+    let mut u = k;
+    for i in 1..=1000 {
+        let out = x448::x448_unchecked(k, u);
+        u = k;
+        k = out;
+        if i == 1 {
+            assert_eq!(
+                hex::encode(&k),
+                "3f482c8a9f19b01e6c46ee9711d9dc14fd4bf67af30765c2ae2b846a4d23a8cd0db897086239492caf350b51f833868b9bc2b3bca9cf4113"
+            );
+        }
+    }
+    assert_eq!(
+        hex::encode(&k),
+        "aa3b4749d55b9daf1e5b00288826c467274ce3ebbdd5c17b975e09d4af6c67cf10d087202db88286e2b79fceea3ec353ef54faa26e219f38"
+    );
+}
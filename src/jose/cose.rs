@@ -0,0 +1,259 @@
+//! COSE
+//!
+//! `COSE_Encrypt` (RFC 9052 section 5.1) with a single KEM recipient. The recipient carries
+//! the KEM ciphertext in the unprotected `ek` header; the key is derived from the shared
+//! secret with HKDF-SHA256 over a `COSE_KDF_Context` (RFC 9053 section 5.2).
+
+use super::keys::{cbor_bytes, cbor_decode, cbor_encode, cbor_int, map_get};
+use super::{AkpKey, KemAlgorithm, KeyManagement};
+use crate::hpke::{Aead, Aes256GcmAead};
+use crate::structures::ByteArray;
+
+use ciborium::value::Value;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use std::convert::TryFrom;
+
+/// CBOR tag of `COSE_Encrypt`
+pub const COSE_ENCRYPT_TAG: u64 = 96;
+
+/// Content encryption algorithm A256GCM
+pub const A256GCM: i64 = 3;
+
+/// Provisional `ek` header label
+pub const EK: i64 = -4;
+
+/// Common header labels
+const ALG: i64 = 1;
+const IV: i64 = 5;
+
+/// Key wrap algorithm identifiers
+const A128KW: i64 = -3;
+const A256KW: i64 = -5;
+
+/// Size of the initialization vector
+const IV_BYTES: usize = 12;
+
+/// Size of the content-encryption key
+const CEK_BYTES: usize = 32;
+
+/// Serialized protected header with a single algorithm
+fn protected(alg: i64) -> Vec<u8> {
+    cbor_encode(&Value::Map(vec![(Value::from(ALG), Value::from(alg))]))
+}
+
+/// Algorithm of a serialized protected header
+fn protected_alg(data: &[u8]) -> Result<i64, String> {
+    match cbor_decode(data)? {
+        Value::Map(map) => cbor_int(map_get(&map, ALG).ok_or("missing COSE algorithm")?),
+        _ => Err("COSE protected header is not a map".to_string()),
+    }
+}
+
+/// Derive the key of a recipient from the KEM shared secret
+///
+/// The context algorithm is the content encryption algorithm in direct mode and the key
+/// wrap algorithm otherwise.
+fn derive(alg: KemAlgorithm, ss: &ByteArray, recipient_protected: &[u8]) -> Vec<u8> {
+    let algorithm_id = match alg.mode {
+        KeyManagement::Direct => A256GCM,
+        KeyManagement::A128KW => A128KW,
+        KeyManagement::A256KW => A256KW,
+    };
+    let len = alg.mode.key_bytes();
+    let party = || Value::Array(vec![Value::Null, Value::Null, Value::Null]);
+    let context = Value::Array(vec![
+        Value::from(algorithm_id),
+        party(),
+        party(),
+        Value::Array(vec![
+            Value::from(8 * len as u64),
+            Value::from(recipient_protected.to_vec()),
+        ]),
+    ]);
+
+    let mut key = vec![0; len];
+    // The length is at most 32 bytes
+    Hkdf::<Sha256>::new(None, &ss.data)
+        .expand(&cbor_encode(&context), &mut key)
+        .unwrap();
+    key
+}
+
+/// Serialized `Enc_structure`, the AEAD associated data
+fn enc_structure(body_protected: &[u8], external_aad: &[u8]) -> Vec<u8> {
+    cbor_encode(&Value::Array(vec![
+        Value::from("Encrypt"),
+        Value::from(body_protected.to_vec()),
+        Value::from(external_aad.to_vec()),
+    ]))
+}
+
+/// Encrypt `plaintext` to the recipient key => tagged `COSE_Encrypt`
+pub fn encrypt(
+    alg: KemAlgorithm,
+    recipient: &AkpKey,
+    plaintext: &[u8],
+    external_aad: &[u8],
+) -> Result<Vec<u8>, String> {
+    if alg.kem != recipient.algorithm {
+        return Err("algorithm does not match the recipient key".to_string());
+    }
+    let (c, ss) = alg.kem.encaps(&recipient.public)?;
+
+    let recipient_protected = protected(alg.cose_id());
+    let key = derive(alg, &ss, &recipient_protected);
+    let (cek, encrypted_key) = match alg.mode {
+        KeyManagement::Direct => (key, Vec::new()),
+        mode => {
+            let cek = ByteArray::random(CEK_BYTES).data;
+            let wrapped = mode.wrap(&key, &cek)?;
+            (cek, wrapped)
+        }
+    };
+
+    let body_protected = protected(A256GCM);
+    let iv = ByteArray::random(IV_BYTES).data;
+    let aad = enc_structure(&body_protected, external_aad);
+    let ct = Aes256GcmAead::seal(&cek, &iv, &aad, plaintext)?;
+
+    let recipient = Value::Array(vec![
+        Value::from(recipient_protected),
+        Value::Map(vec![(Value::from(EK), Value::from(c.data))]),
+        Value::from(encrypted_key),
+    ]);
+    let message = Value::Array(vec![
+        Value::from(body_protected),
+        Value::Map(vec![(Value::from(IV), Value::from(iv))]),
+        Value::from(ct),
+        Value::Array(vec![recipient]),
+    ]);
+
+    Ok(cbor_encode(&Value::Tag(
+        COSE_ENCRYPT_TAG,
+        Box::new(message),
+    )))
+}
+
+/// Decrypt a tagged `COSE_Encrypt` with the recipient key pair
+pub fn decrypt(key: &AkpKey, data: &[u8], external_aad: &[u8]) -> Result<Vec<u8>, String> {
+    let message = match cbor_decode(data)? {
+        Value::Tag(COSE_ENCRYPT_TAG, message) => *message,
+        _ => return Err("not a tagged COSE_Encrypt".to_string()),
+    };
+    let (body_protected, unprotected, ct, recipients) = match message {
+        Value::Array(items) => match <[Value; 4]>::try_from(items) {
+            Ok([Value::Bytes(p), Value::Map(u), Value::Bytes(ct), Value::Array(r)]) => {
+                (p, u, ct, r)
+            }
+            _ => return Err("invalid COSE_Encrypt".to_string()),
+        },
+        _ => return Err("invalid COSE_Encrypt".to_string()),
+    };
+    if protected_alg(&body_protected)? != A256GCM {
+        return Err("unsupported COSE content encryption".to_string());
+    }
+    let iv = cbor_bytes(map_get(&unprotected, IV).ok_or("missing COSE IV")?)?;
+
+    let (recipient_protected, recipient_unprotected, encrypted_key) = match &recipients[..] {
+        [Value::Array(recipient)] => match &recipient[..] {
+            [Value::Bytes(p), Value::Map(u), Value::Bytes(k)] => (p, u, k),
+            _ => return Err("invalid COSE recipient".to_string()),
+        },
+        _ => return Err("expected a single COSE recipient".to_string()),
+    };
+    let alg = KemAlgorithm::from_cose_id(protected_alg(recipient_protected)?)?;
+    if alg.kem != key.algorithm {
+        return Err("algorithm does not match the recipient key".to_string());
+    }
+    let c = cbor_bytes(map_get(recipient_unprotected, EK).ok_or("missing COSE ek")?)?;
+    let ss = alg
+        .kem
+        .decaps(&ByteArray::from_bytes(c), &key.secret_key()?)?;
+
+    let derived = derive(alg, &ss, recipient_protected);
+    let cek = match alg.mode {
+        KeyManagement::Direct if encrypted_key.is_empty() => derived,
+        KeyManagement::Direct => return Err("unexpected COSE encrypted key".to_string()),
+        mode => mode.unwrap(&derived, encrypted_key)?,
+    };
+
+    let aad = enc_structure(&body_protected, external_aad);
+    Aes256GcmAead::open(&cek, iv, &aad, &ct)
+}
+
+#[test]
+fn jose_cose_encrypt_roundtrip() {
+    for kem in super::KEMS.iter() {
+        let key = AkpKey::generate(*kem);
+        for mode in super::MODES.iter() {
+            let alg = KemAlgorithm::new(*kem, *mode);
+            let message = encrypt(alg, &key.public_key(), b"This is the content.", b"aad").unwrap();
+            assert_eq!(
+                decrypt(&key, &message, b"aad").unwrap(),
+                b"This is the content."
+            );
+            // Tag 96 and an array of four items
+            assert_eq!(message[..2], [0xd8, 0x60]);
+            assert_eq!(message[2], 0x84);
+        }
+    }
+}
+
+#[test]
+fn jose_cose_kdf_context() {
+    // [3, [null, null, null], [null, null, null], [256, h'a1013a00011183']]
+    let alg = KemAlgorithm::new(
+        crate::formats::keys::Algorithm::Kyber768,
+        KeyManagement::Direct,
+    );
+    let recipient_protected = protected(alg.cose_id());
+    assert_eq!(
+        recipient_protected,
+        [0xa1, 0x01, 0x3a, 0x00, 0x01, 0x11, 0x83]
+    );
+    let ss = ByteArray::from_bytes(&[0; 32]);
+    let context = [
+        &[
+            0x84, 0x03, 0x83, 0xf6, 0xf6, 0xf6, 0x83, 0xf6, 0xf6, 0xf6, 0x82, 0x19, 0x01, 0x00,
+            0x47,
+        ][..],
+        &recipient_protected,
+    ]
+    .concat();
+    let mut expected = [0; 32];
+    Hkdf::<Sha256>::new(None, &ss.data)
+        .expand(&context, &mut expected)
+        .unwrap();
+    assert_eq!(derive(alg, &ss, &recipient_protected), expected);
+}
+
+#[test]
+fn jose_cose_encrypt_invalid() {
+    let key = AkpKey::generate(crate::formats::keys::Algorithm::Kyber1024);
+    let other = AkpKey::generate(crate::formats::keys::Algorithm::Kyber1024);
+    let alg = KemAlgorithm::new(key.algorithm, KeyManagement::A128KW);
+    let message = encrypt(alg, &key, b"content", b"").unwrap();
+
+    assert!(decrypt(&other, &message, b"").is_err());
+    assert!(decrypt(&key.public_key(), &message, b"").is_err());
+    assert!(decrypt(&key, &message, b"other aad").is_err());
+    assert!(decrypt(&key, &message[2..], b"").is_err());
+    assert!(decrypt(&key, &message[..message.len() - 1], b"").is_err());
+
+    // Altered recipient algorithm: Kyber1024 with A128KW to Kyber1024 with A256KW
+    let recipient_protected = protected(alg.cose_id());
+    let position = message
+        .windows(recipient_protected.len())
+        .position(|w| w == &recipient_protected[..])
+        .unwrap();
+    let mut altered = message.clone();
+    altered[position + recipient_protected.len() - 1] += 1;
+    assert!(decrypt(&key, &altered, b"").is_err());
+
+    // Flipped ciphertext bit
+    let mut altered = message.clone();
+    altered[20] ^= 1;
+    assert!(decrypt(&key, &altered, b"").is_err());
+}
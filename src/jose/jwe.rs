@@ -0,0 +1,199 @@
+//! JWE
+//!
+//! Compact serialization of a JWE to a KEM recipient. The protected header carries the KEM
+//! ciphertext in the `ek` member; the key is derived from the shared secret with the Concat
+//! KDF, as for `ECDH-ES` (RFC 7518 section 4.6).
+
+use super::{base64url_decode, base64url_encode, concat_kdf, AkpKey, KemAlgorithm, KeyManagement};
+use crate::hpke::{Aead, Aes256GcmAead};
+use crate::structures::ByteArray;
+
+use serde_json::json;
+
+/// Content encryption algorithm
+pub const ENC: &str = "A256GCM";
+
+/// Size of the content-encryption key
+const CEK_BYTES: usize = 32;
+
+/// Size of the initialization vector
+const IV_BYTES: usize = 12;
+
+/// Size of the authentication tag
+const TAG_BYTES: usize = 16;
+
+/// Derive the key of a recipient from the KEM shared secret
+///
+/// As with ECDH-ES, the algorithm identifier is `enc` in direct mode and `alg` otherwise.
+fn derive(alg: KemAlgorithm, ss: &ByteArray) -> Vec<u8> {
+    let algorithm_id = match alg.mode {
+        KeyManagement::Direct => ENC.to_string(),
+        _ => alg.name(),
+    };
+    concat_kdf(&ss.data, &algorithm_id, &[], &[], alg.mode.key_bytes())
+}
+
+/// Encrypt `plaintext` to the recipient key => JWE compact serialization
+pub fn encrypt(alg: KemAlgorithm, recipient: &AkpKey, plaintext: &[u8]) -> Result<String, String> {
+    if alg.kem != recipient.algorithm {
+        return Err("algorithm does not match the recipient key".to_string());
+    }
+    let (c, ss) = alg.kem.encaps(&recipient.public)?;
+
+    let header = json!({
+        "alg": alg.name(),
+        "enc": ENC,
+        "ek": base64url_encode(&c.data),
+    });
+    let header = base64url_encode(header.to_string().as_bytes());
+
+    let key = derive(alg, &ss);
+    let (cek, encrypted_key) = match alg.mode {
+        KeyManagement::Direct => (key, Vec::new()),
+        mode => {
+            let cek = ByteArray::random(CEK_BYTES).data;
+            let wrapped = mode.wrap(&key, &cek)?;
+            (cek, wrapped)
+        }
+    };
+
+    let iv = ByteArray::random(IV_BYTES).data;
+    let mut ct = Aes256GcmAead::seal(&cek, &iv, header.as_bytes(), plaintext)?;
+    let tag = ct.split_off(ct.len() - TAG_BYTES);
+
+    Ok([
+        header,
+        base64url_encode(&encrypted_key),
+        base64url_encode(&iv),
+        base64url_encode(&ct),
+        base64url_encode(&tag),
+    ]
+    .join("."))
+}
+
+/// Decrypt a JWE compact serialization with the recipient key pair
+pub fn decrypt(key: &AkpKey, jwe: &str) -> Result<Vec<u8>, String> {
+    let parts: Vec<&str> = jwe.split('.').collect();
+    let (header, encrypted_key, iv, ct, tag) = match parts[..] {
+        [header, encrypted_key, iv, ct, tag] => (header, encrypted_key, iv, ct, tag),
+        _ => return Err("JWE must have five parts".to_string()),
+    };
+
+    let protected: serde_json::Value =
+        serde_json::from_slice(&base64url_decode(header)?).map_err(|e| e.to_string())?;
+    let member = |name: &str| {
+        protected
+            .get(name)
+            .and_then(|v| v.as_str())
+            .ok_or(format!("missing or invalid JWE header {:?}", name))
+    };
+    // Critical extensions are not supported
+    if protected.get("crit").is_some() {
+        return Err("unsupported critical JWE header".to_string());
+    }
+    let alg = KemAlgorithm::from_name(member("alg")?)?;
+    if alg.kem != key.algorithm {
+        return Err("algorithm does not match the recipient key".to_string());
+    }
+    if member("enc")? != ENC {
+        return Err("unsupported JWE content encryption".to_string());
+    }
+    let c = ByteArray::from_bytes(&base64url_decode(member("ek")?)?);
+    let ss = alg.kem.decaps(&c, &key.secret_key()?)?;
+
+    let derived = derive(alg, &ss);
+    let encrypted_key = base64url_decode(encrypted_key)?;
+    let cek = match alg.mode {
+        KeyManagement::Direct if encrypted_key.is_empty() => derived,
+        KeyManagement::Direct => return Err("unexpected JWE encrypted key".to_string()),
+        mode => mode.unwrap(&derived, &encrypted_key)?,
+    };
+
+    let iv = base64url_decode(iv)?;
+    let ct = [base64url_decode(ct)?, base64url_decode(tag)?].concat();
+    Aes256GcmAead::open(&cek, &iv, header.as_bytes(), &ct)
+}
+
+#[test]
+fn jose_jwe_roundtrip() {
+    for kem in super::KEMS.iter() {
+        let key = AkpKey::generate(*kem);
+        for mode in super::MODES.iter() {
+            let alg = KemAlgorithm::new(*kem, *mode);
+            let jwe = encrypt(alg, &key.public_key(), b"Live long and prosper.").unwrap();
+            assert_eq!(decrypt(&key, &jwe).unwrap(), b"Live long and prosper.");
+
+            let parts: Vec<&str> = jwe.split('.').collect();
+            assert_eq!(parts[1].is_empty(), *mode == KeyManagement::Direct);
+            assert_eq!(parts[2].len(), 16);
+            assert_eq!(parts[4].len(), 22);
+        }
+    }
+}
+
+#[test]
+fn jose_jwe_invalid() {
+    let key = AkpKey::generate(crate::formats::keys::Algorithm::Kyber768);
+    let other = AkpKey::generate(crate::formats::keys::Algorithm::Kyber768);
+    let alg = KemAlgorithm::new(key.algorithm, KeyManagement::A256KW);
+    let jwe = encrypt(alg, &key, b"payload").unwrap();
+    let parts: Vec<&str> = jwe.split('.').collect();
+    let replaced = |index: usize, part: &str| {
+        let mut parts = parts.clone();
+        parts[index] = part;
+        decrypt(&key, &parts.join("."))
+    };
+
+    // Wrong key, public key only, mismatched algorithm
+    assert!(decrypt(&other, &jwe).is_err());
+    assert!(decrypt(&key.public_key(), &jwe).is_err());
+    let small = AkpKey::generate(crate::formats::keys::Algorithm::Kyber512);
+    assert!(decrypt(&small, &jwe).is_err());
+    assert!(encrypt(alg, &small, b"payload").is_err());
+
+    // Altered header, encrypted key, ciphertext and tag
+    let header = |value: serde_json::Value| base64url_encode(value.to_string().as_bytes());
+    let protected: serde_json::Value =
+        serde_json::from_slice(&base64url_decode(parts[0]).unwrap()).unwrap();
+    let mut direct = protected.clone();
+    direct["alg"] = json!("kybe-rs/Kyber768");
+    assert!(replaced(0, &header(direct)).is_err());
+    let mut enc = protected.clone();
+    enc["enc"] = json!("A128GCM");
+    assert!(replaced(0, &header(enc)).is_err());
+    let mut crit = protected;
+    crit["crit"] = json!(["exp"]);
+    assert!(replaced(0, &header(crit)).is_err());
+    assert!(replaced(1, "").is_err());
+    assert!(replaced(3, "AAAA").is_err());
+    assert!(replaced(4, &base64url_encode(&[0; 16])).is_err());
+    assert!(decrypt(&key, &parts[..4].join(".")).is_err());
+}
+
+#[test]
+fn jose_jwe_vector() {
+    // Regression vector: kybe-rs/Kyber512+A128KW to the key of the seed 00 01 .. 3f
+    let seed = ByteArray::from_bytes(&(0..64).collect::<Vec<u8>>());
+    let key = AkpKey::from_seed(crate::formats::keys::Algorithm::Kyber512, &seed).unwrap();
+    let jwe = concat!(
+        "eyJhbGciOiJreWJlLXJzL0t5YmVyNTEyK0ExMjhLVyIsImVrIjoiM2QyX1hrTno5WjhSVWUzelU3MVdYZnV5YTg2",
+        "NWV4YVJndWs5Z0tpbEpQb2ZvWk9mLTRyWGNnNHlqNnNPZlQ2WmdpZGdkc0dCRldNZDNEUS1PQnRzR3RWbVVEdGZp",
+        "aUxDNkhnMkI3UHZURVcwc05BSmJqaHpJcmR5OWNkQ0xveWNWUXpxTWcxeXBNYXFvN3FFQXh2dUpIbDFSTkFlNVZF",
+        "YWFjamM3dTRQc0VhbGJ6SFZxY1NPMG5Vb1AyY1BodVFsV3RkeXpKd1JvWEV6LTNoVUV1VEpIRk1WYVY0aThWUE5T",
+        "RTVtM2xWN281ZndUZlFjY3pBOHZ5RnNyMTN1eDlta0NUaEtfSnRrUGRaeDhHYzhPX0VUWjQ0UDlrLXZ1RnNKRmFl",
+        "WXU1dEJsSE5sMWdfaF9MR2MydFgwcVdWM3NvQ2xQWEZjcmZQWkRLM1RRQXZ6YWxKalo1MTZWOGMyM1ZVNFR6eXVP",
+        "SzBSYmhhbGE1TDVXQUdPeVRtRkVsNlVBMmRhWW1mMy1yVDFtWnpwdW92aGI3TUdIYmhNMkp1VlR4ZzJaTUl5T3Vx",
+        "Szd4OVZMbTVCc2tXVDVpVEhLaDdZb2dfOXA3THh6a2VNekJtVk1acnBzNURaRkNMUF9EU2RBVGg5M3A4N1FVTTNh",
+        "bXlvX1FPNGdxbmlnRGdsektmM243eTU1NGdPTzZfMXBHVXZaR3JkbG5OY2JSZ2Y2TktjWEhsN3pISEVCU2dFTkRQ",
+        "bG5YM3JZTE1RbmtVOGE4cVk0MzFIUFQwMlNEM3RGaWVETWhPbGVjdEhnMFV5OFphRTQtMDhUVy1mWm5HZ0xDNG5I",
+        "VkpRT2FCVDZENmFSZ3MxU251YTdNTFRqX0Q2S0pzN25DYy1HSUlLWnJ6UnozdGpfZnBKRlItbUIxZVlKZ1FjX1lt",
+        "RW9UNUNIV3J1MUtQRUZDVjZVaHpIVVZnNFQ3X1RCakhZaWNLbGQ4YXBrS0psekhCdUx4N0tGVTBtd0JaNXVMeHlq",
+        "bVBkSlduTGdHWGV3WXRUb0hpV245TVhrOUVka3lvc0xiR3p3bTJpd1c4eGtlUzRycnFmYjBHTzYwQk9VMllxWnly",
+        "RVhabGhfdDdaVXcwS0ppNmt2NnF1T2RXcGhydXlRVWJUVkEyaVVld2xzcXZoMWRmaXU2SU1GTEFZa3lsWjRsbXE3",
+        "TmJyYnFkRmlIM0lKYmRmSFBUd204ZlBFLWhiQUJ1aWN6amRST3JVOVNNd1pTVWYxV01GelNUcWJJNl9hcTdkUmlj",
+        "YzNlc0Y2WDFZbjJvQXNicWtEeFQ2VVZPRlo3OGQ2dyIsImVuYyI6IkEyNTZHQ00ifQ.cnjl8sexuFJK4JOVbnq9e",
+        "iimSsB2vheYppIZ16KjGHV0cXAuJsnQdA.HCWkauAiHOu6o2tK.34OkID5_tQ.WcpKLpfYIk_SigGBDApDUQ",
+    );
+    assert_eq!(decrypt(&key, jwe).unwrap(), b"kybe-rs");
+}
+
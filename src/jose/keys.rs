@@ -0,0 +1,304 @@
+//! Keys
+//!
+//! `AKP` (algorithm key pair) JWK and COSE_Key encodings of Kyber keys: the public key and
+//! the optional 64-byte seed, tagged with the private Kyber algorithm.
+
+use super::{base64url_decode, base64url_encode, KemAlgorithm, KeyManagement};
+use crate::formats::keys::Algorithm;
+use crate::kem::SEED_BYTES;
+use crate::structures::ByteArray;
+
+use ciborium::value::Value;
+use serde_json::json;
+
+use std::convert::TryFrom;
+
+/// Provisional COSE key type `AKP`
+pub const COSE_KTY_AKP: i64 = 7;
+
+/// COSE_Key labels
+const COSE_KTY: i64 = 1;
+const COSE_ALG: i64 = 3;
+const COSE_PUB: i64 = -1;
+const COSE_PRIV: i64 = -2;
+
+/// Kyber key pair, or public key when the seed is absent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AkpKey {
+    /// Kyber algorithm
+    pub algorithm: Algorithm,
+    /// Public key
+    pub public: ByteArray,
+    /// 64-byte seed `d || z`
+    pub seed: Option<ByteArray>,
+}
+
+impl AkpKey {
+    /// Key pair of a 64-byte seed
+    pub fn from_seed(algorithm: Algorithm, seed: &ByteArray) -> Result<Self, String> {
+        let (_, public) = algorithm.expand_seed(seed)?;
+        Ok(Self {
+            algorithm,
+            public,
+            seed: Some(seed.clone()),
+        })
+    }
+
+    /// Random key pair
+    pub fn generate(algorithm: Algorithm) -> Self {
+        // A random seed has the right length
+        Self::from_seed(algorithm, &ByteArray::random(SEED_BYTES)).unwrap()
+    }
+
+    /// Public key
+    pub fn public_key(&self) -> Self {
+        Self {
+            seed: None,
+            ..self.clone()
+        }
+    }
+
+    /// Expanded decapsulation key
+    pub fn secret_key(&self) -> Result<ByteArray, String> {
+        let seed = self.seed.as_ref().ok_or("missing private key")?;
+        Ok(self.algorithm.expand_seed(seed)?.0)
+    }
+
+    /// Check the key sizes and that the seed matches the public key
+    fn check(self) -> Result<Self, String> {
+        if self.public.data.len() != self.algorithm.pk_bytes() {
+            return Err("invalid public key size".to_string());
+        }
+        if let Some(seed) = &self.seed {
+            if self.algorithm.expand_seed(seed)?.1 != self.public {
+                return Err("seed and public key do not match".to_string());
+            }
+        }
+        Ok(self)
+    }
+
+    /// Private JWK `alg` of the key, e.g. `kybe-rs/Kyber768`
+    pub fn jwk_algorithm(&self) -> &'static str {
+        match self.algorithm {
+            Algorithm::Kyber512 => "kybe-rs/Kyber512",
+            Algorithm::Kyber768 => "kybe-rs/Kyber768",
+            Algorithm::Kyber1024 => "kybe-rs/Kyber1024",
+        }
+    }
+
+    /// JWK encoding
+    pub fn to_jwk(&self) -> String {
+        let mut jwk = json!({
+            "kty": "AKP",
+            "alg": self.jwk_algorithm(),
+            "pub": base64url_encode(&self.public.data),
+        });
+        if let Some(seed) = &self.seed {
+            jwk["priv"] = json!(base64url_encode(&seed.data));
+        }
+        jwk.to_string()
+    }
+
+    /// JWK decoding, unknown members are ignored
+    pub fn from_jwk(s: &str) -> Result<Self, String> {
+        let jwk: serde_json::Value = serde_json::from_str(s).map_err(|e| e.to_string())?;
+        let member = |name: &str| {
+            jwk.get(name)
+                .map(|v| v.as_str().ok_or("invalid JWK member"))
+        };
+
+        if member("kty").transpose()? != Some("AKP") {
+            return Err("unsupported JWK key type".to_string());
+        }
+        let algorithm = match member("alg").transpose()? {
+            Some("kybe-rs/Kyber512") => Algorithm::Kyber512,
+            Some("kybe-rs/Kyber768") => Algorithm::Kyber768,
+            Some("kybe-rs/Kyber1024") => Algorithm::Kyber1024,
+            _ => return Err("unsupported JWK algorithm".to_string()),
+        };
+        let public = base64url_decode(member("pub").ok_or("missing JWK public key")??)?;
+        let seed = match member("priv").transpose()? {
+            Some(seed) => Some(ByteArray::from_bytes(&base64url_decode(seed)?)),
+            None => None,
+        };
+
+        Self {
+            algorithm,
+            public: ByteArray::from_bytes(&public),
+            seed,
+        }
+        .check()
+    }
+
+    /// COSE_Key encoding, with the identifier of the direct KEM recipient algorithm
+    pub fn to_cose_key(&self) -> Vec<u8> {
+        let alg = KemAlgorithm::new(self.algorithm, KeyManagement::Direct);
+        let mut map = vec![
+            (Value::from(COSE_KTY), Value::from(COSE_KTY_AKP)),
+            (Value::from(COSE_ALG), Value::from(alg.cose_id())),
+            (Value::from(COSE_PUB), Value::from(self.public.data.clone())),
+        ];
+        if let Some(seed) = &self.seed {
+            map.push((Value::from(COSE_PRIV), Value::from(seed.data.clone())));
+        }
+        cbor_encode(&Value::Map(map))
+    }
+
+    /// COSE_Key decoding, unknown labels are ignored
+    pub fn from_cose_key(data: &[u8]) -> Result<Self, String> {
+        let map = match cbor_decode(data)? {
+            Value::Map(map) => map,
+            _ => return Err("COSE_Key is not a map".to_string()),
+        };
+
+        if cbor_int(map_get(&map, COSE_KTY).ok_or("missing COSE key type")?)? != COSE_KTY_AKP {
+            return Err("unsupported COSE key type".to_string());
+        }
+        let alg = cbor_int(map_get(&map, COSE_ALG).ok_or("missing COSE algorithm")?)?;
+        let alg = KemAlgorithm::from_cose_id(alg)?;
+        if alg.mode != KeyManagement::Direct {
+            return Err("unsupported COSE key algorithm".to_string());
+        }
+        let public = cbor_bytes(map_get(&map, COSE_PUB).ok_or("missing COSE public key")?)?;
+        let seed = match map_get(&map, COSE_PRIV) {
+            Some(seed) => Some(ByteArray::from_bytes(cbor_bytes(seed)?)),
+            None => None,
+        };
+
+        Self {
+            algorithm: alg.kem,
+            public: ByteArray::from_bytes(public),
+            seed,
+        }
+        .check()
+    }
+}
+
+/// Encode a CBOR value
+pub(crate) fn cbor_encode(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    // Writing to a vector does not fail
+    ciborium::ser::into_writer(value, &mut out).unwrap();
+    out
+}
+
+/// Decode a single CBOR value
+pub(crate) fn cbor_decode(data: &[u8]) -> Result<Value, String> {
+    ciborium::de::from_reader(data).map_err(|e| format!("invalid CBOR: {}", e))
+}
+
+/// Value of an integer label in a CBOR map
+pub(crate) fn map_get(map: &[(Value, Value)], label: i64) -> Option<&Value> {
+    map.iter()
+        .find(|(k, _)| matches!(k, Value::Integer(i) if i128::from(*i) == label as i128))
+        .map(|(_, v)| v)
+}
+
+pub(crate) fn cbor_int(value: &Value) -> Result<i64, String> {
+    match value {
+        Value::Integer(i) => {
+            i64::try_from(i128::from(*i)).map_err(|_| "CBOR integer overflow".to_string())
+        }
+        _ => Err("expected a CBOR integer".to_string()),
+    }
+}
+
+pub(crate) fn cbor_bytes(value: &Value) -> Result<&[u8], String> {
+    match value {
+        Value::Bytes(bytes) => Ok(bytes),
+        _ => Err("expected a CBOR byte string".to_string()),
+    }
+}
+
+#[cfg(test)]
+fn test_seed() -> ByteArray {
+    ByteArray::from_bytes(&(0..SEED_BYTES as u8).collect::<Vec<u8>>())
+}
+
+#[test]
+fn jose_jwk_roundtrip() {
+    for algorithm in super::KEMS.iter() {
+        let key = AkpKey::generate(*algorithm);
+        assert_eq!(AkpKey::from_jwk(&key.to_jwk()).unwrap(), key);
+
+        let public = key.public_key();
+        let jwk = public.to_jwk();
+        assert!(!jwk.contains("priv"));
+        assert_eq!(AkpKey::from_jwk(&jwk).unwrap(), public);
+        assert!(public.secret_key().is_err());
+    }
+}
+
+#[test]
+fn jose_jwk_vector() {
+    // The members are serialized in lexicographic order
+    let key = AkpKey::from_seed(Algorithm::Kyber768, &test_seed()).unwrap();
+    let jwk = key.to_jwk();
+    let prefix = format!(
+        "{{\"alg\":\"kybe-rs/Kyber768\",\"kty\":\"AKP\",\"priv\":\"{}\",\"pub\":\"",
+        "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8gISIjJCUmJygpKissLS4vMDEyMzQ1Njc4OTo7PD0-Pw"
+    );
+    assert!(jwk.starts_with(&prefix));
+    assert_eq!(jwk.len(), prefix.len() + 1579 + 2);
+    assert_eq!(
+        base64url_decode(&jwk[prefix.len()..jwk.len() - 2]).unwrap(),
+        key.public.data
+    );
+}
+
+#[test]
+fn jose_jwk_invalid() {
+    let key = AkpKey::from_seed(Algorithm::Kyber512, &test_seed()).unwrap();
+    let jwk: serde_json::Value = serde_json::from_str(&key.to_jwk()).unwrap();
+    let altered = |name: &str, value: serde_json::Value| {
+        let mut jwk = jwk.clone();
+        jwk[name] = value;
+        AkpKey::from_jwk(&jwk.to_string())
+    };
+
+    assert!(altered("kty", json!("OKP")).is_err());
+    assert!(altered("alg", json!("kybe-rs/Kyber768")).is_err());
+    assert!(altered("alg", json!("ML-KEM-512")).is_err());
+    assert!(altered("alg", json!(1)).is_err());
+    assert!(altered("pub", json!("AAAA")).is_err());
+    assert!(altered("pub", json!("+/+/")).is_err());
+    // Seed of another key
+    assert!(altered("priv", json!(base64url_encode(&[1; SEED_BYTES]))).is_err());
+    assert!(altered("priv", json!("AAAA")).is_err());
+    assert!(AkpKey::from_jwk("{\"kty\":\"AKP\",\"alg\":\"kybe-rs/Kyber512\"}").is_err());
+    assert!(AkpKey::from_jwk("not json").is_err());
+}
+
+#[test]
+fn jose_cose_key() {
+    for algorithm in super::KEMS.iter() {
+        let key = AkpKey::generate(*algorithm);
+        assert_eq!(AkpKey::from_cose_key(&key.to_cose_key()).unwrap(), key);
+        let public = key.public_key();
+        assert_eq!(
+            AkpKey::from_cose_key(&public.to_cose_key()).unwrap(),
+            public
+        );
+    }
+
+    // {1: 7, 3: -70020, -1: h'...', -2: h'0001...'}
+    let key = AkpKey::from_seed(Algorithm::Kyber768, &test_seed()).unwrap();
+    let cose = key.to_cose_key();
+    assert_eq!(
+        cose[..13],
+        [0xa4, 0x01, 0x07, 0x03, 0x3a, 0x00, 0x01, 0x11, 0x83, 0x20, 0x59, 0x04, 0xa0]
+    );
+    assert_eq!(cose[13..13 + 1184], key.public.data[..]);
+    assert_eq!(cose[13 + 1184..13 + 1184 + 3], [0x21, 0x58, 0x40]);
+    assert_eq!(cose[13 + 1184 + 3..], test_seed().data[..]);
+
+    // Not a map, wrong key type, key wrap algorithm, truncated
+    assert!(AkpKey::from_cose_key(&[0x80]).is_err());
+    let mut wrong = cose.clone();
+    wrong[2] = 0x01;
+    assert!(AkpKey::from_cose_key(&wrong).is_err());
+    let mut wrong = cose.clone();
+    wrong[8] = 0x84;
+    assert!(AkpKey::from_cose_key(&wrong).is_err());
+    assert!(AkpKey::from_cose_key(&cose[..cose.len() - 1]).is_err());
+}
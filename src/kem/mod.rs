@@ -2,43 +2,103 @@
 //!
 //! Structure that handles all the parameters and functions required to perform the KEM
 
+mod secret_key;
+#[cfg(feature = "serde")]
+mod serialization;
+mod typed;
+
+#[cfg(feature = "kem-traits")]
+pub use ::kem::{Decapsulate, Encapsulate};
+pub use secret_key::{SecretKey, SEED_BYTES};
+pub use typed::{
+    Ciphertext, Ciphertext1024, Ciphertext512, Ciphertext768, DecapsulationKey,
+    DecapsulationKey1024, DecapsulationKey512, DecapsulationKey768, EncapsulationKey,
+    EncapsulationKey1024, EncapsulationKey512, EncapsulationKey768,
+};
+
 use crate::functions::utils::{g, h, kdf};
+use crate::params::ParameterSet;
 use crate::pke::PKE;
 use crate::structures::ByteArray;
+use crate::validate::{validate_ciphertext, validate_public_key, validate_secret_key};
 
-pub struct KEM<const N: usize, const K: usize> {
-    pke: PKE<N, K>,
-    delta: usize,
-    pk_size: usize,
-    sk_size: usize,
-    ct_size: usize,
+/// Kyber CCAKEM for the parameter set `P`
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone)]
+pub struct KEM<P: ParameterSet, const N: usize, const K: usize> {
+    pke: PKE<P, N, K>,
 }
 
-impl<const N: usize, const K: usize> KEM<N, K> {
+impl<P: ParameterSet, const N: usize, const K: usize> KEM<P, N, K> {
     /// Kyber CCAKEM Key Generation => (secret key, public key)
     /// Algorithm 7 p. 11
     pub fn keygen(&self) -> (ByteArray, ByteArray) {
-        let z = ByteArray::random(32);
+        self.keygen_derand(&ByteArray::random(32), &ByteArray::random(32))
+    }
+
+    /// Deterministic key generation from the 32-byte seeds `d` and `z` => (secret key, public key)
+    ///
+    /// With `fips-selftest`, panics if the pairwise consistency test fails.
+    pub fn keygen_derand(&self, d: &ByteArray, z: &ByteArray) -> (ByteArray, ByteArray) {
+        let (sk, pk) = self.derive(d, z);
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::check_pairwise(self, &sk, &pk);
+
+        (sk, pk)
+    }
 
-        let (sk_prime, pk) = self.pke.keygen();
+    /// Key pair of the seeds `d` and `z`, without the pairwise consistency test
+    fn derive(&self, d: &ByteArray, z: &ByteArray) -> (ByteArray, ByteArray) {
+        let (sk_prime, pk) = self.pke.keygen_derand(d);
         let (h1, h2) = h(&pk);
-        let sk = ByteArray::concat(&[&sk_prime, &pk, &h1, &h2, &z]);
+        let sk = ByteArray::concat(&[&sk_prime, &pk, &h1, &h2, z]);
 
         (sk, pk)
     }
 
+    /// Key generation keeping the seed => (secret key, public key)
+    ///
+    /// The secret key can be stored as its 64-byte seed with `SecretKey::to_seed`.
+    pub fn keygen_with_seed(&self) -> (SecretKey<P, N, K>, ByteArray) {
+        let seed = ByteArray::random(SEED_BYTES);
+        // The seed has the right size, so this only fails like the other unchecked operations
+        let sk = SecretKey::from_seed(&seed).unwrap_or_else(|e| panic!("kybe-rs: {}", e));
+        let pk = sk.public_key();
+
+        (sk, pk)
+    }
+
+    /// Expand a 64-byte seed `d || z` into (secret key, public key)
+    ///
+    /// With `fips-selftest`, fails if the pairwise consistency test fails.
+    pub fn expand_seed(&self, seed: &ByteArray) -> Result<(ByteArray, ByteArray), String> {
+        if seed.data.len() != SEED_BYTES {
+            return Err(format!("seed must be {} bytes", SEED_BYTES));
+        }
+        let (d, z) = seed.split_at(32);
+
+        let (sk, pk) = self.derive(&d, &z);
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::pairwise_consistency(self, &sk, &pk)?;
+        Ok((sk, pk))
+    }
+
     /// Encryption : public key  => ciphertext, Shared Key
     /// Algorithm 8 p. 11
     pub fn encaps(&self, pk: &ByteArray) -> (ByteArray, ByteArray) {
-        let m = ByteArray::random(32);
-        let (m1, m2) = h(&m);
+        self.encaps_derand(pk, &ByteArray::random(32))
+    }
+
+    /// Deterministic encapsulation with the 32-byte message `m` => (ciphertext, shared key)
+    pub fn encaps_derand(&self, pk: &ByteArray, m: &ByteArray) -> (ByteArray, ByteArray) {
+        let (m1, m2) = h(m);
         let (h1, h2) = h(pk);
         let (k_bar, r) = g(&ByteArray::concat(&[&m1, &m2, &h1, &h2]));
 
         let c = self.pke.encrypt(pk, &m1.append(&m2), r);
 
         let (h1, h2) = h(&c);
-        let k = kdf(&ByteArray::concat(&[&k_bar, &h1, &h2]), self.sk_size);
+        let k = kdf(&ByteArray::concat(&[&k_bar, &h1, &h2]), P::SS_BYTES);
 
         (c, k)
     }
@@ -47,8 +107,8 @@ impl<const N: usize, const K: usize> KEM<N, K> {
     /// Algorithm 9 p. 11
     pub fn decaps(&self, c: &ByteArray, sk: &ByteArray) -> ByteArray {
         // Spliting sk = (sk'||pk||H(pk)||z)
-        let (sk_prime, rem) = sk.split_at(12 * K * N / 8);
-        let (pk, rem) = rem.split_at(12 * K * N / 8 + 32);
+        let (sk_prime, rem) = sk.split_at(P::PKE_SK_BYTES);
+        let (pk, rem) = rem.split_at(P::PK_BYTES);
         let (hash, z) = rem.split_at(32);
 
         let m = self.pke.decrypt(&sk_prime, c);
@@ -57,26 +117,44 @@ impl<const N: usize, const K: usize> KEM<N, K> {
 
         let (h1, h2) = h(c);
         if *c == c_prime {
-            kdf(&ByteArray::concat(&[&k_bar, &h1, &h2]), self.sk_size)
+            kdf(&ByteArray::concat(&[&k_bar, &h1, &h2]), P::SS_BYTES)
         } else {
-            kdf(&ByteArray::concat(&[&z, &h1, &h2]), self.sk_size)
+            kdf(&ByteArray::concat(&[&z, &h1, &h2]), P::SS_BYTES)
         }
     }
 
-    pub const fn init(
-        pke: PKE<N, K>,
-        delta: usize,
-        pk_size: usize,
-        sk_size: usize,
-        ct_size: usize,
-    ) -> Self {
-        Self {
-            pke,
-            delta,
-            pk_size,
-            sk_size,
-            ct_size,
-        }
+    /// Key generation after the self-tests => (secret key, public key)
+    ///
+    /// With `fips-selftest`, fails if the pairwise consistency test fails instead of panicking.
+    pub fn keygen_checked(&self) -> Result<(ByteArray, ByteArray), String> {
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::run()?;
+        let (sk, pk) = self.derive(&ByteArray::random(32), &ByteArray::random(32));
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::pairwise_consistency(self, &sk, &pk)?;
+        Ok((sk, pk))
+    }
+
+    /// Encapsulation after validating the public key => (ciphertext, shared key)
+    pub fn encaps_checked(&self, pk: &ByteArray) -> Result<(ByteArray, ByteArray), String> {
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::run()?;
+        validate_public_key::<P>(pk)?;
+        Ok(self.encaps(pk))
+    }
+
+    /// Decapsulation after validating the ciphertext and secret key => shared key
+    pub fn decaps_checked(&self, c: &ByteArray, sk: &ByteArray) -> Result<ByteArray, String> {
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::run()?;
+        validate_ciphertext::<P>(c)?;
+        validate_secret_key::<P>(sk)?;
+        Ok(self.decaps(c, sk))
+    }
+
+    /// Instantiate the KEM on top of a PKE with the same parameter set
+    pub const fn init(pke: PKE<P, N, K>) -> Self {
+        Self { pke }
     }
 }
 
@@ -92,6 +170,40 @@ fn kem_keygen_ccakem_768() {
     kem.keygen();
 }
 
+#[test]
+fn kem_keygen_derand_is_deterministic() {
+    let kem = crate::kyber512kem();
+    let (d, z) = (ByteArray::random(32), ByteArray::random(32));
+
+    let (sk, pk) = kem.keygen_derand(&d, &z);
+    assert_eq!((sk.clone(), pk), kem.keygen_derand(&d, &z));
+    assert_eq!(sk.split_at(sk.data.len() - 32).1, z);
+}
+
+#[test]
+fn kem_encaps_derand_is_deterministic() {
+    let kem = crate::kyber512kem();
+    let (sk, pk) = kem.keygen();
+    let m = ByteArray::random(32);
+
+    let (ctx, shk) = kem.encaps_derand(&pk, &m);
+    assert_eq!((ctx.clone(), shk.clone()), kem.encaps_derand(&pk, &m));
+    assert_eq!(kem.decaps(&ctx, &sk), shk);
+}
+
+#[test]
+fn checked_encapsulate_then_decapsulate() {
+    let kem = crate::kyber768kem();
+
+    let (sk, pk) = kem.keygen_checked().unwrap();
+    let (ctx, shk) = kem.encaps_checked(&pk).unwrap();
+    assert_eq!(kem.decaps_checked(&ctx, &sk).unwrap(), shk);
+
+    assert!(kem.encaps_checked(&pk.truncate(100)).is_err());
+    assert!(kem.decaps_checked(&ctx.truncate(100), &sk).is_err());
+    assert!(kem.decaps_checked(&ctx, &pk).is_err());
+}
+
 #[test]
 fn encapsulate_then_decapsulate_ccakem_512() {
     let kem = crate::kyber512kem();
@@ -111,3 +223,13 @@ fn encapsulate_then_decapsulate_ccakem_768() {
     let shk2 = kem.decaps(&ctx, &sk);
     assert_eq!(shk, shk2);
 }
+
+#[test]
+fn encapsulate_then_decapsulate_ccakem_1024() {
+    let kem = crate::kyber1024kem();
+
+    let (sk, pk) = kem.keygen();
+    let (ctx, shk) = kem.encaps(&pk);
+    let shk2 = kem.decaps(&ctx, &sk);
+    assert_eq!(shk, shk2);
+}
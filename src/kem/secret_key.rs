@@ -0,0 +1,165 @@
+//! Secret key
+//!
+//! KEM decapsulation key, which can be stored as the 64-byte seed it is expanded from
+
+use crate::kem::KEM;
+use crate::params::ParameterSet;
+use crate::pke::PKE;
+use crate::structures::ByteArray;
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Size of the seed `d || z`
+pub const SEED_BYTES: usize = 64;
+
+/// KEM secret key `sk' || pk || H(pk) || z`, with its seed `d || z` when known
+///
+/// The key is zeroized on drop, compared in constant time and redacted from `Debug`.
+#[derive(Clone)]
+pub struct SecretKey<P: ParameterSet, const N: usize, const K: usize> {
+    seed: Option<ByteArray>,
+    bytes: ByteArray,
+    params: PhantomData<P>,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> SecretKey<P, N, K> {
+    /// Expand a 64-byte seed into the full secret key
+    pub fn from_seed(seed: &ByteArray) -> Result<Self, String> {
+        let kem = KEM::<P, N, K>::init(PKE::init());
+        let (bytes, _) = kem.expand_seed(seed)?;
+
+        Ok(Self {
+            seed: Some(seed.clone()),
+            bytes,
+            params: PhantomData,
+        })
+    }
+
+    /// Seed of the key, if it was generated or loaded from a seed
+    pub fn to_seed(&self) -> Option<ByteArray> {
+        self.seed.clone()
+    }
+
+    /// Load an expanded secret key, the seed is then unknown
+    pub fn from_bytes(bytes: &ByteArray) -> Result<Self, String> {
+        if bytes.data.len() != P::SK_BYTES {
+            return Err(format!(
+                "secret key has {} bytes, expected {}",
+                bytes.data.len(),
+                P::SK_BYTES
+            ));
+        }
+
+        Ok(Self {
+            seed: None,
+            bytes: bytes.clone(),
+            params: PhantomData,
+        })
+    }
+
+    /// Expanded secret key, as used by `KEM::decaps`
+    pub fn as_bytes(&self) -> &ByteArray {
+        &self.bytes
+    }
+
+    /// Public key embedded in the secret key
+    pub fn public_key(&self) -> ByteArray {
+        let (_, rem) = self.bytes.split_at(P::PKE_SK_BYTES);
+        rem.truncate(P::PK_BYTES)
+    }
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> fmt::Debug for SecretKey<P, N, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretKey")
+            .field("has_seed", &self.seed.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> PartialEq for SecretKey<P, N, K> {
+    fn eq(&self, other: &Self) -> bool {
+        let seeds = match (&self.seed, &other.seed) {
+            (Some(a), Some(b)) => a.data.ct_eq(&b.data),
+            (a, b) => (a.is_none() as u8).ct_eq(&(b.is_none() as u8)),
+        };
+        (seeds & self.bytes.data.ct_eq(&other.bytes.data)).into()
+    }
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> Eq for SecretKey<P, N, K> {}
+
+impl<P: ParameterSet, const N: usize, const K: usize> Drop for SecretKey<P, N, K> {
+    fn drop(&mut self) {
+        self.bytes.data.zeroize();
+        if let Some(seed) = self.seed.as_mut() {
+            seed.data.zeroize();
+        }
+    }
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> ZeroizeOnDrop for SecretKey<P, N, K> {}
+
+#[test]
+fn seed_matches_keygen() {
+    let kem = crate::kyber768kem();
+    let seed = ByteArray::random(SEED_BYTES);
+    let (d, z) = seed.split_at(32);
+
+    let sk = SecretKey::<crate::Kyber768, 256, 3>::from_seed(&seed).unwrap();
+    let (expected_sk, expected_pk) = kem.keygen_derand(&d, &z);
+
+    assert_eq!(*sk.as_bytes(), expected_sk);
+    assert_eq!(sk.public_key(), expected_pk);
+    assert_eq!(sk.to_seed(), Some(seed));
+}
+
+#[test]
+fn seed_roundtrip_decaps() {
+    let kem = crate::kyber512kem();
+    let (sk, pk) = kem.keygen_with_seed();
+
+    // Store only the seed, then restore the key
+    let seed = sk.to_seed().unwrap();
+    assert_eq!(seed.data.len(), SEED_BYTES);
+    let restored = SecretKey::<crate::Kyber512, 256, 2>::from_seed(&seed).unwrap();
+    assert_eq!(restored, sk);
+
+    let (c, k) = kem.encaps(&pk);
+    assert_eq!(kem.decaps(&c, restored.as_bytes()), k);
+}
+
+#[test]
+fn expanded_keys_have_no_seed() {
+    let kem = crate::kyber512kem();
+    let (sk, pk) = kem.keygen();
+
+    let sk = SecretKey::<crate::Kyber512, 256, 2>::from_bytes(&sk).unwrap();
+    assert_eq!(sk.to_seed(), None);
+    assert_eq!(sk.public_key(), pk);
+
+    assert!(SecretKey::<crate::Kyber512, 256, 2>::from_bytes(&pk).is_err());
+    assert!(SecretKey::<crate::Kyber512, 256, 2>::from_seed(&ByteArray::random(32)).is_err());
+}
+
+#[test]
+fn secret_key_redacted() {
+    let kem = crate::kyber512kem();
+    let (sk, _) = kem.keygen_with_seed();
+    let expanded = SecretKey::<crate::Kyber512, 256, 2>::from_bytes(sk.as_bytes()).unwrap();
+
+    assert_eq!(format!("{:?}", sk), "SecretKey { has_seed: true, .. }");
+    assert_eq!(
+        format!("{:?}", expanded),
+        "SecretKey { has_seed: false, .. }"
+    );
+
+    // Equality covers the seed and the expanded key
+    assert_eq!(sk, sk.clone());
+    assert_ne!(sk, expanded);
+    assert_ne!(sk, kem.keygen_with_seed().0);
+}
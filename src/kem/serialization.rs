@@ -0,0 +1,130 @@
+//! Serialization
+//!
+//! `serde` support for keys and ciphertexts, encoded like a `ByteArray`: hex strings in
+//! human-readable formats and raw bytes otherwise. Deserialization validates the keys
+//! and the ciphertext lengths for the parameter set.
+//!
+//! Secret keys are serialized as their 64-byte seed when it is known and as the expanded
+//! key otherwise, both are accepted.
+
+use crate::kem::{Ciphertext, DecapsulationKey, EncapsulationKey, SecretKey, SEED_BYTES};
+use crate::params::ParameterSet;
+use crate::structures::ByteArray;
+use crate::validate::validate_secret_key;
+
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+impl<P: ParameterSet, const N: usize, const K: usize> Serialize for EncapsulationKey<P, N, K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_bytes().serialize(serializer)
+    }
+}
+
+impl<'de, P: ParameterSet, const N: usize, const K: usize> Deserialize<'de>
+    for EncapsulationKey<P, N, K>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = ByteArray::deserialize(deserializer)?;
+        Self::from_bytes(&bytes).map_err(de::Error::custom)
+    }
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> Serialize for Ciphertext<P, N, K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_bytes().serialize(serializer)
+    }
+}
+
+impl<'de, P: ParameterSet, const N: usize, const K: usize> Deserialize<'de>
+    for Ciphertext<P, N, K>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = ByteArray::deserialize(deserializer)?;
+        Self::from_bytes(&bytes).map_err(de::Error::custom)
+    }
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> Serialize for SecretKey<P, N, K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.to_seed() {
+            Some(seed) => seed.serialize(serializer),
+            None => self.as_bytes().serialize(serializer),
+        }
+    }
+}
+
+impl<'de, P: ParameterSet, const N: usize, const K: usize> Deserialize<'de> for SecretKey<P, N, K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = ByteArray::deserialize(deserializer)?;
+
+        let sk = if bytes.data.len() == SEED_BYTES {
+            Self::from_seed(&bytes)
+        } else {
+            validate_secret_key::<P>(&bytes).and_then(|_| Self::from_bytes(&bytes))
+        };
+        sk.map_err(de::Error::custom)
+    }
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> Serialize for DecapsulationKey<P, N, K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.secret_key().serialize(serializer)
+    }
+}
+
+impl<'de, P: ParameterSet, const N: usize, const K: usize> Deserialize<'de>
+    for DecapsulationKey<P, N, K>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let sk = SecretKey::deserialize(deserializer)?;
+        Self::from_secret_key(sk).map_err(de::Error::custom)
+    }
+}
+
+#[test]
+fn serde_keys() {
+    use crate::{Ciphertext768, DecapsulationKey768, EncapsulationKey768, Kyber512, Kyber768};
+
+    let kem = crate::kyber768kem();
+    let (sk, pk) = kem.keygen_with_seed();
+    let (c, _) = kem.encaps(&pk);
+
+    let dk = DecapsulationKey768::from_secret_key(sk.clone()).unwrap();
+    let ek = dk.encapsulation_key().clone();
+    let ct = Ciphertext768::from_bytes(&c).unwrap();
+
+    // Human readable: hex strings, the secret key is stored as its seed
+    let json = serde_json::to_string(&ek).unwrap();
+    assert_eq!(json.len(), 2 + 2 * Kyber768::PK_BYTES);
+    assert_eq!(
+        serde_json::from_str::<EncapsulationKey768>(&json).unwrap(),
+        ek
+    );
+    assert!(serde_json::from_str::<EncapsulationKey<Kyber512, 256, 2>>(&json).is_err());
+
+    let json = serde_json::to_string(&dk).unwrap();
+    assert_eq!(json.len(), 2 + 2 * SEED_BYTES);
+    assert_eq!(
+        serde_json::from_str::<DecapsulationKey768>(&json).unwrap(),
+        dk
+    );
+
+    let json = serde_json::to_string(&ct).unwrap();
+    assert_eq!(serde_json::from_str::<Ciphertext768>(&json).unwrap(), ct);
+    assert!(serde_json::from_str::<Ciphertext768>("\"00\"").is_err());
+
+    // Binary: raw bytes, expanded secret keys are validated
+    let expanded = SecretKey::<Kyber768, 256, 3>::from_bytes(sk.as_bytes()).unwrap();
+    let mut cbor = Vec::new();
+    ciborium::ser::into_writer(&expanded, &mut cbor).unwrap();
+    let decoded: SecretKey<Kyber768, 256, 3> = ciborium::de::from_reader(&cbor[..]).unwrap();
+    assert_eq!(decoded, expanded);
+
+    let mut corrupted = sk.as_bytes().clone();
+    corrupted.data[Kyber768::PKE_SK_BYTES] ^= 1;
+    let mut cbor = Vec::new();
+    ciborium::ser::into_writer(&corrupted, &mut cbor).unwrap();
+    assert!(ciborium::de::from_reader::<SecretKey<Kyber768, 256, 3>, _>(&cbor[..]).is_err());
+}
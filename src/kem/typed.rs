@@ -0,0 +1,293 @@
+//! Typed keys
+//!
+//! Encapsulation keys, decapsulation keys and ciphertexts bound to a parameter set.
+//! With the `kem-traits` feature, `rand_core` key generation and the `Encapsulate` and
+//! `Decapsulate` traits of the RustCrypto `kem` crate (0.3.0-pre.0, as used by `ml-kem`).
+
+use crate::kem::SecretKey;
+use crate::params::{Kyber1024, Kyber512, Kyber768, ParameterSet};
+use crate::structures::ByteArray;
+use crate::validate::{validate_ciphertext, validate_public_key, validate_secret_key};
+
+use std::marker::PhantomData;
+
+#[cfg(feature = "kem-traits")]
+use crate::{
+    kem::{KEM, SEED_BYTES},
+    pke::PKE,
+};
+#[cfg(feature = "kem-traits")]
+use ::kem::{Decapsulate, Encapsulate};
+#[cfg(feature = "kem-traits")]
+use rand_core::CryptoRngCore;
+
+/// KEM of the parameter set `P`
+#[cfg(feature = "kem-traits")]
+fn kem<P: ParameterSet, const N: usize, const K: usize>() -> KEM<P, N, K> {
+    KEM::init(PKE::init())
+}
+
+/// Public key of the parameter set `P`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncapsulationKey<P: ParameterSet, const N: usize, const K: usize> {
+    bytes: ByteArray,
+    params: PhantomData<P>,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> EncapsulationKey<P, N, K> {
+    /// Load a public key after validating it
+    pub fn from_bytes(bytes: &ByteArray) -> Result<Self, String> {
+        validate_public_key::<P>(bytes)?;
+
+        Ok(Self {
+            bytes: bytes.clone(),
+            params: PhantomData,
+        })
+    }
+
+    /// Encoded public key
+    pub fn as_bytes(&self) -> &ByteArray {
+        &self.bytes
+    }
+}
+
+/// Ciphertext of the parameter set `P`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ciphertext<P: ParameterSet, const N: usize, const K: usize> {
+    bytes: ByteArray,
+    params: PhantomData<P>,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> Ciphertext<P, N, K> {
+    /// Load a ciphertext after validating its length
+    pub fn from_bytes(bytes: &ByteArray) -> Result<Self, String> {
+        validate_ciphertext::<P>(bytes)?;
+
+        Ok(Self {
+            bytes: bytes.clone(),
+            params: PhantomData,
+        })
+    }
+
+    /// Encoded ciphertext
+    pub fn as_bytes(&self) -> &ByteArray {
+        &self.bytes
+    }
+}
+
+#[cfg(feature = "kem-traits")]
+impl<P: ParameterSet, const N: usize, const K: usize> Encapsulate<Ciphertext<P, N, K>, ByteArray>
+    for EncapsulationKey<P, N, K>
+{
+    type Error = String;
+
+    fn encapsulate(
+        &self,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<(Ciphertext<P, N, K>, ByteArray), Self::Error> {
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::run()?;
+
+        let mut m = [0; 32];
+        rng.try_fill_bytes(&mut m).map_err(|e| e.to_string())?;
+
+        let (c, ss) = kem::<P, N, K>().encaps_derand(&self.bytes, &ByteArray::from_bytes(&m));
+        let c = Ciphertext {
+            bytes: c,
+            params: PhantomData,
+        };
+
+        Ok((c, ss))
+    }
+}
+
+/// Secret key of the parameter set `P`, with its public key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecapsulationKey<P: ParameterSet, const N: usize, const K: usize> {
+    sk: SecretKey<P, N, K>,
+    ek: EncapsulationKey<P, N, K>,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> DecapsulationKey<P, N, K> {
+    /// Generate a key pair from the random number generator
+    ///
+    /// With `fips-selftest`, fails if the self-tests or the pairwise consistency test fail.
+    #[cfg(feature = "kem-traits")]
+    pub fn generate(rng: &mut impl CryptoRngCore) -> Result<Self, String> {
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::run()?;
+
+        let mut seed = [0; SEED_BYTES];
+        rng.try_fill_bytes(&mut seed).map_err(|e| e.to_string())?;
+        // `from_seed` runs the pairwise consistency test
+        Self::from_secret_key(SecretKey::from_seed(&ByteArray::from_bytes(&seed))?)
+    }
+
+    /// Wrap a secret key after validating it
+    pub fn from_secret_key(sk: SecretKey<P, N, K>) -> Result<Self, String> {
+        validate_secret_key::<P>(sk.as_bytes())?;
+        let ek = EncapsulationKey {
+            bytes: sk.public_key(),
+            params: PhantomData,
+        };
+
+        Ok(Self { sk, ek })
+    }
+
+    /// Secret key
+    pub fn secret_key(&self) -> &SecretKey<P, N, K> {
+        &self.sk
+    }
+
+    /// Public key matching the secret key
+    pub fn encapsulation_key(&self) -> &EncapsulationKey<P, N, K> {
+        &self.ek
+    }
+}
+
+#[cfg(feature = "kem-traits")]
+impl<P: ParameterSet, const N: usize, const K: usize> Decapsulate<Ciphertext<P, N, K>, ByteArray>
+    for DecapsulationKey<P, N, K>
+{
+    type Error = String;
+
+    fn decapsulate(
+        &self,
+        encapsulated_key: &Ciphertext<P, N, K>,
+    ) -> Result<ByteArray, Self::Error> {
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::run()?;
+
+        Ok(kem::<P, N, K>().decaps(&encapsulated_key.bytes, self.sk.as_bytes()))
+    }
+}
+
+/// Kyber512 public key
+pub type EncapsulationKey512 = EncapsulationKey<Kyber512, 256, 2>;
+/// Kyber512 secret key
+pub type DecapsulationKey512 = DecapsulationKey<Kyber512, 256, 2>;
+/// Kyber512 ciphertext
+pub type Ciphertext512 = Ciphertext<Kyber512, 256, 2>;
+/// Kyber768 public key
+pub type EncapsulationKey768 = EncapsulationKey<Kyber768, 256, 3>;
+/// Kyber768 secret key
+pub type DecapsulationKey768 = DecapsulationKey<Kyber768, 256, 3>;
+/// Kyber768 ciphertext
+pub type Ciphertext768 = Ciphertext<Kyber768, 256, 3>;
+/// Kyber1024 public key
+pub type EncapsulationKey1024 = EncapsulationKey<Kyber1024, 256, 4>;
+/// Kyber1024 secret key
+pub type DecapsulationKey1024 = DecapsulationKey<Kyber1024, 256, 4>;
+/// Kyber1024 ciphertext
+pub type Ciphertext1024 = Ciphertext<Kyber1024, 256, 4>;
+
+/// Deterministic generator for the tests (xorshift64*, not cryptographic)
+#[cfg(all(test, feature = "kem-traits"))]
+struct TestRng(u64);
+
+#[cfg(all(test, feature = "kem-traits"))]
+impl rand_core::RngCore for TestRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "kem-traits"))]
+impl rand_core::CryptoRng for TestRng {}
+
+/// Conformance suite run for every parameter set
+#[cfg(all(test, feature = "kem-traits"))]
+fn conformance<P: ParameterSet + Clone + std::fmt::Debug + Eq, const N: usize, const K: usize>() {
+    let dk = DecapsulationKey::<P, N, K>::generate(&mut TestRng(1)).unwrap();
+    let ek = dk.encapsulation_key();
+    assert_eq!(ek.as_bytes().data.len(), P::PK_BYTES);
+    assert_eq!(dk.secret_key().as_bytes().data.len(), P::SK_BYTES);
+
+    // Generation only depends on the generator
+    assert_eq!(DecapsulationKey::generate(&mut TestRng(1)), Ok(dk.clone()));
+    assert_ne!(DecapsulationKey::generate(&mut TestRng(2)), Ok(dk.clone()));
+
+    // Round trip
+    let (ct, ss) = ek.encapsulate(&mut TestRng(3)).unwrap();
+    assert_eq!(ct.as_bytes().data.len(), P::CT_BYTES);
+    assert_eq!(ss.data.len(), P::SS_BYTES);
+    assert_eq!(dk.decapsulate(&ct).unwrap(), ss);
+    assert_eq!(
+        ek.encapsulate(&mut TestRng(3)).unwrap(),
+        (ct.clone(), ss.clone())
+    );
+
+    // Implicit rejection
+    let mut tampered = ct.as_bytes().clone();
+    tampered.data[0] ^= 1;
+    let tampered = Ciphertext::from_bytes(&tampered).unwrap();
+    assert_ne!(dk.decapsulate(&tampered).unwrap(), ss);
+
+    // Serialization
+    assert_eq!(EncapsulationKey::from_bytes(ek.as_bytes()).as_ref(), Ok(ek));
+    assert_eq!(
+        DecapsulationKey::from_secret_key(dk.secret_key().clone()).as_ref(),
+        Ok(&dk)
+    );
+
+    // Malformed inputs
+    assert_eq!(Ciphertext::from_bytes(ct.as_bytes()).as_ref(), Ok(&ct));
+    assert!(Ciphertext::<P, N, K>::from_bytes(&ct.as_bytes().truncate(P::CT_BYTES - 1)).is_err());
+    assert!(EncapsulationKey::<P, N, K>::from_bytes(&ek.as_bytes().truncate(100)).is_err());
+    let mut sk = dk.secret_key().as_bytes().clone();
+    sk.data[P::PKE_SK_BYTES] ^= 1;
+    assert!(
+        DecapsulationKey::from_secret_key(SecretKey::<P, N, K>::from_bytes(&sk).unwrap()).is_err()
+    );
+}
+
+/// Generic code written against the upstream traits
+#[cfg(all(test, feature = "kem-traits"))]
+fn upstream_roundtrip<EK, SS: PartialEq + std::fmt::Debug>(
+    ek: &impl Encapsulate<EK, SS>,
+    dk: &impl Decapsulate<EK, SS>,
+) {
+    let (ct, ss) = ek.encapsulate(&mut TestRng(4)).unwrap();
+    assert_eq!(dk.decapsulate(&ct).unwrap(), ss);
+}
+
+#[cfg(feature = "kem-traits")]
+#[test]
+fn upstream_traits() {
+    let dk = DecapsulationKey768::generate(&mut TestRng(5)).unwrap();
+    upstream_roundtrip(dk.encapsulation_key(), &dk);
+}
+
+#[cfg(feature = "kem-traits")]
+#[test]
+fn conformance_512() {
+    conformance::<Kyber512, 256, 2>();
+}
+
+#[cfg(feature = "kem-traits")]
+#[test]
+fn conformance_768() {
+    conformance::<Kyber768, 256, 3>();
+}
+
+#[cfg(feature = "kem-traits")]
+#[test]
+fn conformance_1024() {
+    conformance::<Kyber1024, 256, 4>();
+}
@@ -51,32 +51,81 @@
 
 extern crate sha3;
 
+#[cfg(feature = "age")]
+pub mod age;
+pub mod ake;
+pub mod analysis;
+#[cfg(feature = "envelope")]
+pub mod envelope;
+pub mod estimator;
+pub mod formats;
 mod functions;
+#[cfg(feature = "hpke")]
+pub mod hpke;
+#[cfg(feature = "hybrid")]
+pub mod hybrid;
+#[cfg(feature = "jose")]
+pub mod jose;
 mod kem;
+#[cfg(feature = "noise")]
+pub mod noise;
+#[cfg(feature = "openpgp")]
+pub mod openpgp;
+mod params;
 mod pke;
+#[cfg(feature = "pqxdh")]
+pub mod pqxdh;
+#[cfg(feature = "fips-selftest")]
+pub mod selftest;
+#[cfg(feature = "ssh")]
+pub mod ssh;
 mod structures;
+#[cfg(feature = "tls")]
+pub mod tls;
+mod validate;
+#[cfg(feature = "xwing")]
+pub mod xwing;
 
-pub use structures::ByteArray;
-
-use kem::KEM;
-use pke::PKE;
+pub use kem::{
+    Ciphertext, Ciphertext1024, Ciphertext512, Ciphertext768, DecapsulationKey,
+    DecapsulationKey1024, DecapsulationKey512, DecapsulationKey768, EncapsulationKey,
+    EncapsulationKey1024, EncapsulationKey512, EncapsulationKey768,
+};
+#[cfg(feature = "kem-traits")]
+pub use kem::{Decapsulate, Encapsulate};
+pub use kem::{SecretKey, KEM, SEED_BYTES};
+pub use params::{Kyber1024, Kyber512, Kyber768, ParameterSet, ParameterSetBuilder, Parameters};
+pub use pke::PKE;
+pub use structures::algebraics::{FiniteField, FiniteGroup, FiniteRing, RingModule, VectorSpace};
+pub use structures::{ByteArray, Poly3329, PolyMatrix3329, PolyVec3329, F3329};
+pub use validate::{validate_ciphertext, validate_public_key, validate_secret_key};
 
 /// Instantiate the Kyber 512 PKE with the appropriate parameters
-pub const fn kyber512pke() -> PKE<256, 2> {
-    PKE::<256, 2>::init(3329, 2, 10, 3)
+pub const fn kyber512pke() -> PKE<Kyber512, 256, 2> {
+    PKE::init()
 }
 
 /// Instantiate the Kyber 512 KEM with the appropriate parameters
-pub const fn kyber512kem() -> KEM<256, 2> {
-    KEM::<256, 2>::init(kyber512pke(), 178, 800, 1632, 738)
+pub const fn kyber512kem() -> KEM<Kyber512, 256, 2> {
+    KEM::init(kyber512pke())
 }
 
 /// Instantiate the Kyber 768 PKE with the appropriate parameters
-pub const fn kyber768pke() -> PKE<256, 3> {
-    PKE::<256, 3>::init(3329, 2, 10, 4)
+pub const fn kyber768pke() -> PKE<Kyber768, 256, 3> {
+    PKE::init()
 }
 
 /// Instantiate the Kyber 768 KEM with the appropriate parameters
-pub const fn kyber768kem() -> KEM<256, 3> {
-    KEM::<256, 3>::init(kyber768pke(), 164, 1184, 2400, 1088)
+pub const fn kyber768kem() -> KEM<Kyber768, 256, 3> {
+    KEM::init(kyber768pke())
+}
+
+/// Instantiate the Kyber 1024 PKE with the appropriate parameters
+pub const fn kyber1024pke() -> PKE<Kyber1024, 256, 4> {
+    PKE::init()
+}
+
+/// Instantiate the Kyber 1024 KEM with the appropriate parameters
+pub const fn kyber1024kem() -> KEM<Kyber1024, 256, 4> {
+    KEM::init(kyber1024pke())
 }
@@ -0,0 +1,576 @@
+//! Noise
+//!
+//! Post-quantum Noise handshakes (PQNoise, Schwabe, Stebila and Wiggers, 2022) where
+//! Diffie-Hellman is replaced by the Kyber KEM, with ChaChaPoly and SHA256.
+//!
+//! Tokens of the KEM patterns:
+//! - `e`: send an ephemeral public key, mixed into the handshake hash
+//! - `s`: send the static public key, encrypted once a key is set
+//! - `ekem`: encapsulate to the remote ephemeral key, send the (encrypted) ciphertext
+//!   and mix the shared secret into the chaining key
+//! - `skem`: same with the remote static key
+//!
+//! Protocol names follow the Noise convention, e.g. `Noise_pqXX_Kyber768_ChaChaPoly_SHA256`.
+//! The KEM is the round 2 Kyber of this crate, so handshakes do not interoperate with
+//! ML-KEM based PQNoise implementations.
+
+mod state;
+
+pub use state::{CipherState, SymmetricState, HASHLEN, TAGLEN};
+
+use crate::kem::KEM;
+use crate::params::ParameterSet;
+use crate::structures::ByteArray;
+use crate::validate::{validate_public_key, validate_secret_key};
+
+/// Noise messages are limited to 65535 bytes
+pub const MAX_MESSAGE_BYTES: usize = 65535;
+
+/// Token of a handshake message
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Token {
+    /// Ephemeral public key
+    E,
+    /// Static public key
+    S,
+    /// Encapsulation to the remote ephemeral key
+    Ekem,
+    /// Encapsulation to the remote static key
+    Skem,
+}
+
+/// KEM handshake pattern
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// No static keys
+    ///
+    /// ```text
+    /// -> e
+    /// <- ekem
+    /// ```
+    PqNN,
+    /// Responder static key known in advance, initiator static key sent immediately
+    ///
+    /// ```text
+    /// <- s
+    /// ...
+    /// -> skem, e, s
+    /// <- ekem, skem
+    /// ```
+    PqIK,
+    /// Static keys exchanged during the handshake
+    ///
+    /// ```text
+    /// -> e
+    /// <- ekem, s
+    /// -> skem, s
+    /// <- skem
+    /// ```
+    PqXX,
+}
+
+impl Pattern {
+    /// Name used in the protocol name
+    pub fn name(self) -> &'static str {
+        match self {
+            Pattern::PqNN => "pqNN",
+            Pattern::PqIK => "pqIK",
+            Pattern::PqXX => "pqXX",
+        }
+    }
+
+    /// Messages of the handshake, alternating from the initiator
+    pub fn messages(self) -> &'static [&'static [Token]] {
+        use Token::*;
+
+        match self {
+            Pattern::PqNN => &[&[E], &[Ekem]],
+            Pattern::PqIK => &[&[Skem, E, S], &[Ekem, Skem]],
+            Pattern::PqXX => &[&[E], &[Ekem, S], &[Skem, S], &[Skem]],
+        }
+    }
+
+    /// Whether the (initiator, responder) need a static key
+    fn needs_static(self) -> (bool, bool) {
+        match self {
+            Pattern::PqNN => (false, false),
+            Pattern::PqIK | Pattern::PqXX => (true, true),
+        }
+    }
+
+    /// Whether the responder static key is a pre-message
+    fn responder_premessage(self) -> bool {
+        self == Pattern::PqIK
+    }
+}
+
+/// Key pair (secret key, public key)
+type KeyPair = (ByteArray, ByteArray);
+
+/// State of one party during a handshake
+pub struct HandshakeState<P: ParameterSet, const N: usize, const K: usize> {
+    kem: KEM<P, N, K>,
+    pattern: Pattern,
+    symmetric: SymmetricState,
+    initiator: bool,
+    s: Option<KeyPair>,
+    e: Option<KeyPair>,
+    rs: Option<ByteArray>,
+    re: Option<ByteArray>,
+    index: usize,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> HandshakeState<P, N, K> {
+    /// Initialize a party with its static key pair `s` and the remote static key `rs` when
+    /// the pattern requires them
+    pub fn new(
+        kem: KEM<P, N, K>,
+        pattern: Pattern,
+        initiator: bool,
+        prologue: &[u8],
+        s: Option<KeyPair>,
+        rs: Option<ByteArray>,
+    ) -> Result<Self, String> {
+        let (initiator_s, responder_s) = pattern.needs_static();
+        let needs_s = if initiator { initiator_s } else { responder_s };
+        if needs_s != s.is_some() {
+            return Err(format!(
+                "{} {} static key",
+                pattern.name(),
+                if needs_s { "requires a" } else { "takes no" }
+            ));
+        }
+        let needs_rs = pattern.responder_premessage() && initiator;
+        if needs_rs != rs.is_some() {
+            return Err(format!(
+                "{} {} remote static key",
+                pattern.name(),
+                if needs_rs { "requires a" } else { "takes no" }
+            ));
+        }
+        if let Some((sk, pk)) = &s {
+            validate_secret_key::<P>(sk)?;
+            validate_public_key::<P>(pk)?;
+        }
+        if let Some(rs) = &rs {
+            validate_public_key::<P>(rs)?;
+        }
+
+        let name = format!(
+            "Noise_{}_Kyber{}_ChaChaPoly_SHA256",
+            pattern.name(),
+            P::K * P::N
+        );
+        let mut symmetric = SymmetricState::new(&name);
+        symmetric.mix_hash(prologue);
+        if pattern.responder_premessage() {
+            let responder_pk = if initiator {
+                rs.as_ref()
+            } else {
+                s.as_ref().map(|(_, pk)| pk)
+            };
+            // Unwraps safely since the keys were checked above
+            symmetric.mix_hash(&responder_pk.unwrap().data);
+        }
+
+        Ok(Self {
+            kem,
+            pattern,
+            symmetric,
+            initiator,
+            s,
+            e: None,
+            rs,
+            re: None,
+            index: 0,
+        })
+    }
+
+    /// Whether all the handshake messages have been processed
+    pub fn is_finished(&self) -> bool {
+        self.index == self.pattern.messages().len()
+    }
+
+    /// Whether the next message is ours to write
+    pub fn is_my_turn(&self) -> bool {
+        !self.is_finished() && self.index.is_multiple_of(2) == self.initiator
+    }
+
+    /// Static public key of the remote party, once known
+    pub fn remote_static(&self) -> Option<&ByteArray> {
+        self.rs.as_ref()
+    }
+
+    /// Handshake hash, for channel binding
+    pub fn handshake_hash(&self) -> [u8; HASHLEN] {
+        self.symmetric.handshake_hash()
+    }
+
+    /// Public key to encapsulate to for a KEM token
+    fn remote_key(&self, token: Token) -> Result<&ByteArray, String> {
+        let key = match token {
+            Token::Ekem => self.re.as_ref(),
+            _ => self.rs.as_ref(),
+        };
+        key.ok_or_else(|| format!("missing remote key for {:?}", token))
+    }
+
+    /// Secret key to decapsulate with for a KEM token
+    fn local_key(&self, token: Token) -> Result<&ByteArray, String> {
+        let key = match token {
+            Token::Ekem => self.e.as_ref(),
+            _ => self.s.as_ref(),
+        };
+        key.map(|(sk, _)| sk)
+            .ok_or_else(|| format!("missing local key for {:?}", token))
+    }
+
+    /// Length of the next handshake message, carrying `payload_len` bytes
+    fn message_len(&self, payload_len: usize) -> usize {
+        let mut has_key = self.symmetric.has_key();
+        let mut len = 0;
+
+        for token in self.pattern.messages()[self.index] {
+            let tag = if has_key { TAGLEN } else { 0 };
+            len += match token {
+                Token::E => P::PK_BYTES,
+                Token::S => P::PK_BYTES + tag,
+                Token::Ekem | Token::Skem => {
+                    has_key = true;
+                    P::CT_BYTES + tag
+                }
+            };
+        }
+        let tag = if has_key { TAGLEN } else { 0 };
+
+        len.saturating_add(payload_len).saturating_add(tag)
+    }
+
+    /// Write the next handshake message, carrying `payload`
+    pub fn write_message(&mut self, payload: &[u8]) -> Result<Vec<u8>, String> {
+        if !self.is_my_turn() {
+            return Err("not our turn to write".to_string());
+        }
+        // Checked before the keys, hash and nonce change
+        if self.message_len(payload.len()) > MAX_MESSAGE_BYTES {
+            return Err("handshake message too long".to_string());
+        }
+        let mut message = Vec::new();
+
+        for token in self.pattern.messages()[self.index] {
+            match token {
+                Token::E => {
+                    let (sk, pk) = self.kem.keygen();
+                    self.symmetric.mix_hash(&pk.data);
+                    message.extend_from_slice(&pk.data);
+                    self.e = Some((sk, pk));
+                }
+                Token::S => {
+                    // Unwraps safely since patterns sending `s` require a static key
+                    let (_, pk) = self.s.as_ref().unwrap();
+                    let ct = self.symmetric.encrypt_and_hash(&pk.data)?;
+                    message.extend_from_slice(&ct);
+                }
+                Token::Ekem | Token::Skem => {
+                    let (c, ss) = self.kem.encaps_checked(self.remote_key(*token)?)?;
+                    let ct = self.symmetric.encrypt_and_hash(&c.data)?;
+                    message.extend_from_slice(&ct);
+                    self.symmetric.mix_key(&ss.data);
+                }
+            }
+        }
+        message.extend_from_slice(&self.symmetric.encrypt_and_hash(payload)?);
+        self.index += 1;
+
+        Ok(message)
+    }
+
+    /// Read the next handshake message => payload
+    pub fn read_message(&mut self, message: &[u8]) -> Result<Vec<u8>, String> {
+        if self.is_finished() || self.is_my_turn() {
+            return Err("not our turn to read".to_string());
+        }
+        if message.len() > MAX_MESSAGE_BYTES {
+            return Err("handshake message too long".to_string());
+        }
+        let mut rest = message;
+        let mut take = |len: usize| -> Result<&[u8], String> {
+            if rest.len() < len {
+                return Err("truncated handshake message".to_string());
+            }
+            let (head, tail) = rest.split_at(len);
+            rest = tail;
+            Ok(head)
+        };
+
+        for token in self.pattern.messages()[self.index] {
+            let tag = if self.symmetric.has_key() { TAGLEN } else { 0 };
+            match token {
+                Token::E => {
+                    let pk = ByteArray::from_bytes(take(P::PK_BYTES)?);
+                    validate_public_key::<P>(&pk)?;
+                    self.symmetric.mix_hash(&pk.data);
+                    self.re = Some(pk);
+                }
+                Token::S => {
+                    let pk = self.symmetric.decrypt_and_hash(take(P::PK_BYTES + tag)?)?;
+                    let pk = ByteArray::from_bytes(&pk);
+                    validate_public_key::<P>(&pk)?;
+                    self.rs = Some(pk);
+                }
+                Token::Ekem | Token::Skem => {
+                    let c = self.symmetric.decrypt_and_hash(take(P::CT_BYTES + tag)?)?;
+                    let c = ByteArray::from_bytes(&c);
+                    let ss = self.kem.decaps_checked(&c, self.local_key(*token)?)?;
+                    self.symmetric.mix_key(&ss.data);
+                }
+            }
+        }
+        let payload = self.symmetric.decrypt_and_hash(rest)?;
+        self.index += 1;
+
+        Ok(payload)
+    }
+
+    /// Finish the handshake => transport state
+    pub fn into_transport(self) -> Result<TransportState, String> {
+        if !self.is_finished() {
+            return Err("handshake not finished".to_string());
+        }
+        let (c1, c2) = self.symmetric.split();
+        let (send, recv) = if self.initiator { (c1, c2) } else { (c2, c1) };
+
+        Ok(TransportState {
+            send,
+            recv,
+            h: self.symmetric.handshake_hash(),
+        })
+    }
+}
+
+/// Cipher states of an established session
+pub struct TransportState {
+    send: CipherState,
+    recv: CipherState,
+    h: [u8; HASHLEN],
+}
+
+impl TransportState {
+    /// Encrypt a transport message
+    pub fn encrypt(&mut self, pt: &[u8]) -> Result<Vec<u8>, String> {
+        if pt.len() + TAGLEN > MAX_MESSAGE_BYTES {
+            return Err("transport message too long".to_string());
+        }
+        self.send.encrypt_with_ad(&[], pt)
+    }
+
+    /// Decrypt a transport message
+    pub fn decrypt(&mut self, ct: &[u8]) -> Result<Vec<u8>, String> {
+        self.recv.decrypt_with_ad(&[], ct)
+    }
+
+    /// Handshake hash, for channel binding
+    pub fn handshake_hash(&self) -> [u8; HASHLEN] {
+        self.h
+    }
+}
+
+/// Outcome of a party: (transport state, received payloads, remote static key)
+#[cfg(test)]
+type PartyResult = Result<(TransportState, Vec<Vec<u8>>, Option<ByteArray>), String>;
+
+/// Run a party over in-memory pipes, exchanging a payload per message => transport state
+#[cfg(test)]
+fn run_party<P: ParameterSet, const N: usize, const K: usize>(
+    mut hs: HandshakeState<P, N, K>,
+    tx: std::sync::mpsc::Sender<Vec<u8>>,
+    rx: std::sync::mpsc::Receiver<Vec<u8>>,
+) -> PartyResult {
+    let mut received = Vec::new();
+    while !hs.is_finished() {
+        if hs.is_my_turn() {
+            let payload = format!("payload {}", hs.index).into_bytes();
+            tx.send(hs.write_message(&payload)?)
+                .map_err(|e| e.to_string())?;
+        } else {
+            let message = rx.recv().map_err(|e| e.to_string())?;
+            received.push(hs.read_message(&message)?);
+        }
+    }
+    let rs = hs.remote_static().cloned();
+
+    Ok((hs.into_transport()?, received, rs))
+}
+
+/// Run both parties in their threads => (initiator, responder) results
+#[cfg(test)]
+fn handshake<P: ParameterSet, const N: usize, const K: usize>(
+    initiator: HandshakeState<P, N, K>,
+    responder: HandshakeState<P, N, K>,
+) -> (PartyResult, PartyResult)
+where
+    HandshakeState<P, N, K>: Send + 'static,
+{
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    let (to_responder, from_initiator) = channel();
+    let (to_initiator, from_responder) = channel();
+    let i = thread::spawn(move || run_party(initiator, to_responder, from_responder));
+    let r = thread::spawn(move || run_party(responder, to_initiator, from_initiator));
+
+    (i.join().unwrap(), r.join().unwrap())
+}
+
+#[cfg(test)]
+fn transport(initiator: &mut TransportState, responder: &mut TransportState) {
+    assert_eq!(initiator.handshake_hash(), responder.handshake_hash());
+    for i in 0..3u8 {
+        let ct = initiator.encrypt(&[i; 10]).unwrap();
+        assert_eq!(responder.decrypt(&ct).unwrap(), [i; 10]);
+        let ct = responder.encrypt(&[i; 20]).unwrap();
+        assert_eq!(initiator.decrypt(&ct).unwrap(), [i; 20]);
+    }
+    // Directions use different keys
+    let ct = initiator.encrypt(b"echo").unwrap();
+    assert!(initiator.decrypt(&ct).is_err());
+}
+
+#[test]
+fn noise_pq_nn() {
+    use crate::params::Kyber768;
+
+    let kem = crate::kyber768kem();
+    let i = HandshakeState::new(kem.clone(), Pattern::PqNN, true, b"p", None, None).unwrap();
+    let r = HandshakeState::new(kem, Pattern::PqNN, false, b"p", None, None).unwrap();
+
+    let (i, r) = handshake(i, r);
+    let (mut ti, pi, _) = i.unwrap();
+    let (mut tr, pr, _) = r.unwrap();
+    assert_eq!(pr, vec![b"payload 0".to_vec()]);
+    assert_eq!(pi, vec![b"payload 1".to_vec()]);
+    transport(&mut ti, &mut tr);
+
+    // Message sizes: e, then ekem and the payload encrypted
+    let kem = crate::kyber768kem();
+    let mut i = HandshakeState::new(kem.clone(), Pattern::PqNN, true, b"", None, None).unwrap();
+    let mut r = HandshakeState::new(kem, Pattern::PqNN, false, b"", None, None).unwrap();
+    let m1 = i.write_message(b"").unwrap();
+    assert_eq!(m1.len(), Kyber768::PK_BYTES);
+    r.read_message(&m1).unwrap();
+    // A payload too long for the message is rejected without changing the state
+    let max = MAX_MESSAGE_BYTES - Kyber768::CT_BYTES - TAGLEN;
+    assert!(r.write_message(&vec![0; max + 1]).is_err());
+    let m2 = r.write_message(b"").unwrap();
+    assert_eq!(m2.len(), Kyber768::CT_BYTES + TAGLEN);
+    assert_eq!(i.read_message(&m2).unwrap(), b"");
+    assert!(r.write_message(b"").is_err());
+}
+
+#[test]
+fn noise_pq_ik() {
+    let kem = crate::kyber512kem();
+    let (sk_i, pk_i) = kem.keygen();
+    let (sk_r, pk_r) = kem.keygen();
+
+    let i = HandshakeState::new(
+        kem.clone(),
+        Pattern::PqIK,
+        true,
+        b"prologue",
+        Some((sk_i.clone(), pk_i.clone())),
+        Some(pk_r.clone()),
+    )
+    .unwrap();
+    let r = HandshakeState::new(
+        kem.clone(),
+        Pattern::PqIK,
+        false,
+        b"prologue",
+        Some((sk_r.clone(), pk_r.clone())),
+        None,
+    )
+    .unwrap();
+
+    let (i, r) = handshake(i, r);
+    let (mut ti, _, rs_i) = i.unwrap();
+    let (mut tr, pr, rs_r) = r.unwrap();
+    assert_eq!(rs_i, Some(pk_r.clone()));
+    assert_eq!(rs_r, Some(pk_i.clone()));
+    assert_eq!(pr, vec![b"payload 0".to_vec()]);
+    transport(&mut ti, &mut tr);
+
+    // An initiator with the wrong responder key fails at the first message
+    let (_, pk_m) = kem.keygen();
+    let mut i = HandshakeState::new(
+        kem.clone(),
+        Pattern::PqIK,
+        true,
+        b"prologue",
+        Some((sk_i.clone(), pk_i.clone())),
+        Some(pk_m),
+    )
+    .unwrap();
+    let mut r = HandshakeState::new(
+        kem.clone(),
+        Pattern::PqIK,
+        false,
+        b"prologue",
+        Some((sk_r.clone(), pk_r.clone())),
+        None,
+    )
+    .unwrap();
+    assert!(r.read_message(&i.write_message(b"").unwrap()).is_err());
+
+    // Missing keys are rejected
+    assert!(HandshakeState::new(kem.clone(), Pattern::PqIK, true, b"", None, Some(pk_r)).is_err());
+    assert!(HandshakeState::new(kem, Pattern::PqIK, true, b"", Some((sk_i, pk_i)), None).is_err());
+}
+
+#[test]
+fn noise_pq_xx() {
+    let kem = crate::kyber1024kem();
+    let (sk_i, pk_i) = kem.keygen();
+    let (sk_r, pk_r) = kem.keygen();
+    let state = |initiator: bool, prologue: &[u8]| {
+        let s = if initiator {
+            (sk_i.clone(), pk_i.clone())
+        } else {
+            (sk_r.clone(), pk_r.clone())
+        };
+        HandshakeState::new(
+            kem.clone(),
+            Pattern::PqXX,
+            initiator,
+            prologue,
+            Some(s),
+            None,
+        )
+        .unwrap()
+    };
+
+    let (i, r) = handshake(state(true, b"v1"), state(false, b"v1"));
+    let (mut ti, pi, rs_i) = i.unwrap();
+    let (mut tr, pr, rs_r) = r.unwrap();
+    assert_eq!(rs_i.as_ref(), Some(&pk_r));
+    assert_eq!(rs_r.as_ref(), Some(&pk_i));
+    assert_eq!(pr, vec![b"payload 0".to_vec(), b"payload 2".to_vec()]);
+    assert_eq!(pi, vec![b"payload 1".to_vec(), b"payload 3".to_vec()]);
+    transport(&mut ti, &mut tr);
+
+    // Prologues must match
+    let mut i = state(true, b"v1");
+    let mut r = state(false, b"v2");
+    r.read_message(&i.write_message(b"").unwrap()).unwrap();
+    assert!(i.read_message(&r.write_message(b"").unwrap()).is_err());
+
+    // Tampered messages are rejected
+    let mut i = state(true, b"v1");
+    let mut r = state(false, b"v1");
+    r.read_message(&i.write_message(b"").unwrap()).unwrap();
+    let mut m2 = r.write_message(b"").unwrap();
+    m2[10] ^= 1;
+    assert!(i.read_message(&m2).is_err());
+    assert!(i.read_message(&m2[..100]).is_err());
+    assert!(i.into_transport().is_err());
+}
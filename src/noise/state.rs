@@ -0,0 +1,235 @@
+//! Symmetric states
+//!
+//! CipherState and SymmetricState of the Noise specification (section 5), with
+//! ChaChaPoly and SHA256.
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+
+/// Size of a hash output and of a chaining key
+pub const HASHLEN: usize = 32;
+
+/// Size of an authentication tag
+pub const TAGLEN: usize = 16;
+
+/// Cipher key and nonce counter
+#[derive(Clone, Default)]
+pub struct CipherState {
+    k: Option<[u8; 32]>,
+    n: u64,
+}
+
+impl CipherState {
+    /// Cipher state with the key `k` and a zero nonce
+    pub fn new(k: [u8; 32]) -> Self {
+        Self { k: Some(k), n: 0 }
+    }
+
+    /// Whether a key is set
+    pub fn has_key(&self) -> bool {
+        self.k.is_some()
+    }
+
+    /// 96-bit nonce: 32 zero bits followed by the little-endian counter
+    fn nonce(&self) -> Result<[u8; 12], String> {
+        // The maximum nonce is reserved
+        if self.n == u64::MAX {
+            return Err("nonce exhausted".to_string());
+        }
+        let mut nonce = [0; 12];
+        nonce[4..].copy_from_slice(&self.n.to_le_bytes());
+
+        Ok(nonce)
+    }
+
+    /// Encrypt `pt` with the associated data `ad`, or return it if no key is set
+    pub fn encrypt_with_ad(&mut self, ad: &[u8], pt: &[u8]) -> Result<Vec<u8>, String> {
+        let k = match self.k {
+            Some(k) => k,
+            None => return Ok(pt.to_vec()),
+        };
+        let nonce = self.nonce()?;
+        let ct = ChaCha20Poly1305::new(&k.into())
+            .encrypt(&nonce.into(), Payload { msg: pt, aad: ad })
+            .map_err(|_| "encryption failed".to_string())?;
+        self.n += 1;
+
+        Ok(ct)
+    }
+
+    /// Decrypt `ct` with the associated data `ad`, or return it if no key is set
+    ///
+    /// The nonce is only incremented on success.
+    pub fn decrypt_with_ad(&mut self, ad: &[u8], ct: &[u8]) -> Result<Vec<u8>, String> {
+        let k = match self.k {
+            Some(k) => k,
+            None => return Ok(ct.to_vec()),
+        };
+        let nonce = self.nonce()?;
+        let pt = ChaCha20Poly1305::new(&k.into())
+            .decrypt(&nonce.into(), Payload { msg: ct, aad: ad })
+            .map_err(|_| "decryption failed".to_string())?;
+        self.n += 1;
+
+        Ok(pt)
+    }
+}
+
+/// Noise HKDF with two outputs: HKDF-SHA256 of `ikm` salted with `ck`, with an empty info
+fn hkdf2(ck: &[u8], ikm: &[u8]) -> ([u8; HASHLEN], [u8; HASHLEN]) {
+    let mut okm = [0; 2 * HASHLEN];
+    // Unwraps safely since the output is shorter than 255 hashes
+    Hkdf::<Sha256>::new(Some(ck), ikm)
+        .expand(&[], &mut okm)
+        .unwrap();
+
+    let mut out1 = [0; HASHLEN];
+    let mut out2 = [0; HASHLEN];
+    out1.copy_from_slice(&okm[..HASHLEN]);
+    out2.copy_from_slice(&okm[HASHLEN..]);
+
+    (out1, out2)
+}
+
+/// Chaining key, handshake hash and cipher state of a handshake
+#[derive(Clone)]
+pub struct SymmetricState {
+    cipher: CipherState,
+    ck: [u8; HASHLEN],
+    h: [u8; HASHLEN],
+}
+
+impl SymmetricState {
+    /// Initialize from the protocol name
+    pub fn new(protocol_name: &str) -> Self {
+        let name = protocol_name.as_bytes();
+        let mut h = [0; HASHLEN];
+        if name.len() <= HASHLEN {
+            h[..name.len()].copy_from_slice(name);
+        } else {
+            h.copy_from_slice(&Sha256::digest(name));
+        }
+
+        Self {
+            cipher: CipherState::default(),
+            ck: h,
+            h,
+        }
+    }
+
+    /// Mix input key material into the chaining key and rekey the cipher
+    pub fn mix_key(&mut self, ikm: &[u8]) {
+        let (ck, k) = hkdf2(&self.ck, ikm);
+        self.ck = ck;
+        self.cipher = CipherState::new(k);
+    }
+
+    /// Mix data into the handshake hash
+    pub fn mix_hash(&mut self, data: &[u8]) {
+        let mut hasher = Sha256::new();
+        hasher.update(self.h);
+        hasher.update(data);
+        self.h.copy_from_slice(&hasher.finalize());
+    }
+
+    /// Whether the cipher has a key
+    pub fn has_key(&self) -> bool {
+        self.cipher.has_key()
+    }
+
+    /// Encrypt `pt` bound to the handshake hash, and mix the ciphertext
+    pub fn encrypt_and_hash(&mut self, pt: &[u8]) -> Result<Vec<u8>, String> {
+        let ct = self.cipher.encrypt_with_ad(&self.h, pt)?;
+        self.mix_hash(&ct);
+
+        Ok(ct)
+    }
+
+    /// Decrypt `ct` bound to the handshake hash, and mix the ciphertext
+    pub fn decrypt_and_hash(&mut self, ct: &[u8]) -> Result<Vec<u8>, String> {
+        let pt = self.cipher.decrypt_with_ad(&self.h, ct)?;
+        self.mix_hash(ct);
+
+        Ok(pt)
+    }
+
+    /// Handshake hash
+    pub fn handshake_hash(&self) -> [u8; HASHLEN] {
+        self.h
+    }
+
+    /// Derive the two transport cipher states: (initiator to responder, responder to initiator)
+    pub fn split(&self) -> (CipherState, CipherState) {
+        let (k1, k2) = hkdf2(&self.ck, &[]);
+
+        (CipherState::new(k1), CipherState::new(k2))
+    }
+}
+
+#[test]
+fn noise_cipher_state() {
+    let mut alice = CipherState::new([7; 32]);
+    let mut bob = alice.clone();
+
+    let c0 = alice.encrypt_with_ad(b"ad", b"first").unwrap();
+    let c1 = alice.encrypt_with_ad(b"ad", b"first").unwrap();
+    assert_eq!(c0.len(), 5 + TAGLEN);
+    // Nonces are never reused
+    assert_ne!(c0, c1);
+
+    assert!(bob.decrypt_with_ad(b"other", &c0).is_err());
+    assert!(bob.decrypt_with_ad(b"ad", &c1).is_err());
+    assert_eq!(bob.decrypt_with_ad(b"ad", &c0).unwrap(), b"first");
+    assert_eq!(bob.decrypt_with_ad(b"ad", &c1).unwrap(), b"first");
+
+    // Without a key, the plaintext goes through
+    let mut empty = CipherState::default();
+    assert_eq!(empty.encrypt_with_ad(b"ad", b"clear").unwrap(), b"clear");
+
+    let mut exhausted = CipherState {
+        k: Some([0; 32]),
+        n: u64::MAX,
+    };
+    assert!(exhausted.encrypt_with_ad(b"", b"").is_err());
+}
+
+#[test]
+fn noise_symmetric_state() {
+    use crate::formats::hex;
+
+    // Short names are padded, long names are hashed
+    let short = SymmetricState::new("Noise_NN");
+    assert_eq!(&short.handshake_hash()[..8], b"Noise_NN");
+    assert_eq!(short.handshake_hash()[8..], [0; 24]);
+    let name = "Noise_pqNN_Kyber768_ChaChaPoly_SHA256";
+    assert_eq!(
+        SymmetricState::new(name).handshake_hash()[..],
+        Sha256::digest(name.as_bytes())[..]
+    );
+
+    // HKDF test case 3 of RFC 5869: empty salt and info
+    let ikm = [0x0b; 22];
+    let (out1, out2) = hkdf2(&[], &ikm);
+    assert_eq!(
+        hex::encode(&[out1, out2].concat()[..42]),
+        "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d\
+         9d201395faa4b61a96c8"
+    );
+
+    let mut a = SymmetricState::new(name);
+    let mut b = SymmetricState::new(name);
+    assert_eq!(a.encrypt_and_hash(b"clear").unwrap(), b"clear");
+    assert_eq!(b.decrypt_and_hash(b"clear").unwrap(), b"clear");
+    a.mix_key(b"secret");
+    b.mix_key(b"secret");
+    let ct = a.encrypt_and_hash(b"payload").unwrap();
+    assert_eq!(b.decrypt_and_hash(&ct).unwrap(), b"payload");
+    assert_eq!(a.handshake_hash(), b.handshake_hash());
+
+    let (mut a1, _) = a.split();
+    let (mut b1, _) = b.split();
+    let ct = a1.encrypt_with_ad(&[], b"transport").unwrap();
+    assert_eq!(b1.decrypt_with_ad(&[], &ct).unwrap(), b"transport");
+}
@@ -0,0 +1,447 @@
+//! Packets
+//!
+//! OpenPGP packet framing (RFC 9580 section 4.2) and the v6 public key, secret key and
+//! PKESK packets of the composite algorithms.
+
+use super::{decrypt, encrypt, Algorithm, EncryptedSessionKey, PublicKey, SecretKey};
+
+use sha2::{Digest, Sha256};
+
+use std::convert::TryFrom;
+
+/// Public-Key Encrypted Session Key packet tag
+pub const PKESK_TAG: u8 = 1;
+/// Secret-Key packet tag
+pub const SECRET_KEY_TAG: u8 = 5;
+/// Public-Key packet tag
+pub const PUBLIC_KEY_TAG: u8 = 6;
+/// Secret-Subkey packet tag
+pub const SECRET_SUBKEY_TAG: u8 = 7;
+/// Public-Subkey packet tag
+pub const PUBLIC_SUBKEY_TAG: u8 = 14;
+
+/// Key and PKESK version
+const VERSION: u8 = 6;
+
+/// Size of a v6 fingerprint
+const FINGERPRINT_BYTES: usize = 32;
+
+/// Encode a packet with an OpenPGP format header
+pub fn encode_packet(tag: u8, body: &[u8]) -> Vec<u8> {
+    let len = body.len();
+    let mut out = vec![0xc0 | tag];
+
+    if len < 192 {
+        out.push(len as u8);
+    } else if len < 8384 {
+        out.push((((len - 192) >> 8) + 192) as u8);
+        out.push((len - 192) as u8);
+    } else {
+        out.push(0xff);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+    out.extend_from_slice(body);
+
+    out
+}
+
+/// Decode the first packet of a buffer => (tag, body, rest)
+///
+/// Legacy format headers and partial body lengths are rejected.
+pub fn decode_packet(data: &[u8]) -> Result<(u8, &[u8], &[u8]), String> {
+    let (&header, rest) = data.split_first().ok_or("empty packet")?;
+    if header & 0xc0 != 0xc0 {
+        return Err("unsupported packet header".to_string());
+    }
+
+    let (len, rest) = match rest {
+        [first @ 0..=191, rest @ ..] => (*first as usize, rest),
+        [first @ 192..=223, second, rest @ ..] => (
+            ((*first as usize - 192) << 8) + *second as usize + 192,
+            rest,
+        ),
+        [255, a, b, c, d, rest @ ..] => (u32::from_be_bytes([*a, *b, *c, *d]) as usize, rest),
+        [224..=254, ..] => return Err("partial body lengths are not supported".to_string()),
+        _ => return Err("truncated packet length".to_string()),
+    };
+    if rest.len() < len {
+        return Err("truncated packet body".to_string());
+    }
+    let (body, rest) = rest.split_at(len);
+
+    Ok((header & 0x3f, body, rest))
+}
+
+/// v6 fingerprint of a key packet body
+pub fn fingerprint(body: &[u8]) -> [u8; FINGERPRINT_BYTES] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x9b]);
+    hasher.update((body.len() as u32).to_be_bytes());
+    hasher.update(body);
+    hasher.finalize().into()
+}
+
+/// v6 public key or public subkey packet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicKeyPacket {
+    /// Creation time, in seconds since the epoch
+    pub created: u32,
+    /// Key material
+    pub key: PublicKey,
+}
+
+impl PublicKeyPacket {
+    /// Packet body
+    pub fn to_body(&self) -> Vec<u8> {
+        let material = self.key.to_bytes();
+        [
+            &[VERSION][..],
+            &self.created.to_be_bytes(),
+            &[self.key.algorithm.id()],
+            &(material.len() as u32).to_be_bytes(),
+            &material,
+        ]
+        .concat()
+    }
+
+    /// Decode a packet body => (packet, rest of the body)
+    fn read_body(body: &[u8]) -> Result<(Self, &[u8]), String> {
+        let (header, rest) = match body {
+            [VERSION, a, b, c, d, id, e, f, g, h, rest @ ..] => {
+                ([*a, *b, *c, *d, *id, *e, *f, *g, *h], rest)
+            }
+            [VERSION, ..] => return Err("truncated key packet".to_string()),
+            _ => return Err("unsupported key packet version".to_string()),
+        };
+        let created = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let algorithm = Algorithm::from_id(header[4])?;
+        let len = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) as usize;
+        if rest.len() < len {
+            return Err("truncated key material".to_string());
+        }
+        let (material, rest) = rest.split_at(len);
+
+        let key = PublicKey::from_bytes(algorithm, material)?;
+        Ok((Self { created, key }, rest))
+    }
+
+    /// Decode a packet body
+    pub fn from_body(body: &[u8]) -> Result<Self, String> {
+        match Self::read_body(body)? {
+            (packet, []) => Ok(packet),
+            _ => Err("trailing key packet data".to_string()),
+        }
+    }
+
+    /// v6 fingerprint
+    pub fn fingerprint(&self) -> [u8; FINGERPRINT_BYTES] {
+        fingerprint(&self.to_body())
+    }
+
+    /// Encode as a public key or public subkey packet
+    pub fn to_packet(&self, subkey: bool) -> Vec<u8> {
+        let tag = if subkey {
+            PUBLIC_SUBKEY_TAG
+        } else {
+            PUBLIC_KEY_TAG
+        };
+        encode_packet(tag, &self.to_body())
+    }
+
+    /// Decode a public key or public subkey packet => (packet, subkey)
+    pub fn from_packet(data: &[u8]) -> Result<(Self, bool), String> {
+        match decode_packet(data)? {
+            (tag, body, []) if tag == PUBLIC_KEY_TAG || tag == PUBLIC_SUBKEY_TAG => {
+                Ok((Self::from_body(body)?, tag == PUBLIC_SUBKEY_TAG))
+            }
+            (_, _, []) => Err("not a public key packet".to_string()),
+            _ => Err("trailing packet data".to_string()),
+        }
+    }
+}
+
+/// v6 secret key or secret subkey packet with unencrypted key material
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretKeyPacket {
+    /// Public part
+    pub public: PublicKeyPacket,
+    /// Secret key material
+    pub secret: SecretKey,
+}
+
+impl SecretKeyPacket {
+    /// Secret key packet of a new key
+    pub fn generate(algorithm: Algorithm, created: u32) -> Self {
+        let secret = SecretKey::generate(algorithm);
+        // A random seed has the right size
+        let key = secret.public_key().unwrap();
+        Self {
+            public: PublicKeyPacket { created, key },
+            secret,
+        }
+    }
+
+    /// Packet body, with string-to-key usage 0
+    pub fn to_body(&self) -> Vec<u8> {
+        [&self.public.to_body()[..], &[0], &self.secret.to_bytes()].concat()
+    }
+
+    /// Decode a packet body, the secret key material must match the public key
+    pub fn from_body(body: &[u8]) -> Result<Self, String> {
+        let (public, rest) = PublicKeyPacket::read_body(body)?;
+        let material = match rest.split_first() {
+            Some((0, material)) => material,
+            Some(_) => return Err("encrypted secret keys are not supported".to_string()),
+            None => return Err("truncated secret key packet".to_string()),
+        };
+
+        let secret = SecretKey::from_bytes(public.key.algorithm, material)?;
+        if secret.public_key()? != public.key {
+            return Err("secret key does not match the public key".to_string());
+        }
+        Ok(Self { public, secret })
+    }
+
+    /// Encode as a secret key or secret subkey packet
+    pub fn to_packet(&self, subkey: bool) -> Vec<u8> {
+        let tag = if subkey {
+            SECRET_SUBKEY_TAG
+        } else {
+            SECRET_KEY_TAG
+        };
+        encode_packet(tag, &self.to_body())
+    }
+
+    /// Decode a secret key or secret subkey packet => (packet, subkey)
+    pub fn from_packet(data: &[u8]) -> Result<(Self, bool), String> {
+        match decode_packet(data)? {
+            (tag, body, []) if tag == SECRET_KEY_TAG || tag == SECRET_SUBKEY_TAG => {
+                Ok((Self::from_body(body)?, tag == SECRET_SUBKEY_TAG))
+            }
+            (_, _, []) => Err("not a secret key packet".to_string()),
+            _ => Err("trailing packet data".to_string()),
+        }
+    }
+}
+
+/// v6 Public-Key Encrypted Session Key packet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pkesk {
+    /// Fingerprint of the v6 recipient key, `None` for an anonymous recipient
+    pub recipient: Option<[u8; FINGERPRINT_BYTES]>,
+    /// Algorithm-specific fields
+    pub esk: EncryptedSessionKey,
+}
+
+impl Pkesk {
+    /// Encrypt a session key to a key packet
+    ///
+    /// A v6 PKESK carries the bare session key: the symmetric algorithm is given by the
+    /// v2 SEIPD packet.
+    pub fn encrypt(
+        key: &PublicKeyPacket,
+        session_key: &[u8],
+        anonymous: bool,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            recipient: if anonymous {
+                None
+            } else {
+                Some(key.fingerprint())
+            },
+            esk: encrypt(&key.key, session_key)?,
+        })
+    }
+
+    /// Decrypt the session key with a secret key packet
+    pub fn decrypt(&self, key: &SecretKeyPacket) -> Result<Vec<u8>, String> {
+        if let Some(recipient) = self.recipient {
+            if recipient != key.public.fingerprint() {
+                return Err("PKESK is for another key".to_string());
+            }
+        }
+        decrypt(&key.secret, &self.esk)
+    }
+
+    /// Packet body
+    pub fn to_body(&self) -> Result<Vec<u8>, String> {
+        let mut out = vec![VERSION];
+        match self.recipient {
+            Some(fingerprint) => {
+                out.extend_from_slice(&[1 + FINGERPRINT_BYTES as u8, VERSION]);
+                out.extend_from_slice(&fingerprint);
+            }
+            None => out.push(0),
+        }
+        out.push(self.esk.algorithm.id());
+        out.extend(self.esk.to_bytes()?);
+        Ok(out)
+    }
+
+    /// Decode a packet body
+    pub fn from_body(body: &[u8]) -> Result<Self, String> {
+        let (recipient, rest) = match body {
+            [VERSION, 0, rest @ ..] => (None, rest),
+            [VERSION, 33, VERSION, rest @ ..] if rest.len() >= FINGERPRINT_BYTES => {
+                let (fingerprint, rest) = rest.split_at(FINGERPRINT_BYTES);
+                // The slice has the fingerprint size
+                (
+                    Some(<[u8; FINGERPRINT_BYTES]>::try_from(fingerprint).unwrap()),
+                    rest,
+                )
+            }
+            [VERSION, ..] => return Err("unsupported PKESK recipient".to_string()),
+            _ => return Err("unsupported PKESK version".to_string()),
+        };
+        let (id, fields) = rest.split_first().ok_or("truncated PKESK")?;
+        let esk = EncryptedSessionKey::from_bytes(Algorithm::from_id(*id)?, fields)?;
+
+        Ok(Self { recipient, esk })
+    }
+
+    /// Encode as a packet
+    pub fn to_packet(&self) -> Result<Vec<u8>, String> {
+        Ok(encode_packet(PKESK_TAG, &self.to_body()?))
+    }
+
+    /// Decode a packet
+    pub fn from_packet(data: &[u8]) -> Result<Self, String> {
+        match decode_packet(data)? {
+            (PKESK_TAG, body, []) => Self::from_body(body),
+            (_, _, []) => Err("not a PKESK packet".to_string()),
+            _ => Err("trailing packet data".to_string()),
+        }
+    }
+}
+
+#[test]
+fn openpgp_packet_lengths() {
+    for (len, header) in [
+        (0, &[0xc1, 0][..]),
+        (191, &[0xc1, 191]),
+        (192, &[0xc1, 192, 0]),
+        (1723, &[0xc1, 197, 251]),
+        (8383, &[0xc1, 223, 255]),
+        (8384, &[0xc1, 255, 0, 0, 0x20, 0xc0]),
+        (100000, &[0xc1, 255, 0, 1, 0x86, 0xa0]),
+    ]
+    .iter()
+    {
+        let body = vec![7; *len];
+        let packet = encode_packet(PKESK_TAG, &body);
+        assert_eq!(packet[..header.len()], **header);
+        assert_eq!(
+            decode_packet(&packet).unwrap(),
+            (PKESK_TAG, &body[..], &[][..])
+        );
+    }
+
+    // Legacy format, partial length, truncated
+    assert!(decode_packet(&[0x84, 0]).is_err());
+    assert!(decode_packet(&[0xc1, 224]).is_err());
+    assert!(decode_packet(&[0xc1, 200]).is_err());
+    assert!(decode_packet(&[0xc1, 2, 0]).is_err());
+    assert!(decode_packet(&[]).is_err());
+}
+
+#[test]
+fn openpgp_fingerprint_rfc9580() {
+    // Appendix A.3: v6 Ed25519 primary key
+    use crate::formats::hex;
+
+    let body = hex::decode(
+        "0663877fe31b00000020f94da7bb48d60a61e567706a6587d0331999bb9d891a08242ead84543df895a3",
+    )
+    .unwrap();
+    assert_eq!(
+        hex::encode(&fingerprint(&body)),
+        "cb186c4f0609a697e4d52dfa6c722b0c1f1e27c18a56708f6525ec27bad9acc9"
+    );
+}
+
+#[test]
+fn openpgp_key_packets() {
+    for algorithm in super::ALGORITHMS.iter() {
+        let secret = SecretKeyPacket::generate(*algorithm, 0x6387_7fe3);
+        let public = secret.public.clone();
+
+        let packet = public.to_packet(true);
+        assert_eq!(packet[0], 0xc0 | PUBLIC_SUBKEY_TAG);
+        assert_eq!(
+            PublicKeyPacket::from_packet(&packet).unwrap(),
+            (public.clone(), true)
+        );
+        assert_eq!(
+            PublicKeyPacket::from_packet(&public.to_packet(false)).unwrap(),
+            (public.clone(), false)
+        );
+        let body = public.to_body();
+        assert_eq!(body[..6], [6, 0x63, 0x87, 0x7f, 0xe3, algorithm.id()]);
+        assert_eq!(body[6..10], (algorithm.public_bytes() as u32).to_be_bytes());
+
+        let packet = secret.to_packet(true);
+        let (decoded, subkey) = SecretKeyPacket::from_packet(&packet).unwrap();
+        assert!(subkey && decoded == secret);
+        assert_eq!(decoded.public.fingerprint(), public.fingerprint());
+
+        // Wrong tags, trailing data, mismatched or encrypted secret material
+        assert!(PublicKeyPacket::from_packet(&secret.to_packet(true)).is_err());
+        assert!(SecretKeyPacket::from_packet(&public.to_packet(true)).is_err());
+        assert!(PublicKeyPacket::from_body(&[&body[..], &[0]].concat()).is_err());
+        let mut body = secret.to_body();
+        body[10 + algorithm.public_bytes() + 1 + algorithm.ecdh_bytes()] ^= 1;
+        assert!(SecretKeyPacket::from_body(&body).is_err());
+        let mut body = secret.to_body();
+        body[10 + algorithm.public_bytes()] = 254;
+        assert!(SecretKeyPacket::from_body(&body).is_err());
+        let mut body = public.to_body();
+        body[0] = 4;
+        assert!(PublicKeyPacket::from_body(&body).is_err());
+    }
+}
+
+#[test]
+fn openpgp_pkesk() {
+    for algorithm in super::ALGORITHMS.iter() {
+        let key = SecretKeyPacket::generate(*algorithm, 0);
+        let other = SecretKeyPacket::generate(*algorithm, 0);
+        let session_key = [0x11; 32];
+
+        for anonymous in [false, true].iter() {
+            let pkesk = Pkesk::encrypt(&key.public, &session_key, *anonymous).unwrap();
+            let packet = pkesk.to_packet().unwrap();
+            let decoded = Pkesk::from_packet(&packet).unwrap();
+            assert_eq!(decoded, pkesk);
+            assert_eq!(decoded.decrypt(&key).unwrap(), session_key);
+            assert!(decoded.decrypt(&other).is_err());
+
+            // Header: version, recipient, algorithm
+            let (_, body, _) = decode_packet(&packet).unwrap();
+            if *anonymous {
+                assert_eq!(body[..3], [6, 0, algorithm.id()]);
+            } else {
+                assert_eq!(body[..3], [6, 33, 6]);
+                assert_eq!(body[3..35], key.public.fingerprint());
+                assert_eq!(body[35], algorithm.id());
+            }
+        }
+    }
+
+    let key = SecretKeyPacket::generate(Algorithm::MlKem768X25519, 0);
+    let body = Pkesk::encrypt(&key.public, &[0; 16], false)
+        .unwrap()
+        .to_body()
+        .unwrap();
+    let altered = |index: usize, value: u8| {
+        let mut body = body.clone();
+        body[index] = value;
+        Pkesk::from_body(&body)
+    };
+    assert!(altered(0, 3).is_err());
+    assert!(altered(1, 9).is_err());
+    assert!(altered(2, 4).is_err());
+    assert!(altered(35, 18).is_err());
+    assert!(altered(35, 101).is_err());
+    assert!(Pkesk::from_body(&body[..body.len() - 1]).is_err());
+    assert!(Pkesk::from_body(&body[..30]).is_err());
+}
@@ -0,0 +1,236 @@
+//! Custom parameters
+//!
+//! Runtime description of a parameter set, with a checked builder for non-standard combinations
+
+use crate::analysis::failure_log2;
+use crate::params::ParameterSet;
+
+/// Size in bits of the coefficients of an uncompressed polynomial
+const COEFF_BITS: usize = 12;
+
+/// Checks that a combination of parameters is supported by the implementation
+///
+/// Returns the reason of the rejection if it is not. This is a `const fn` so that
+/// `PKE::init` can reject an invalid `ParameterSet` at compile time.
+pub const fn check_parameters(
+    n: usize,
+    k: usize,
+    eta1: usize,
+    eta2: usize,
+    du: usize,
+    dv: usize,
+) -> Result<(), &'static str> {
+    if !n.is_multiple_of(2) {
+        return Err("N must be even (the NTT operates modulo X^2 - zeta)");
+    }
+    if n != 256 {
+        return Err("only N = 256 is supported");
+    }
+    if k == 0 {
+        return Err("K must be at least 1");
+    }
+    if eta1 == 0 || eta2 == 0 {
+        return Err("ETA1 and ETA2 must be at least 1");
+    }
+    if du == 0 || du > COEFF_BITS {
+        return Err("DU must be between 1 and 12");
+    }
+    if dv == 0 || dv > COEFF_BITS {
+        return Err("DV must be between 1 and 12");
+    }
+    Ok(())
+}
+
+/// Runtime description of a parameter set, with all the derived sizes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameters {
+    /// Degree of the polynomial ring
+    pub n: usize,
+    /// Rank of the module
+    pub k: usize,
+    /// Noise parameter for keygen secrets and encryption randomness
+    pub eta1: usize,
+    /// Noise parameter for encryption errors
+    pub eta2: usize,
+    /// Compression of `u`
+    pub du: usize,
+    /// Compression of `v`
+    pub dv: usize,
+    /// Size of the public key
+    pub pk_bytes: usize,
+    /// Size of the PKE secret key
+    pub pke_sk_bytes: usize,
+    /// Size of the KEM secret key
+    pub sk_bytes: usize,
+    /// Size of the ciphertext
+    pub ct_bytes: usize,
+    /// Size of the shared secret
+    pub ss_bytes: usize,
+    /// Decryption failure rate as -log2(probability), see `analysis::failure_log2`
+    pub delta: f64,
+}
+
+impl Parameters {
+    /// Describe a compile-time parameter set, checking that it is supported
+    pub fn of<P: ParameterSet>() -> Result<Self, String> {
+        let p = ParameterSetBuilder::new()
+            .n(P::N)
+            .k(P::K)
+            .eta1(P::ETA1)
+            .eta2(P::ETA2)
+            .du(P::DU)
+            .dv(P::DV)
+            .build()?;
+
+        // The sizes of the trait must agree with the ones derived here
+        if (p.pk_bytes, p.sk_bytes, p.ct_bytes, p.ss_bytes)
+            != (P::PK_BYTES, P::SK_BYTES, P::CT_BYTES, P::SS_BYTES)
+        {
+            return Err("byte sizes do not match the parameters".to_string());
+        }
+
+        Ok(p)
+    }
+}
+
+/// Checked builder for custom parameter sets
+///
+/// Starts from the Kyber 768 parameters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParameterSetBuilder {
+    n: usize,
+    k: usize,
+    eta1: usize,
+    eta2: usize,
+    du: usize,
+    dv: usize,
+}
+
+impl ParameterSetBuilder {
+    /// Initialise the builder with the Kyber 768 parameters
+    pub const fn new() -> Self {
+        Self {
+            n: 256,
+            k: 3,
+            eta1: 2,
+            eta2: 2,
+            du: 10,
+            dv: 4,
+        }
+    }
+
+    /// Set the degree of the polynomial ring
+    pub const fn n(mut self, n: usize) -> Self {
+        self.n = n;
+        self
+    }
+
+    /// Set the rank of the module
+    pub const fn k(mut self, k: usize) -> Self {
+        self.k = k;
+        self
+    }
+
+    /// Set the noise parameter of keygen secrets and encryption randomness
+    pub const fn eta1(mut self, eta1: usize) -> Self {
+        self.eta1 = eta1;
+        self
+    }
+
+    /// Set the noise parameter of encryption errors
+    pub const fn eta2(mut self, eta2: usize) -> Self {
+        self.eta2 = eta2;
+        self
+    }
+
+    /// Set the compression of `u`
+    pub const fn du(mut self, du: usize) -> Self {
+        self.du = du;
+        self
+    }
+
+    /// Set the compression of `v`
+    pub const fn dv(mut self, dv: usize) -> Self {
+        self.dv = dv;
+        self
+    }
+
+    /// Check the parameters and derive the sizes and failure rate
+    pub fn build(&self) -> Result<Parameters, String> {
+        let Self {
+            n,
+            k,
+            eta1,
+            eta2,
+            du,
+            dv,
+        } = self.clone();
+        check_parameters(n, k, eta1, eta2, du, dv)?;
+
+        let polyvec_bytes = COEFF_BITS * k * n / 8;
+        let pk_bytes = polyvec_bytes + 32;
+        let pke_sk_bytes = polyvec_bytes;
+
+        Ok(Parameters {
+            n,
+            k,
+            eta1,
+            eta2,
+            du,
+            dv,
+            pk_bytes,
+            pke_sk_bytes,
+            sk_bytes: pke_sk_bytes + pk_bytes + 32 + 32,
+            ct_bytes: (du * k + dv) * n / 8,
+            ss_bytes: 32,
+            delta: -failure_log2(n, k, eta1, eta2, du, dv),
+        })
+    }
+}
+
+impl Default for ParameterSetBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn builder_sizes_match_presets() {
+    use crate::params::{Kyber512, Kyber768};
+
+    let p512 = Parameters::of::<Kyber512>().unwrap();
+    assert_eq!(
+        (p512.pk_bytes, p512.sk_bytes, p512.ct_bytes),
+        (800, 1632, 736)
+    );
+
+    let p768 = Parameters::of::<Kyber768>().unwrap();
+    assert_eq!(
+        (p768.pk_bytes, p768.sk_bytes, p768.ct_bytes),
+        (1184, 2400, 1088)
+    );
+
+    // Failure rates of the specification: 2^-178 and 2^-164
+    assert!((p512.delta - 178.0).abs() < 2.0);
+    assert!((p768.delta - 164.0).abs() < 2.0);
+}
+
+#[test]
+fn builder_rejects_unsupported() {
+    assert!(ParameterSetBuilder::new().du(13).build().is_err());
+    assert!(ParameterSetBuilder::new().dv(0).build().is_err());
+    assert!(ParameterSetBuilder::new().n(255).build().is_err());
+    assert!(ParameterSetBuilder::new().n(512).build().is_err());
+    assert!(ParameterSetBuilder::new().k(0).build().is_err());
+    assert!(ParameterSetBuilder::new().eta2(0).build().is_err());
+}
+
+#[test]
+fn builder_delta_decreases_with_noise() {
+    let base = ParameterSetBuilder::new().build().unwrap();
+    let noisy = ParameterSetBuilder::new().eta1(5).build().unwrap();
+    let coarse = ParameterSetBuilder::new().du(6).dv(2).build().unwrap();
+
+    assert!(noisy.delta < base.delta);
+    assert!(coarse.delta < base.delta);
+}
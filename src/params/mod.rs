@@ -0,0 +1,113 @@
+//! Parameter sets
+//!
+//! Compile-time description of the Kyber parameter sets used by `PKE` and `KEM`
+
+mod custom;
+
+pub use custom::{check_parameters, ParameterSetBuilder, Parameters};
+
+/// A Kyber parameter set
+///
+/// Only `K`, `ETA1`, `ETA2`, `DU` and `DV` need to be provided, all the byte sizes
+/// are derived from them. Unsupported combinations are rejected when instantiating
+/// a `PKE`, use `Parameters::of` to get the derived sizes and failure rate.
+pub trait ParameterSet {
+    /// Degree of the polynomial ring R_q = Z_q[X]/(X^n+1)
+    const N: usize = 256;
+
+    /// Rank of the module (number of polynomials in a vector)
+    const K: usize;
+
+    /// Noise parameter for the secret and error vectors in key generation and `r` in encryption
+    const ETA1: usize;
+
+    /// Noise parameter for the errors `e1` and `e2` in encryption
+    const ETA2: usize;
+
+    /// Number of bits per coefficient of `u` in the ciphertext
+    const DU: usize;
+
+    /// Number of bits per coefficient of `v` in the ciphertext
+    const DV: usize;
+
+    /// Size of an encoded vector of K polynomials with 12-bit coefficients
+    const POLYVEC_BYTES: usize = 12 * Self::K * Self::N / 8;
+
+    /// Size of the public key (`t || rho`)
+    const PK_BYTES: usize = Self::POLYVEC_BYTES + 32;
+
+    /// Size of the PKE secret key
+    const PKE_SK_BYTES: usize = Self::POLYVEC_BYTES;
+
+    /// Size of the KEM secret key (`sk' || pk || H(pk) || z`)
+    const SK_BYTES: usize = Self::PKE_SK_BYTES + Self::PK_BYTES + 32 + 32;
+
+    /// Size of the first part of the ciphertext (compressed `u`)
+    const CT_U_BYTES: usize = Self::DU * Self::K * Self::N / 8;
+
+    /// Size of the ciphertext (compressed `u` and `v`)
+    const CT_BYTES: usize = Self::CT_U_BYTES + Self::DV * Self::N / 8;
+
+    /// Size of the shared secret
+    const SS_BYTES: usize = 32;
+}
+
+/// Kyber 512 parameters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Kyber512;
+
+impl ParameterSet for Kyber512 {
+    const K: usize = 2;
+    const ETA1: usize = 2;
+    const ETA2: usize = 2;
+    const DU: usize = 10;
+    const DV: usize = 3;
+}
+
+/// Kyber 768 parameters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Kyber768;
+
+impl ParameterSet for Kyber768 {
+    const K: usize = 3;
+    const ETA1: usize = 2;
+    const ETA2: usize = 2;
+    const DU: usize = 10;
+    const DV: usize = 4;
+}
+
+/// Kyber 1024 parameters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Kyber1024;
+
+impl ParameterSet for Kyber1024 {
+    const K: usize = 4;
+    const ETA1: usize = 2;
+    const ETA2: usize = 2;
+    const DU: usize = 11;
+    const DV: usize = 5;
+}
+
+#[test]
+fn kyber512_sizes() {
+    assert_eq!(Kyber512::PK_BYTES, 800);
+    assert_eq!(Kyber512::SK_BYTES, 1632);
+    assert_eq!(Kyber512::CT_BYTES, 736);
+    assert_eq!(Kyber512::SS_BYTES, 32);
+}
+
+#[test]
+fn kyber768_sizes() {
+    assert_eq!(Kyber768::PK_BYTES, 1184);
+    assert_eq!(Kyber768::SK_BYTES, 2400);
+    assert_eq!(Kyber768::CT_BYTES, 1088);
+    assert_eq!(Kyber768::SS_BYTES, 32);
+}
+
+#[test]
+fn kyber1024_sizes() {
+    assert_eq!(Kyber1024::PK_BYTES, 1568);
+    assert_eq!(Kyber1024::SK_BYTES, 3168);
+    assert_eq!(Kyber1024::CT_BYTES, 1568);
+    assert_eq!(Kyber1024::SS_BYTES, 32);
+}
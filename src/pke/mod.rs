@@ -8,43 +8,74 @@ use crate::functions::{
     ntt::*,
     utils::{cbd, g, parse, prf, xof},
 };
+use crate::params::{check_parameters, ParameterSet};
 use crate::structures::{
     algebraics::{FiniteRing, RingModule},
-    ByteArray, PolyMatrix3329, PolyVec3329,
+    ByteArray, PolyMatrix3329, PolyVec3329, F3329,
 };
+use crate::validate::{validate_ciphertext, validate_public_key};
+
+use std::marker::PhantomData;
 
 /// Default length used for XOF
 const XOF_LEN: usize = 4000;
 
+/// Modulus q
+const Q: usize = F3329::order();
+
+/// Kyber CPAPKE for the parameter set `P`
+///
+/// `N` and `K` must match `P::N` and `P::K`, and `P` must be supported by
+/// `check_parameters`, otherwise `init` fails to compile:
+///
+/// ```compile_fail
+/// use kybe_rs::{Kyber512, PKE};
+/// let pke = PKE::<Kyber512, 256, 3>::init();
+/// ```
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone)]
-pub struct PKE<const N: usize, const K: usize> {
-    eta: usize,
-    q: usize,
-    du: usize,
-    dv: usize,
+pub struct PKE<P: ParameterSet, const N: usize, const K: usize> {
+    params: PhantomData<P>,
 }
 
-impl<const N: usize, const K: usize> PKE<N, K> {
+impl<P: ParameterSet, const N: usize, const K: usize> PKE<P, N, K> {
+    /// Compile-time check that the dimensions match a supported parameter set
+    const PARAMETERS_VALID: () = {
+        assert!(
+            P::N == N && P::K == K,
+            "PKE dimensions do not match the parameter set"
+        );
+        if let Err(reason) = check_parameters(P::N, P::K, P::ETA1, P::ETA2, P::DU, P::DV) {
+            panic!("{}", reason);
+        }
+    };
+
     /// Kyber CPAPKE Key Generation => (secret key, public key)
     /// Algorithm 4 p. 9
     pub fn keygen(&self) -> (ByteArray, ByteArray) {
-        let d = ByteArray::random(32);
-        let (rho, sigma) = g(&d);
+        self.keygen_derand(&ByteArray::random(32))
+    }
+
+    /// Deterministic key generation from the 32-byte seed `d` => (secret key, public key)
+    pub fn keygen_derand(&self, d: &ByteArray) -> (ByteArray, ByteArray) {
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::check();
+        let (rho, sigma) = g(d);
 
         let mut a = PolyMatrix3329::init();
 
         for i in 0..K {
             for j in 0..K {
-                a.set(i, j, parse(&xof(&rho, j, i, XOF_LEN), self.q));
+                a.set(i, j, parse(&xof(&rho, j, i, XOF_LEN), Q));
             }
         }
 
         let (mut s, mut e) = (PolyVec3329::<N, K>::init(), PolyVec3329::<N, K>::init());
-        let prf_len = 64 * self.eta;
+        let prf_len = 64 * P::ETA1;
 
         for i in 0..K {
-            s.set(i, cbd(prf(&sigma, i, prf_len), self.eta));
-            e.set(i, cbd::<N>(prf(&sigma, K + i, prf_len), self.eta));
+            s.set(i, cbd(prf(&sigma, i, prf_len), P::ETA1));
+            e.set(i, cbd::<N>(prf(&sigma, K + i, prf_len), P::ETA1));
         }
         let s_hat = ntt_vec(&s);
         let e_hat = ntt_vec(&e);
@@ -60,39 +91,34 @@ impl<const N: usize, const K: usize> PKE<N, K> {
     /// Kyber CPAPKE Encryption : public key, message, random coins => ciphertext
     /// Algorithm 5 p. 10
     pub fn encrypt(&self, pk: &ByteArray, m: &ByteArray, r: ByteArray) -> ByteArray {
-        let offset = 12 * K * N / 8;
-        let prf_len = 64 * self.eta;
-
-        let (t, rho) = pk.split_at(offset);
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::check();
+        let (t, rho) = pk.split_at(P::POLYVEC_BYTES);
         let t_hat = decode_to_polyvec(t, 12);
         let mut a_t = PolyMatrix3329::init();
 
         for i in 0..K {
             for j in 0..K {
-                a_t.set(i, j, parse(&xof(&rho, i, j, XOF_LEN), self.q));
+                a_t.set(i, j, parse(&xof(&rho, i, j, XOF_LEN), Q));
             }
         }
 
         let (mut r_bold, mut e1) = (PolyVec3329::<N, K>::init(), PolyVec3329::<N, K>::init());
         for i in 0..K {
-            r_bold.set(i, cbd(prf(&r, i, prf_len), self.eta));
-            e1.set(i, cbd(prf(&r, K + i, prf_len), self.eta));
+            r_bold.set(i, cbd(prf(&r, i, 64 * P::ETA1), P::ETA1));
+            e1.set(i, cbd(prf(&r, K + i, 64 * P::ETA2), P::ETA2));
         }
-        let e2 = cbd(prf(&r, 2 * K, prf_len), self.eta);
+        let e2 = cbd(prf(&r, 2 * K, 64 * P::ETA2), P::ETA2);
 
         let r_hat = ntt_vec(&r_bold);
         let u_bold = ntt_product_matvec(&a_t, &r_hat).add(&e1);
 
         let v = ntt_product_vec(&t_hat, &r_hat)
             .add(&e2)
-            .add(&decompress_poly(
-                decode_to_poly::<N>(m.clone(), 1),
-                1,
-                self.q,
-            ));
+            .add(&decompress_poly(decode_to_poly::<N>(m.clone(), 1), 1, Q));
 
-        let c1 = encode_polyvec(compress_polyvec(u_bold, self.du, self.q), self.du);
-        let c2 = encode_poly(compress_poly(v, self.dv, self.q), self.dv);
+        let c1 = encode_polyvec(compress_polyvec(u_bold, P::DU, Q), P::DU);
+        let c2 = encode_poly(compress_poly(v, P::DV, Q), P::DV);
 
         c1.append(&c2)
     }
@@ -100,22 +126,55 @@ impl<const N: usize, const K: usize> PKE<N, K> {
     /// Kyber CPAPKE Decryption : secret key, ciphertext => message
     /// Algorithm 6 p. 10
     pub fn decrypt(&self, sk: &ByteArray, c: &ByteArray) -> ByteArray {
-        let offset = self.du * K * N / 8;
-        let (c1, c2) = c.split_at(offset);
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::check();
+        let (c1, c2) = c.split_at(P::CT_U_BYTES);
 
-        let u = decompress_polyvec(decode_to_polyvec::<N, K>(c1, self.du), self.du, self.q);
-        let v = decompress_poly(decode_to_poly(c2, self.dv), self.dv, self.q);
+        let u = decompress_polyvec(decode_to_polyvec::<N, K>(c1, P::DU), P::DU, Q);
+        let v = decompress_poly(decode_to_poly(c2, P::DV), P::DV, Q);
         let s = decode_to_polyvec(sk.clone(), 12);
 
         let u_hat = ntt_vec(&u);
         let x = ntt_product_vec(&s, &u_hat);
         let p = v.sub(&x);
 
-        encode_poly(compress_poly(p, 1, self.q), 1)
+        encode_poly(compress_poly(p, 1, Q), 1)
     }
 
-    pub const fn init(q: usize, eta: usize, du: usize, dv: usize) -> Self {
-        Self { q, eta, du, dv }
+    /// Encryption after validating the public key, message and random coins
+    pub fn encrypt_checked(
+        &self,
+        pk: &ByteArray,
+        m: &ByteArray,
+        r: ByteArray,
+    ) -> Result<ByteArray, String> {
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::run()?;
+        validate_public_key::<P>(pk)?;
+        if m.data.len() != 32 || r.data.len() != 32 {
+            return Err("message and random coins must be 32 bytes".to_string());
+        }
+        Ok(self.encrypt(pk, m, r))
+    }
+
+    /// Decryption after validating the lengths of the secret key and ciphertext
+    pub fn decrypt_checked(&self, sk: &ByteArray, c: &ByteArray) -> Result<ByteArray, String> {
+        #[cfg(feature = "fips-selftest")]
+        crate::selftest::run()?;
+        if sk.data.len() != P::PKE_SK_BYTES {
+            return Err(format!("secret key must be {} bytes", P::PKE_SK_BYTES));
+        }
+        validate_ciphertext::<P>(c)?;
+        Ok(self.decrypt(sk, c))
+    }
+
+    /// Instantiate the PKE for the parameter set `P`
+    pub const fn init() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::PARAMETERS_VALID;
+        Self {
+            params: PhantomData,
+        }
     }
 }
 
@@ -131,6 +190,31 @@ fn pke_keygen_cpapke_768() {
     pke.keygen();
 }
 
+#[test]
+fn pke_keygen_derand_is_deterministic() {
+    let pke = crate::kyber512pke();
+    let d = ByteArray::random(32);
+
+    assert_eq!(pke.keygen_derand(&d), pke.keygen_derand(&d));
+    assert_ne!(pke.keygen_derand(&d), pke.keygen());
+}
+
+#[test]
+fn checked_encrypt_then_decrypt() {
+    let pke = crate::kyber512pke();
+    let (sk, pk) = pke.keygen();
+    let m = ByteArray::random(32);
+
+    let enc = pke.encrypt_checked(&pk, &m, ByteArray::random(32)).unwrap();
+    assert_eq!(pke.decrypt_checked(&sk, &enc).unwrap(), m);
+
+    assert!(pke
+        .encrypt_checked(&pk, &ByteArray::random(31), ByteArray::random(32))
+        .is_err());
+    assert!(pke.decrypt_checked(&sk, &enc.truncate(10)).is_err());
+    assert!(pke.decrypt_checked(&pk, &enc).is_err());
+}
+
 #[test]
 fn encrypt_then_decrypt_cpapke_512() {
     let pke = crate::kyber512pke();
@@ -158,3 +242,17 @@ fn encrypt_then_decrypt_cpapke_768() {
 
     assert_eq!(m, dec);
 }
+
+#[test]
+fn encrypt_then_decrypt_cpapke_1024() {
+    let pke = crate::kyber1024pke();
+    let (sk, pk) = pke.keygen();
+
+    let m = ByteArray::random(32);
+    let r = ByteArray::random(32);
+
+    let enc = pke.encrypt(&pk, &m, r);
+    let dec = pke.decrypt(&sk, &enc);
+
+    assert_eq!(m, dec);
+}
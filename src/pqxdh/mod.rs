@@ -0,0 +1,659 @@
+//! PQXDH
+//!
+//! Key agreement of the Signal PQXDH specification with X25519, SHA-512 and the Kyber
+//! KEM: KEM prekeys (last-resort and one-time), their encodings with a key-type prefix
+//! byte, and the initiator and responder computation of the shared secret
+//!
+//! `SK = HKDF-SHA512(salt = 0^64, ikm = 0xFF^32 || DH1 || DH2 || DH3 || [DH4] || SS, info)`
+//!
+//! with `DH1 = DH(IK_A, SPK_B)`, `DH2 = DH(EK_A, IK_B)`, `DH3 = DH(EK_A, SPK_B)`,
+//! `DH4 = DH(EK_A, OPK_B)` and `SS` the KEM shared secret of the KEM prekey.
+//!
+//! Prekey signatures (XEdDSA with the identity key) are left to the caller: the
+//! `signed_data` of a prekey is the message to sign, and bundles must be verified before
+//! use. The KEM is round 2 Kyber and does not interoperate with libsignal.
+
+use crate::hybrid::{DiffieHellman, X25519};
+use crate::kem::KEM;
+use crate::params::ParameterSet;
+use crate::structures::ByteArray;
+use crate::validate::{validate_ciphertext, validate_public_key, validate_secret_key};
+
+use hkdf::Hkdf;
+use sha2::Sha512;
+
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+
+/// Key-type prefix of the X25519 public keys
+pub const CURVE25519_KEY_TYPE: u8 = 0x05;
+/// Key-type prefix of the Kyber768 public keys
+///
+/// libsignal assigns 0x07 and 0x08 to its Kyber keys, which do not interoperate with these:
+/// the KEM prefixes are bytes it does not assign.
+pub const KYBER768_KEY_TYPE: u8 = 0xf7;
+/// Key-type prefix of the Kyber1024 public keys
+pub const KYBER1024_KEY_TYPE: u8 = 0xf8;
+
+/// Size of the shared secret
+pub const SECRET_BYTES: usize = 32;
+
+/// Size of an encoded X25519 public key
+const EC_BYTES: usize = 33;
+
+/// Key-type prefix of the KEM public keys of a parameter set
+fn kem_key_type<P: ParameterSet>() -> Result<u8, String> {
+    match P::K {
+        3 => Ok(KYBER768_KEY_TYPE),
+        4 => Ok(KYBER1024_KEY_TYPE),
+        _ => Err("PQXDH supports Kyber768 and Kyber1024 prekeys only".to_string()),
+    }
+}
+
+/// `EncodeEC`: key-type prefix and X25519 public key
+pub fn encode_ec(pk: &ByteArray) -> Vec<u8> {
+    [&[CURVE25519_KEY_TYPE][..], &pk.data].concat()
+}
+
+/// Decode an encoded X25519 public key
+pub fn decode_ec(bytes: &[u8]) -> Result<ByteArray, String> {
+    match bytes.split_first() {
+        Some((&CURVE25519_KEY_TYPE, pk)) if pk.len() == X25519::PK_BYTES => {
+            Ok(ByteArray::from_bytes(pk))
+        }
+        Some((&CURVE25519_KEY_TYPE, _)) => Err("X25519 public key must be 32 bytes".to_string()),
+        _ => Err("invalid X25519 key type".to_string()),
+    }
+}
+
+/// `EncodeKEM`: key-type prefix and KEM public key
+pub fn encode_kem<P: ParameterSet>(pk: &ByteArray) -> Result<Vec<u8>, String> {
+    Ok([&[kem_key_type::<P>()?][..], &pk.data].concat())
+}
+
+/// Decode and validate an encoded KEM public key
+pub fn decode_kem<P: ParameterSet>(bytes: &[u8]) -> Result<ByteArray, String> {
+    match bytes.split_first() {
+        Some((t, pk)) if *t == kem_key_type::<P>()? => {
+            let pk = ByteArray::from_bytes(pk);
+            validate_public_key::<P>(&pk)?;
+            Ok(pk)
+        }
+        _ => Err("invalid KEM key type".to_string()),
+    }
+}
+
+/// Read a big-endian prekey identifier
+fn read_id(bytes: &[u8]) -> Result<(u32, &[u8]), String> {
+    if bytes.len() < 4 {
+        return Err("truncated prekey identifier".to_string());
+    }
+    let (id, rest) = bytes.split_at(4);
+    // The slice has 4 bytes
+    Ok((u32::from_be_bytes(<[u8; 4]>::try_from(id).unwrap()), rest))
+}
+
+/// X25519 identity key pair
+#[derive(Clone)]
+pub struct IdentityKeyPair {
+    sk: ByteArray,
+    /// Public identity key
+    pub pk: ByteArray,
+}
+
+impl IdentityKeyPair {
+    /// Random identity key pair
+    pub fn generate() -> Self {
+        let sk = X25519::secret_key(&ByteArray::random(X25519::SK_BYTES));
+        let pk = X25519::public_key(&sk);
+        Self { sk, pk }
+    }
+}
+
+/// X25519 prekey: signed prekey or one-time prekey
+#[derive(Clone)]
+pub struct EcPrekey {
+    /// Prekey identifier
+    pub id: u32,
+    sk: ByteArray,
+    /// Public prekey
+    pub pk: ByteArray,
+}
+
+impl EcPrekey {
+    /// Random prekey
+    pub fn generate(id: u32) -> Self {
+        let sk = X25519::secret_key(&ByteArray::random(X25519::SK_BYTES));
+        let pk = X25519::public_key(&sk);
+        Self { id, sk, pk }
+    }
+
+    /// Message signed by the identity key for a signed prekey: `EncodeEC(pk)`
+    pub fn signed_data(&self) -> Vec<u8> {
+        encode_ec(&self.pk)
+    }
+}
+
+/// Kind of a KEM prekey
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KemPrekeyKind {
+    /// One-time prekey, deleted after use
+    OneTime,
+    /// Last-resort prekey, used when no one-time prekey is left
+    LastResort,
+}
+
+/// KEM prekey of the responder, with its secret key
+#[derive(Clone, PartialEq, Eq)]
+pub struct KemPrekey<P: ParameterSet, const N: usize, const K: usize> {
+    /// Prekey identifier
+    pub id: u32,
+    /// One-time or last-resort
+    pub kind: KemPrekeyKind,
+    sk: ByteArray,
+    /// Public prekey
+    pub pk: ByteArray,
+    params: PhantomData<P>,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> KemPrekey<P, N, K> {
+    /// Random prekey
+    pub fn generate(kem: &KEM<P, N, K>, id: u32, kind: KemPrekeyKind) -> Result<Self, String> {
+        kem_key_type::<P>()?;
+        let (sk, pk) = kem.keygen();
+        Ok(Self {
+            id,
+            kind,
+            sk,
+            pk,
+            params: PhantomData,
+        })
+    }
+
+    /// Message signed by the identity key: `EncodeKEM(pk)`
+    pub fn signed_data(&self) -> Vec<u8> {
+        // The parameter set is checked at creation
+        encode_kem::<P>(&self.pk).unwrap()
+    }
+
+    /// Public prekey with the identity key signature of `signed_data`
+    pub fn public(&self, signature: &[u8]) -> KemPrekeyPublic<P, N, K> {
+        KemPrekeyPublic {
+            id: self.id,
+            pk: self.pk.clone(),
+            signature: signature.to_vec(),
+            params: PhantomData,
+        }
+    }
+
+    /// Storage encoding: `id || kind || EncodeKEM(pk) || sk`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let kind = match self.kind {
+            KemPrekeyKind::OneTime => 0,
+            KemPrekeyKind::LastResort => 1,
+        };
+        [
+            &self.id.to_be_bytes()[..],
+            &[kind],
+            &self.signed_data(),
+            &self.sk.data,
+        ]
+        .concat()
+    }
+
+    /// Decode a stored prekey, the secret key must match the public key
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let (id, rest) = read_id(bytes)?;
+        let (kind, rest) = match rest.split_first() {
+            Some((0, rest)) => (KemPrekeyKind::OneTime, rest),
+            Some((1, rest)) => (KemPrekeyKind::LastResort, rest),
+            _ => return Err("invalid KEM prekey kind".to_string()),
+        };
+        if rest.len() != 1 + P::PK_BYTES + P::SK_BYTES {
+            return Err("invalid KEM prekey size".to_string());
+        }
+        let (pk, sk) = rest.split_at(1 + P::PK_BYTES);
+        let pk = decode_kem::<P>(pk)?;
+        let sk = ByteArray::from_bytes(sk);
+        validate_secret_key::<P>(&sk)?;
+        // The public key is embedded in the secret key
+        let (_, embedded) = sk.split_at(P::PKE_SK_BYTES);
+        if embedded.truncate(P::PK_BYTES) != pk {
+            return Err("KEM prekey secret and public keys do not match".to_string());
+        }
+
+        Ok(Self {
+            id,
+            kind,
+            sk,
+            pk,
+            params: PhantomData,
+        })
+    }
+}
+
+/// Public KEM prekey of a bundle, with its signature
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KemPrekeyPublic<P: ParameterSet, const N: usize, const K: usize> {
+    /// Prekey identifier
+    pub id: u32,
+    /// Public prekey
+    pub pk: ByteArray,
+    /// Identity key signature of `signed_data`
+    pub signature: Vec<u8>,
+    params: PhantomData<P>,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> KemPrekeyPublic<P, N, K> {
+    /// Message signed by the identity key: `EncodeKEM(pk)`
+    pub fn signed_data(&self) -> Vec<u8> {
+        // Decoded prekeys have a supported parameter set
+        encode_kem::<P>(&self.pk).unwrap()
+    }
+
+    /// Encoding: `id || EncodeKEM(pk) || signature`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [
+            &self.id.to_be_bytes()[..],
+            &self.signed_data(),
+            &self.signature,
+        ]
+        .concat()
+    }
+
+    /// Decode and validate a public prekey
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let (id, rest) = read_id(bytes)?;
+        if rest.len() < 1 + P::PK_BYTES {
+            return Err("truncated KEM prekey".to_string());
+        }
+        let (pk, signature) = rest.split_at(1 + P::PK_BYTES);
+
+        Ok(Self {
+            id,
+            pk: decode_kem::<P>(pk)?,
+            signature: signature.to_vec(),
+            params: PhantomData,
+        })
+    }
+}
+
+/// Public X25519 prekey of a bundle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EcPrekeyPublic {
+    /// Prekey identifier
+    pub id: u32,
+    /// Public prekey
+    pub pk: ByteArray,
+}
+
+/// Prekey bundle of the responder, whose signatures have been verified
+#[derive(Clone, Debug)]
+pub struct PrekeyBundle<P: ParameterSet, const N: usize, const K: usize> {
+    /// Identity key `IK_B`
+    pub identity_key: ByteArray,
+    /// Signed prekey `SPK_B`
+    pub signed_prekey: EcPrekeyPublic,
+    /// One-time KEM prekey `PQOPK_B`, or the last-resort prekey `PQSPK_B`
+    pub kem_prekey: KemPrekeyPublic<P, N, K>,
+    /// One-time prekey `OPK_B`, if any is left
+    pub one_time_prekey: Option<EcPrekeyPublic>,
+}
+
+/// Initial message of the initiator, without the encrypted payload
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InitialMessage<P: ParameterSet, const N: usize, const K: usize> {
+    /// Identity key `IK_A`
+    pub identity_key: ByteArray,
+    /// Ephemeral key `EK_A`
+    pub ephemeral_key: ByteArray,
+    /// Identifier of the signed prekey
+    pub signed_prekey_id: u32,
+    /// Identifier of the KEM prekey
+    pub kem_prekey_id: u32,
+    /// Identifier of the one-time prekey, if used
+    pub one_time_prekey_id: Option<u32>,
+    /// KEM ciphertext `CT`
+    pub ciphertext: ByteArray,
+    params: PhantomData<P>,
+}
+
+impl<P: ParameterSet, const N: usize, const K: usize> InitialMessage<P, N, K> {
+    /// Encoding: `EncodeEC(IK_A) || EncodeEC(EK_A) || spk id || kem id || opk flag [|| opk id] || CT`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = encode_ec(&self.identity_key);
+        out.extend(encode_ec(&self.ephemeral_key));
+        out.extend_from_slice(&self.signed_prekey_id.to_be_bytes());
+        out.extend_from_slice(&self.kem_prekey_id.to_be_bytes());
+        match self.one_time_prekey_id {
+            Some(id) => {
+                out.push(1);
+                out.extend_from_slice(&id.to_be_bytes());
+            }
+            None => out.push(0),
+        }
+        out.extend_from_slice(&self.ciphertext.data);
+        out
+    }
+
+    /// Decode a message
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 2 * EC_BYTES {
+            return Err("truncated initial message".to_string());
+        }
+        let identity_key = decode_ec(&bytes[..EC_BYTES])?;
+        let ephemeral_key = decode_ec(&bytes[EC_BYTES..2 * EC_BYTES])?;
+        let (signed_prekey_id, rest) = read_id(&bytes[2 * EC_BYTES..])?;
+        let (kem_prekey_id, rest) = read_id(rest)?;
+        let (one_time_prekey_id, rest) = match rest.split_first() {
+            Some((0, rest)) => (None, rest),
+            Some((1, rest)) => {
+                let (id, rest) = read_id(rest)?;
+                (Some(id), rest)
+            }
+            _ => return Err("invalid one-time prekey flag".to_string()),
+        };
+        let ciphertext = ByteArray::from_bytes(rest);
+        validate_ciphertext::<P>(&ciphertext)?;
+
+        Ok(Self {
+            identity_key,
+            ephemeral_key,
+            signed_prekey_id,
+            kem_prekey_id,
+            one_time_prekey_id,
+            ciphertext,
+            params: PhantomData,
+        })
+    }
+}
+
+/// Result of the key agreement
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SharedSecret {
+    /// Secret key `SK`
+    pub key: ByteArray,
+    /// Associated data `EncodeEC(IK_A) || EncodeEC(IK_B)`
+    pub associated_data: Vec<u8>,
+}
+
+/// `KDF(KM)` of the specification with the application info string
+fn kdf(info: &[u8], dh: &[&ByteArray], ss: &ByteArray) -> ByteArray {
+    let mut ikm = vec![0xff; 32];
+    for output in dh {
+        ikm.extend_from_slice(&output.data);
+    }
+    ikm.extend_from_slice(&ss.data);
+
+    let mut key = [0; SECRET_BYTES];
+    // The output length is valid for HKDF-SHA512
+    Hkdf::<Sha512>::new(Some(&[0; 64]), &ikm)
+        .expand(info, &mut key)
+        .unwrap();
+    ByteArray::from_bytes(&key)
+}
+
+fn associated_data(ik_a: &ByteArray, ik_b: &ByteArray) -> Vec<u8> {
+    [encode_ec(ik_a), encode_ec(ik_b)].concat()
+}
+
+/// Initiator side: key agreement with a verified prekey bundle
+pub fn initiate<P: ParameterSet, const N: usize, const K: usize>(
+    kem: &KEM<P, N, K>,
+    info: &[u8],
+    identity: &IdentityKeyPair,
+    bundle: &PrekeyBundle<P, N, K>,
+) -> Result<(InitialMessage<P, N, K>, SharedSecret), String> {
+    initiate_derand(
+        kem,
+        info,
+        identity,
+        bundle,
+        &ByteArray::random(X25519::SK_BYTES),
+        &ByteArray::random(32),
+    )
+}
+
+/// Deterministic initiator side with the ephemeral secret key and the 32 bytes of KEM
+/// encapsulation randomness
+pub fn initiate_derand<P: ParameterSet, const N: usize, const K: usize>(
+    kem: &KEM<P, N, K>,
+    info: &[u8],
+    identity: &IdentityKeyPair,
+    bundle: &PrekeyBundle<P, N, K>,
+    ephemeral: &ByteArray,
+    m: &ByteArray,
+) -> Result<(InitialMessage<P, N, K>, SharedSecret), String> {
+    if ephemeral.data.len() != X25519::SK_BYTES || m.data.len() != 32 {
+        return Err("invalid initiator randomness".to_string());
+    }
+    validate_public_key::<P>(&bundle.kem_prekey.pk)?;
+    let ek = X25519::secret_key(ephemeral);
+
+    let dh1 = X25519::dh(&identity.sk, &bundle.signed_prekey.pk)?;
+    let dh2 = X25519::dh(&ek, &bundle.identity_key)?;
+    let dh3 = X25519::dh(&ek, &bundle.signed_prekey.pk)?;
+    let dh4 = match &bundle.one_time_prekey {
+        Some(opk) => Some(X25519::dh(&ek, &opk.pk)?),
+        None => None,
+    };
+    let (ciphertext, ss) = kem.encaps_derand(&bundle.kem_prekey.pk, m);
+
+    let mut dh = vec![&dh1, &dh2, &dh3];
+    dh.extend(dh4.as_ref());
+    let secret = SharedSecret {
+        key: kdf(info, &dh, &ss),
+        associated_data: associated_data(&identity.pk, &bundle.identity_key),
+    };
+    let message = InitialMessage {
+        identity_key: identity.pk.clone(),
+        ephemeral_key: X25519::public_key(&ek),
+        signed_prekey_id: bundle.signed_prekey.id,
+        kem_prekey_id: bundle.kem_prekey.id,
+        one_time_prekey_id: bundle.one_time_prekey.as_ref().map(|opk| opk.id),
+        ciphertext,
+        params: PhantomData,
+    };
+
+    Ok((message, secret))
+}
+
+/// Responder side: key agreement with the prekeys named in the initial message
+///
+/// The caller deletes the one-time prekeys after use.
+pub fn respond<P: ParameterSet, const N: usize, const K: usize>(
+    kem: &KEM<P, N, K>,
+    info: &[u8],
+    identity: &IdentityKeyPair,
+    signed_prekey: &EcPrekey,
+    kem_prekey: &KemPrekey<P, N, K>,
+    one_time_prekey: Option<&EcPrekey>,
+    message: &InitialMessage<P, N, K>,
+) -> Result<SharedSecret, String> {
+    if message.signed_prekey_id != signed_prekey.id || message.kem_prekey_id != kem_prekey.id {
+        return Err("initial message uses other prekeys".to_string());
+    }
+    if message.one_time_prekey_id != one_time_prekey.map(|opk| opk.id) {
+        return Err("initial message uses another one-time prekey".to_string());
+    }
+
+    let dh1 = X25519::dh(&signed_prekey.sk, &message.identity_key)?;
+    let dh2 = X25519::dh(&identity.sk, &message.ephemeral_key)?;
+    let dh3 = X25519::dh(&signed_prekey.sk, &message.ephemeral_key)?;
+    let dh4 = match one_time_prekey {
+        Some(opk) => Some(X25519::dh(&opk.sk, &message.ephemeral_key)?),
+        None => None,
+    };
+    let ss = kem.decaps_checked(&message.ciphertext, &kem_prekey.sk)?;
+
+    let mut dh = vec![&dh1, &dh2, &dh3];
+    dh.extend(dh4.as_ref());
+    Ok(SharedSecret {
+        key: kdf(info, &dh, &ss),
+        associated_data: associated_data(&message.identity_key, &identity.pk),
+    })
+}
+
+#[cfg(test)]
+const INFO: &[u8] = b"kybe-rs_CURVE25519_SHA-512_CRYSTALS-KYBER-1024";
+
+/// Two parties in separate threads, the bundle and initial message going through
+/// channels as bytes
+#[cfg(test)]
+fn exchange<P: ParameterSet + Clone + Send + 'static, const N: usize, const K: usize>(
+    kem: KEM<P, N, K>,
+    one_time: bool,
+    kind: KemPrekeyKind,
+) where
+    KEM<P, N, K>: Clone + Send + 'static,
+{
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    let (to_alice, from_bob) = channel::<(Vec<u8>, Vec<u8>, Option<Vec<u8>>, Vec<u8>)>();
+    let (to_bob, from_alice) = channel::<Vec<u8>>();
+
+    let bob_kem = kem.clone();
+    let bob = thread::spawn(move || {
+        let identity = IdentityKeyPair::generate();
+        let spk = EcPrekey::generate(1);
+        let opk = EcPrekey::generate(7);
+        let pqpk = KemPrekey::generate(&bob_kem, 42, kind).unwrap();
+        // Stored and reloaded prekey, published with a placeholder signature
+        let pqpk = KemPrekey::<P, N, K>::from_bytes(&pqpk.to_bytes()).unwrap();
+        to_alice
+            .send((
+                encode_ec(&identity.pk),
+                encode_ec(&spk.pk),
+                if one_time {
+                    Some(encode_ec(&opk.pk))
+                } else {
+                    None
+                },
+                pqpk.public(&[0x5a; 64]).to_bytes(),
+            ))
+            .unwrap();
+
+        let message = InitialMessage::<P, N, K>::from_bytes(&from_alice.recv().unwrap()).unwrap();
+        let opk = message.one_time_prekey_id.map(|_| &opk);
+        respond(&bob_kem, INFO, &identity, &spk, &pqpk, opk, &message).unwrap()
+    });
+
+    let identity = IdentityKeyPair::generate();
+    let (ik, spk, opk, pqpk) = from_bob.recv().unwrap();
+    let kem_prekey = KemPrekeyPublic::<P, N, K>::from_bytes(&pqpk).unwrap();
+    assert_eq!(kem_prekey.signature, [0x5a; 64]);
+    let bundle = PrekeyBundle {
+        identity_key: decode_ec(&ik).unwrap(),
+        signed_prekey: EcPrekeyPublic {
+            id: 1,
+            pk: decode_ec(&spk).unwrap(),
+        },
+        kem_prekey,
+        one_time_prekey: opk.map(|opk| EcPrekeyPublic {
+            id: 7,
+            pk: decode_ec(&opk).unwrap(),
+        }),
+    };
+    let (message, alice) = initiate(&kem, INFO, &identity, &bundle).unwrap();
+    to_bob.send(message.to_bytes()).unwrap();
+
+    let bob = bob.join().unwrap();
+    assert_eq!(alice, bob);
+    assert_eq!(alice.key.data.len(), SECRET_BYTES);
+    assert_eq!(
+        alice.associated_data[..EC_BYTES],
+        encode_ec(&identity.pk)[..]
+    );
+}
+
+#[test]
+fn pqxdh_exchange() {
+    exchange(crate::kyber768kem(), true, KemPrekeyKind::OneTime);
+    exchange(crate::kyber1024kem(), true, KemPrekeyKind::OneTime);
+    exchange(crate::kyber1024kem(), false, KemPrekeyKind::LastResort);
+}
+
+#[test]
+fn pqxdh_encodings() {
+    use crate::params::{Kyber1024, Kyber512, Kyber768};
+
+    let kem = crate::kyber1024kem();
+    let prekey = KemPrekey::generate(&kem, 3, KemPrekeyKind::LastResort).unwrap();
+    let signed = prekey.signed_data();
+    assert_eq!(signed[0], KYBER1024_KEY_TYPE);
+    assert_eq!(signed[1..], prekey.pk.data[..]);
+    assert_eq!(decode_kem::<Kyber1024>(&signed).unwrap(), prekey.pk);
+    assert!(decode_kem::<Kyber768>(&signed).is_err());
+    assert!(encode_kem::<Kyber512>(&prekey.pk).is_err());
+    assert!(KemPrekey::generate(&crate::kyber512kem(), 3, KemPrekeyKind::OneTime).is_err());
+
+    // Stored prekeys
+    let bytes = prekey.to_bytes();
+    assert_eq!(bytes[..5], [0, 0, 0, 3, 1]);
+    assert!(KemPrekey::<Kyber1024, 256, 4>::from_bytes(&bytes).unwrap() == prekey);
+    let other = KemPrekey::generate(&kem, 3, KemPrekeyKind::LastResort).unwrap();
+    let mixed = [&bytes[..6 + Kyber1024::PK_BYTES], &other.sk.data].concat();
+    assert!(KemPrekey::<Kyber1024, 256, 4>::from_bytes(&mixed).is_err());
+    let mut altered = bytes.clone();
+    altered[4] = 2;
+    assert!(KemPrekey::<Kyber1024, 256, 4>::from_bytes(&altered).is_err());
+    assert!(KemPrekey::<Kyber1024, 256, 4>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+    // Public prekeys
+    let public = prekey.public(b"signature");
+    let bytes = public.to_bytes();
+    assert_eq!(
+        KemPrekeyPublic::<Kyber1024, 256, 4>::from_bytes(&bytes).unwrap(),
+        public
+    );
+    assert!(KemPrekeyPublic::<Kyber1024, 256, 4>::from_bytes(&bytes[..100]).is_err());
+
+    // X25519 keys
+    let pk = IdentityKeyPair::generate().pk;
+    assert_eq!(decode_ec(&encode_ec(&pk)).unwrap(), pk);
+    assert!(decode_ec(&pk.data).is_err());
+    assert!(decode_ec(&[5; 32]).is_err());
+}
+
+#[test]
+fn pqxdh_test_vector() {
+    use crate::formats::hex;
+
+    // KDF of the specification, computed with another HMAC-SHA512 implementation:
+    // HKDF-SHA512(salt = 0^64, ikm = 0xFF^32 || 01^96 || 02^32, INFO)
+    let dh = ByteArray::from_bytes(&[1; 32]);
+    let ss = ByteArray::from_bytes(&[2; 32]);
+    assert_eq!(
+        hex::encode(&kdf(INFO, &[&dh, &dh, &dh], &ss).data),
+        "f2f67df7f9f52c223618ab61ea2c865f6542aa1814215866e09c6d3dc04a7b67"
+    );
+
+    // Regression vector from fixed keys
+    let key = |b: u8| ByteArray::from_bytes(&[b; 32]);
+    let kem = crate::kyber1024kem();
+    let identity = IdentityKeyPair {
+        sk: key(1),
+        pk: X25519::public_key(&key(1)),
+    };
+    let (_, pqpk) = kem.keygen_derand(&key(4), &key(5));
+    let bundle = PrekeyBundle {
+        identity_key: X25519::public_key(&key(2)),
+        signed_prekey: EcPrekeyPublic {
+            id: 1,
+            pk: X25519::public_key(&key(3)),
+        },
+        kem_prekey: KemPrekeyPublic {
+            id: 2,
+            pk: pqpk,
+            signature: vec![],
+            params: PhantomData,
+        },
+        one_time_prekey: None,
+    };
+    let (_, secret) = initiate_derand(&kem, INFO, &identity, &bundle, &key(6), &key(7)).unwrap();
+    assert_eq!(
+        hex::encode(&secret.key.data),
+        "38cc5667fe0825dad3b5eba0e7dce7e2ff69573023e1e69b28e9331a9056a544"
+    );
+}
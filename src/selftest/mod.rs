@@ -0,0 +1,302 @@
+//! Self-tests
+//!
+//! Power-on self-tests and pairwise consistency test, as required by FIPS 140-3.
+//!
+//! The known-answer tests of the hash primitives, `PKE` and `KEM` run once, at the
+//! first use of the module. The pairwise consistency test follows every key generation:
+//! `KEM::keygen_checked`, `KEM::expand_seed`, `SecretKey::from_seed` and
+//! `DecapsulationKey::generate` return its error, `KEM::keygen`, `KEM::keygen_derand`
+//! and `KEM::keygen_with_seed` panic. If a test fails, the module enters an error
+//! state: the checked APIs return an error and the other operations panic.
+//!
+//! This crate implements the round 2 specification, so there are no published
+//! vectors for `PKE` and `KEM`: their known answers were generated by this
+//! implementation and only detect regressions and faults.
+
+use crate::formats::hex;
+use crate::functions::hash::{sha3_256, sha3_512, shake_128, shake_256};
+use crate::params::ParameterSet;
+use crate::structures::ByteArray;
+use crate::{kyber768kem, kyber768pke, KEM};
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, Once};
+
+/// The self-tests have not run yet
+const UNTESTED: u8 = 0;
+/// The self-tests passed
+const OPERATIONAL: u8 = 1;
+/// A self-test failed, the module is disabled
+const ERROR: u8 = 2;
+
+thread_local! {
+    /// Set while the self-tests run on this thread, so that they can use the module
+    static RUNNING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// State of the module
+struct Module {
+    once: Once,
+    state: AtomicU8,
+    reason: Mutex<Option<String>>,
+}
+
+impl Module {
+    const fn new() -> Self {
+        Self {
+            once: Once::new(),
+            state: AtomicU8::new(UNTESTED),
+            reason: Mutex::new(None),
+        }
+    }
+
+    /// Run `tests` on first use and report the state of the module
+    fn run(&self, tests: fn() -> Result<(), String>) -> Result<(), String> {
+        if RUNNING.with(Cell::get) {
+            return Ok(());
+        }
+
+        self.once.call_once(|| {
+            RUNNING.with(|r| r.set(true));
+            let result = tests();
+            RUNNING.with(|r| r.set(false));
+
+            match result {
+                Ok(()) => {
+                    let _ = self.state.compare_exchange(
+                        UNTESTED,
+                        OPERATIONAL,
+                        Ordering::SeqCst,
+                        Ordering::SeqCst,
+                    );
+                }
+                Err(e) => self.fail(e),
+            }
+        });
+
+        self.status()
+    }
+
+    /// Enter the error state, keeping the first reason
+    fn fail(&self, reason: String) {
+        let mut current = self.reason.lock().unwrap_or_else(|e| e.into_inner());
+        if current.is_none() {
+            *current = Some(reason);
+        }
+        self.state.store(ERROR, Ordering::SeqCst);
+    }
+
+    /// Pairwise consistency test, entering the error state on failure
+    fn pairwise_consistency<P: ParameterSet, const N: usize, const K: usize>(
+        &self,
+        kem: &KEM<P, N, K>,
+        sk: &ByteArray,
+        pk: &ByteArray,
+    ) -> Result<(), String> {
+        // The known-answer tests check the key pairs they generate
+        if RUNNING.with(Cell::get) {
+            return Ok(());
+        }
+
+        let (c, ss) = kem.encaps(pk);
+        if kem.decaps(&c, sk) != ss {
+            self.fail("pairwise consistency test failed".to_string());
+        }
+        self.status()
+    }
+
+    fn status(&self) -> Result<(), String> {
+        match self.state.load(Ordering::SeqCst) {
+            OPERATIONAL => Ok(()),
+            ERROR => {
+                let reason = self.reason.lock().unwrap_or_else(|e| e.into_inner());
+                Err(format!(
+                    "module disabled: {}",
+                    reason.as_deref().unwrap_or("self-test failure")
+                ))
+            }
+            _ => Err("self-tests have not run".to_string()),
+        }
+    }
+}
+
+static MODULE: Module = Module::new();
+
+/// Run the self-tests if they have not run yet and report the state of the module
+pub fn run() -> Result<(), String> {
+    MODULE.run(known_answer_tests)
+}
+
+/// Panic unless the module is operational
+pub(crate) fn check() {
+    if let Err(e) = run() {
+        panic!("kybe-rs: {}", e);
+    }
+}
+
+/// Pairwise consistency test of a freshly generated KEM key pair
+///
+/// An encapsulation to `pk` must decapsulate with `sk` to the same shared secret,
+/// otherwise the module enters the error state.
+pub(crate) fn pairwise_consistency<P: ParameterSet, const N: usize, const K: usize>(
+    kem: &KEM<P, N, K>,
+    sk: &ByteArray,
+    pk: &ByteArray,
+) -> Result<(), String> {
+    MODULE.pairwise_consistency(kem, sk, pk)
+}
+
+/// Pairwise consistency test, panicking on failure
+pub(crate) fn check_pairwise<P: ParameterSet, const N: usize, const K: usize>(
+    kem: &KEM<P, N, K>,
+    sk: &ByteArray,
+    pk: &ByteArray,
+) {
+    if let Err(e) = pairwise_consistency(kem, sk, pk) {
+        panic!("kybe-rs: {}", e);
+    }
+}
+
+/// Compare `data` with the expected SHA3-256 digest
+fn expect_digest(name: &str, data: &ByteArray, expected: &str) -> Result<(), String> {
+    expect(name, &sha3_256(&data.data), expected)
+}
+
+/// Compare `data` with the expected value
+fn expect(name: &str, data: &[u8], expected: &str) -> Result<(), String> {
+    if hex::decode(expected)? == data {
+        Ok(())
+    } else {
+        Err(format!("{} known-answer test failed", name))
+    }
+}
+
+/// 32 consecutive bytes starting at `start`
+fn counter(start: u8) -> ByteArray {
+    ByteArray::from_bytes(&(start..start + 32).collect::<Vec<u8>>())
+}
+
+/// Known answers of the hash primitives, from FIPS 202
+fn hash_tests() -> Result<(), String> {
+    expect(
+        "SHA3-256",
+        &sha3_256(b"abc"),
+        "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+    )?;
+    expect(
+        "SHA3-512",
+        &sha3_512(b"abc"),
+        "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e\
+         10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0",
+    )?;
+    expect(
+        "SHAKE128",
+        &shake_128(b"", 32),
+        "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26",
+    )?;
+    expect(
+        "SHAKE256",
+        &shake_256(b"", 32),
+        "46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762f",
+    )
+}
+
+/// Known answers of Kyber768 PKE, as SHA3-256 digests of the outputs
+fn pke_tests() -> Result<(), String> {
+    let pke = kyber768pke();
+    let m = counter(64);
+
+    let (sk, pk) = pke.keygen_derand(&counter(0));
+    expect_digest("PKE public key", &pk, PKE_PK)?;
+    expect_digest("PKE secret key", &sk, PKE_SK)?;
+
+    let c = pke.encrypt(&pk, &m, counter(32));
+    expect_digest("PKE ciphertext", &c, PKE_CT)?;
+
+    if pke.decrypt(&sk, &c) != m {
+        return Err("PKE decryption known-answer test failed".to_string());
+    }
+    Ok(())
+}
+
+/// Known answers of Kyber768 KEM, including the implicit rejection
+fn kem_tests() -> Result<(), String> {
+    let kem = kyber768kem();
+
+    let (sk, pk) = kem.keygen_derand(&counter(0), &counter(32));
+    expect_digest("KEM public key", &pk, KEM_PK)?;
+    expect_digest("KEM secret key", &sk, KEM_SK)?;
+
+    let (c, ss) = kem.encaps_derand(&pk, &counter(64));
+    expect_digest("KEM ciphertext", &c, KEM_CT)?;
+    expect("KEM shared secret", &ss.data, KEM_SS)?;
+    expect("KEM decapsulation", &kem.decaps(&c, &sk).data, KEM_SS)?;
+
+    let mut invalid = c;
+    invalid.data[0] ^= 1;
+    expect("KEM rejection", &kem.decaps(&invalid, &sk).data, KEM_REJECT)
+}
+
+fn known_answer_tests() -> Result<(), String> {
+    hash_tests()?;
+    pke_tests()?;
+    kem_tests()
+}
+
+// SHA3-256 of the Kyber768 outputs, except for the shared secrets
+const PKE_PK: &str = "214dd14086d4b5c5e28b74c798debbe2ce03ca383dd01be0d8e2cb27f42fe22d";
+const PKE_SK: &str = "cbfb423b431807c79793debd2c847bf730ef1a303a383c3a847ffdd1f626e2b4";
+const PKE_CT: &str = "06fb6368453ad66c464f3a1915fbd46d337b28f037f88599698cc5020143ad20";
+const KEM_PK: &str = "214dd14086d4b5c5e28b74c798debbe2ce03ca383dd01be0d8e2cb27f42fe22d";
+const KEM_SK: &str = "cfc2b9784acc3f261edd3ea5fc2cc6d9a2524b642968f0661c87ab6ec959b33d";
+const KEM_CT: &str = "102b7dfe8f8d137c1a4a0dcf1cdac1d40b5df9033ef9a42d63e17c58e61a912d";
+const KEM_SS: &str = "5a2c3bc397b4a32e85e54d0651cb1dfdd6ba4cdb7c71c2e5819491555b6bfc16";
+const KEM_REJECT: &str = "1bd20945c4f08ae64448f61c2671c246a002373894670b6f7bb89d24bc3759a2";
+
+#[test]
+fn selftest_passes() {
+    assert_eq!(known_answer_tests(), Ok(()));
+    assert_eq!(run(), Ok(()));
+}
+
+#[test]
+fn selftest_error_state() {
+    let module = Module::new();
+    assert!(module.status().is_err());
+
+    assert!(module
+        .run(|| Err("injected failure".to_string()))
+        .unwrap_err()
+        .contains("injected failure"));
+    // The module stays disabled
+    assert!(module.run(|| Ok(())).is_err());
+}
+
+#[test]
+fn selftest_pairwise_failure() {
+    let kem = kyber768kem();
+    let module = Module::new();
+    assert_eq!(module.run(|| Ok(())), Ok(()));
+
+    let (sk, pk) = kem.keygen();
+    assert_eq!(module.pairwise_consistency(&kem, &sk, &pk), Ok(()));
+
+    // Secret key of another key pair
+    let (other, _) = kem.keygen();
+    assert!(module
+        .pairwise_consistency(&kem, &other, &pk)
+        .unwrap_err()
+        .contains("pairwise consistency"));
+    // The module stays disabled
+    assert!(module.status().is_err());
+    assert!(module.pairwise_consistency(&kem, &sk, &pk).is_err());
+}
+
+#[test]
+fn selftest_checked_keygen() {
+    let kem = kyber768kem();
+    let (sk, pk) = kem.keygen_checked().unwrap();
+    let (c, ss) = kem.encaps_checked(&pk).unwrap();
+    assert_eq!(kem.decaps_checked(&c, &sk), Ok(ss));
+}
@@ -0,0 +1,335 @@
+//! SSH hybrid key exchange
+//!
+//! The `kyber768x25519-sha256@kybe-rs` key exchange method, with the messages of the
+//! `mlkem768x25519-sha256` method of OpenSSH:
+//! - the client sends `Q_C = pk || x25519` in `SSH_MSG_KEX_HYBRID_INIT`
+//! - the server answers `Q_S = ct || x25519` in `SSH_MSG_KEX_HYBRID_REPLY`, with its host
+//!   key `K_S` and its signature of the exchange hash
+//! - the shared secret is `K = SHA-256(K_PQ || K_CL)`, encoded as a `string`
+//!
+//! The KEM is the round 2 Kyber768 of this crate: the encodings are those of the draft,
+//! but the KEM values do not interoperate with ML-KEM peers, so the method has a private
+//! name (RFC 4250 section 4.6.2) instead of the OpenSSH one.
+
+use crate::hybrid::{DiffieHellman, X25519};
+use crate::kem::SEED_BYTES;
+use crate::kyber768kem;
+use crate::params::{Kyber768, ParameterSet};
+use crate::structures::ByteArray;
+use crate::validate::{validate_ciphertext, validate_public_key};
+
+use sha2::{Digest, Sha256};
+
+/// Name of the key exchange method
+pub const KEX_NAME: &str = "kyber768x25519-sha256@kybe-rs";
+
+/// Message number of `SSH_MSG_KEX_HYBRID_INIT`
+pub const SSH_MSG_KEX_HYBRID_INIT: u8 = 30;
+
+/// Message number of `SSH_MSG_KEX_HYBRID_REPLY`
+pub const SSH_MSG_KEX_HYBRID_REPLY: u8 = 31;
+
+/// Size of an X25519 public key
+const X25519_BYTES: usize = 32;
+
+/// Size of `Q_C`
+pub const Q_C_BYTES: usize = Kyber768::PK_BYTES + X25519_BYTES;
+
+/// Size of `Q_S`
+pub const Q_S_BYTES: usize = Kyber768::CT_BYTES + X25519_BYTES;
+
+/// Encode an SSH `string`: 32-bit big-endian length and data
+pub fn encode_string(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(4 + data.len());
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+
+    out
+}
+
+/// Decode an SSH `string` => (data, remaining bytes)
+pub fn decode_string(bytes: &[u8]) -> Result<(&[u8], &[u8]), String> {
+    if bytes.len() < 4 {
+        return Err("truncated string length".to_string());
+    }
+    let (len, rest) = bytes.split_at(4);
+    let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    if rest.len() < len {
+        return Err("truncated string".to_string());
+    }
+
+    Ok(rest.split_at(len))
+}
+
+/// `SSH_MSG_KEX_HYBRID_INIT`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HybridInit {
+    /// Client share `Q_C`
+    pub q_c: Vec<u8>,
+}
+
+impl HybridInit {
+    /// Encode the message payload
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [&[SSH_MSG_KEX_HYBRID_INIT][..], &encode_string(&self.q_c)].concat()
+    }
+
+    /// Decode a message payload
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        match bytes.split_first() {
+            Some((&SSH_MSG_KEX_HYBRID_INIT, rest)) => {
+                let (q_c, rest) = decode_string(rest)?;
+                if !rest.is_empty() {
+                    return Err("trailing bytes".to_string());
+                }
+                Ok(Self { q_c: q_c.to_vec() })
+            }
+            _ => Err("not a SSH_MSG_KEX_HYBRID_INIT message".to_string()),
+        }
+    }
+}
+
+/// `SSH_MSG_KEX_HYBRID_REPLY`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HybridReply {
+    /// Server host key blob `K_S`
+    pub k_s: Vec<u8>,
+    /// Server share `Q_S`
+    pub q_s: Vec<u8>,
+    /// Signature of the exchange hash
+    pub signature: Vec<u8>,
+}
+
+impl HybridReply {
+    /// Encode the message payload
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [
+            &[SSH_MSG_KEX_HYBRID_REPLY][..],
+            &encode_string(&self.k_s),
+            &encode_string(&self.q_s),
+            &encode_string(&self.signature),
+        ]
+        .concat()
+    }
+
+    /// Decode a message payload
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        match bytes.split_first() {
+            Some((&SSH_MSG_KEX_HYBRID_REPLY, rest)) => {
+                let (k_s, rest) = decode_string(rest)?;
+                let (q_s, rest) = decode_string(rest)?;
+                let (signature, rest) = decode_string(rest)?;
+                if !rest.is_empty() {
+                    return Err("trailing bytes".to_string());
+                }
+                Ok(Self {
+                    k_s: k_s.to_vec(),
+                    q_s: q_s.to_vec(),
+                    signature: signature.to_vec(),
+                })
+            }
+            _ => Err("not a SSH_MSG_KEX_HYBRID_REPLY message".to_string()),
+        }
+    }
+}
+
+/// Shared secret `K = SHA-256(K_PQ || K_CL)`
+fn combine(k_pq: &ByteArray, k_cl: &ByteArray) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(&k_pq.data);
+    hasher.update(&k_cl.data);
+
+    hasher.finalize().to_vec()
+}
+
+/// Client side of the key exchange
+pub struct ClientKex {
+    q_c: Vec<u8>,
+    sk: ByteArray,
+    x25519_sk: ByteArray,
+}
+
+impl ClientKex {
+    /// Size of the seed of `from_seed`: KEM seed and X25519 secret key
+    pub const SEED_BYTES: usize = SEED_BYTES + X25519_BYTES;
+
+    /// Start a key exchange
+    pub fn generate() -> Self {
+        // Unwraps safely since the seed has the right size
+        Self::from_seed(&ByteArray::random(Self::SEED_BYTES)).unwrap()
+    }
+
+    /// Deterministic key exchange from a seed of `SEED_BYTES` bytes
+    pub fn from_seed(seed: &ByteArray) -> Result<Self, String> {
+        if seed.data.len() != Self::SEED_BYTES {
+            return Err(format!("seed must be {} bytes", Self::SEED_BYTES));
+        }
+        let (kem_seed, x25519_sk) = seed.split_at(SEED_BYTES);
+        let (sk, pk) = kyber768kem().expand_seed(&kem_seed)?;
+        let q_c = pk.append(&X25519::public_key(&x25519_sk)).data;
+
+        Ok(Self { q_c, sk, x25519_sk })
+    }
+
+    /// `SSH_MSG_KEX_HYBRID_INIT` to send
+    pub fn init(&self) -> HybridInit {
+        HybridInit {
+            q_c: self.q_c.clone(),
+        }
+    }
+
+    /// Process the server share `Q_S` => shared secret `K`
+    pub fn complete(&self, q_s: &[u8]) -> Result<Vec<u8>, String> {
+        if q_s.len() != Q_S_BYTES {
+            return Err(format!("Q_S must be {} bytes", Q_S_BYTES));
+        }
+        let (ct, x25519) = ByteArray::from_bytes(q_s).split_at(Kyber768::CT_BYTES);
+        validate_ciphertext::<Kyber768>(&ct)?;
+
+        let k_pq = kyber768kem().decaps_checked(&ct, &self.sk)?;
+        let k_cl = X25519::dh(&self.x25519_sk, &x25519)?;
+
+        Ok(combine(&k_pq, &k_cl))
+    }
+}
+
+/// Answer the client share `Q_C` => (server share `Q_S`, shared secret `K`)
+pub fn server_kex(q_c: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+    server_kex_derand(q_c, &ByteArray::random(64))
+}
+
+/// Deterministic server answer with 64 bytes of randomness: KEM message and X25519 secret key
+pub fn server_kex_derand(q_c: &[u8], coins: &ByteArray) -> Result<(Vec<u8>, Vec<u8>), String> {
+    if q_c.len() != Q_C_BYTES {
+        return Err(format!("Q_C must be {} bytes", Q_C_BYTES));
+    }
+    if coins.data.len() != 64 {
+        return Err("randomness must be 64 bytes".to_string());
+    }
+    let (pk, x25519) = ByteArray::from_bytes(q_c).split_at(Kyber768::PK_BYTES);
+    validate_public_key::<Kyber768>(&pk)?;
+    let (m, x25519_sk) = coins.split_at(32);
+
+    let (ct, k_pq) = kyber768kem().encaps_derand(&pk, &m);
+    let k_cl = X25519::dh(&x25519_sk, &x25519)?;
+    let q_s = ct.append(&X25519::public_key(&x25519_sk)).data;
+
+    Ok((q_s, combine(&k_pq, &k_cl)))
+}
+
+/// Exchange hash `H = SHA-256(V_C || V_S || I_C || I_S || K_S || Q_C || Q_S || K)` of
+/// the `string` encodings
+#[allow(clippy::too_many_arguments)]
+pub fn exchange_hash(
+    v_c: &[u8],
+    v_s: &[u8],
+    i_c: &[u8],
+    i_s: &[u8],
+    k_s: &[u8],
+    q_c: &[u8],
+    q_s: &[u8],
+    k: &[u8],
+) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    for field in [v_c, v_s, i_c, i_s, k_s, q_c, q_s, k].iter() {
+        hasher.update(encode_string(field));
+    }
+
+    hasher.finalize().to_vec()
+}
+
+#[test]
+fn ssh_string() {
+    assert_eq!(encode_string(b"kex"), [0, 0, 0, 3, b'k', b'e', b'x']);
+    assert_eq!(encode_string(b""), [0, 0, 0, 0]);
+    let encoded = [encode_string(b"a"), vec![7]].concat();
+    assert_eq!(decode_string(&encoded).unwrap(), (&b"a"[..], &[7][..]));
+    assert!(decode_string(&[0, 0, 0]).is_err());
+    assert!(decode_string(&[0, 0, 0, 2, 1]).is_err());
+}
+
+#[test]
+fn ssh_kex() {
+    let client = ClientKex::generate();
+    let init = HybridInit::from_bytes(&client.init().to_bytes()).unwrap();
+    assert_eq!(init.q_c.len(), Q_C_BYTES);
+
+    let (q_s, k) = server_kex(&init.q_c).unwrap();
+    let reply = HybridReply {
+        k_s: b"host key".to_vec(),
+        q_s,
+        signature: b"signature".to_vec(),
+    };
+    let reply = HybridReply::from_bytes(&reply.to_bytes()).unwrap();
+    assert_eq!(reply.q_s.len(), Q_S_BYTES);
+    assert_eq!(client.complete(&reply.q_s).unwrap(), k);
+    assert_eq!(k.len(), 32);
+
+    // Malformed messages and shares
+    let mut payload = client.init().to_bytes();
+    assert!(HybridInit::from_bytes(&payload[..100]).is_err());
+    payload.push(0);
+    assert!(HybridInit::from_bytes(&payload).is_err());
+    assert!(HybridReply::from_bytes(&client.init().to_bytes()).is_err());
+    assert!(server_kex(&init.q_c[1..]).is_err());
+    assert!(client.complete(&reply.q_s[1..]).is_err());
+    let mut low_order = reply.q_s.clone();
+    for b in low_order[Kyber768::CT_BYTES..].iter_mut() {
+        *b = 0;
+    }
+    assert!(client.complete(&low_order).is_err());
+}
+
+#[test]
+fn ssh_test_vector() {
+    use crate::formats::hex;
+    use crate::hybrid::x25519::Rfc7748;
+
+    let Rfc7748 {
+        alice,
+        alice_pk,
+        bob,
+        bob_pk,
+        shared: k_cl,
+    } = Rfc7748::keys();
+
+    let seed = ByteArray::from_bytes(&(0..64).collect::<Vec<u8>>());
+    let client = ClientKex::from_seed(&seed.append(&alice)).unwrap();
+    let coins = ByteArray::from_bytes(&[0x42; 32]).append(&bob);
+    let q_c = client.init().q_c;
+    let (q_s, k) = server_kex_derand(&q_c, &coins).unwrap();
+    assert_eq!(client.complete(&q_s).unwrap(), k);
+
+    // Layouts: KEM part first, and K is the hash of the KEM and X25519 secrets
+    let (sk, pk) = kyber768kem().expand_seed(&seed).unwrap();
+    assert_eq!(q_c, pk.append(&alice_pk).data);
+    assert_eq!(q_s[Kyber768::CT_BYTES..], bob_pk.data[..]);
+    let ct = ByteArray::from_bytes(&q_s[..Kyber768::CT_BYTES]);
+    let k_pq = kyber768kem().decaps(&ct, &sk);
+    assert_eq!(k, Sha256::digest(&k_pq.append(&k_cl).data).to_vec());
+
+    let h = exchange_hash(
+        b"SSH-2.0-client",
+        b"SSH-2.0-server",
+        b"client kexinit",
+        b"server kexinit",
+        b"host key",
+        &q_c,
+        &q_s,
+        &k,
+    );
+
+    // Regression values, see `Rfc7748`
+    assert_eq!(
+        hex::encode(&Sha256::digest(&q_s)),
+        "61906d711ec0dd229c98686e0227b08f7b8001a64322634139d9a8fac5aca33d"
+    );
+    assert_eq!(
+        hex::encode(&k),
+        "b689a9a847ba2940390022320eaeaab457fd731023c887ed74f7a743047c6e8d"
+    );
+    assert_eq!(
+        hex::encode(&h),
+        "da467aeed692dc269e0297869753cba8b89f11327cce265d43542e80e6d27a9d"
+    );
+}
@@ -96,17 +96,17 @@ where
             return Self::zero();
         }
 
-        let coeffs = [T::zero(); N];
+        let mut coeffs = [T::zero(); N];
 
         for i in 0..N {
             for j in 0..N {
                 let c = self[i].mul(&other[j]);
                 let k = i + j;
                 if k < N {
-                    coeffs[k].add(&c);
+                    coeffs[k] = coeffs[k].add(&c);
                 } else {
                     // X^n = -1
-                    coeffs[k % N].sub(&c);
+                    coeffs[k % N] = coeffs[k % N].sub(&c);
                 }
             }
         }
@@ -199,8 +199,8 @@ where
 
         let mut v = [Default::default(); N];
 
-        for i in 0..degree {
-            v[i] = self.coefficients[i].mul(other)
+        for (c, p) in v.iter_mut().zip(self.coefficients.iter()).take(degree + 1) {
+            *c = p.mul(other)
         }
         Self::from_vec(v)
     }
@@ -239,3 +239,20 @@ where
         Self::init()
     }
 }
+
+#[test]
+fn polynomial_mul() {
+    use crate::structures::{Poly3329, F3329};
+
+    let monomial = |i: usize, c: usize| {
+        let mut p = Poly3329::<4>::init();
+        p.set_coeff(i, F3329::from_int(c));
+        p
+    };
+
+    // X^4 = -1
+    assert!(monomial(1, 1).mul(&monomial(3, 1)) == monomial(0, 3328));
+    assert!(monomial(2, 2).mul(&monomial(1, 3)) == monomial(3, 6));
+    assert!(Poly3329::<4>::one().mul(&monomial(3, 5)) == monomial(3, 5));
+    assert!(monomial(3, 5).mulf(&F3329::from_int(2)) == monomial(3, 10));
+}
@@ -4,6 +4,8 @@
 
 mod bytearray;
 mod primefield;
+#[cfg(feature = "serde")]
+mod serialization;
 
 pub mod algebraics;
 
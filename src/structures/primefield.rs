@@ -4,7 +4,7 @@ use std::fmt::Debug;
 
 /// Inversion table on the Finite Field F_3329
 /// INV_3329[i] = i^-1 mod 3329
-const INV_3329: [usize; 3329] = [
+static INV_3329: [usize; 3329] = [
     0, 1, 1665, 1110, 2497, 666, 555, 2378, 2913, 370, 333, 908, 1942, 3073, 1189, 222, 3121, 1175,
     185, 2453, 1831, 3012, 454, 579, 971, 799, 3201, 1233, 2259, 574, 111, 1933, 3225, 2522, 2252,
     2473, 1757, 90, 2891, 2134, 2580, 406, 3170, 2400, 227, 74, 1954, 425, 2150, 2242, 2064, 2611,
@@ -212,7 +212,7 @@ const INV_3329: [usize; 3329] = [
     3272, 1664, 3328,
 ];
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub struct PrimeField3329 {
     val: usize,
 }
@@ -259,7 +259,7 @@ impl FiniteField for PrimeField3329 {
 
     fn mul(&self, other: &Self) -> Self {
         Self {
-            val: (&self.val * &other.val) % Self::order(),
+            val: (self.val * other.val) % Self::order(),
         }
     }
 
@@ -284,12 +284,6 @@ impl PartialEq for PrimeField3329 {
 
 impl Eq for PrimeField3329 {}
 
-impl Default for PrimeField3329 {
-    fn default() -> Self {
-        Self { val: 0 }
-    }
-}
-
 impl PrimeField3329 {
     #[inline]
     pub const fn order() -> usize {
@@ -301,7 +295,7 @@ impl PrimeField3329 {
         Self { val: (x + o) % o }
     }
 
-    pub const fn to_int(&self) -> usize {
+    pub const fn to_int(self) -> usize {
         self.val
     }
 }
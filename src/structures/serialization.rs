@@ -0,0 +1,241 @@
+//! Serialization
+//!
+//! `serde` support: byte arrays are hex strings in human-readable formats and raw bytes
+//! otherwise, algebraic structures are sequences of coefficients.
+
+use crate::formats::hex;
+use crate::structures::algebraics::{FiniteField, FiniteRing, Matrix, PolyVec, Polynomial};
+use crate::structures::{ByteArray, F3329};
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use std::convert::TryInto;
+use std::fmt;
+use std::marker::PhantomData;
+
+impl Serialize for ByteArray {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(&self.data))
+        } else {
+            serializer.serialize_bytes(&self.data)
+        }
+    }
+}
+
+struct ByteArrayVisitor;
+
+impl<'de> Visitor<'de> for ByteArrayVisitor {
+    type Value = ByteArray;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a hex string or a byte array")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<ByteArray, E> {
+        let data = hex::decode(v).map_err(E::custom)?;
+        Ok(ByteArray { data })
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<ByteArray, E> {
+        Ok(ByteArray::from_bytes(v))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, data: Vec<u8>) -> Result<ByteArray, E> {
+        Ok(ByteArray { data })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ByteArray, A::Error> {
+        let mut data = Vec::new();
+        while let Some(b) = seq.next_element()? {
+            data.push(b);
+        }
+        Ok(ByteArray { data })
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteArray {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(ByteArrayVisitor)
+        } else {
+            deserializer.deserialize_bytes(ByteArrayVisitor)
+        }
+    }
+}
+
+impl Serialize for F3329 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.to_int() as u16)
+    }
+}
+
+impl<'de> Deserialize<'de> for F3329 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let x = u16::deserialize(deserializer)? as usize;
+        if x >= F3329::order() {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(x as u64),
+                &"an integer below 3329",
+            ));
+        }
+        Ok(F3329::from_int(x))
+    }
+}
+
+/// Visitor of a sequence of exactly `L` elements
+struct ArrayVisitor<T, const L: usize>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>, const L: usize> Visitor<'de> for ArrayVisitor<T, L> {
+    type Value = [T; L];
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a sequence of {} elements", L)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[T; L], A::Error> {
+        let mut items = Vec::with_capacity(L);
+        while let Some(item) = seq.next_element()? {
+            if items.len() == L {
+                return Err(de::Error::invalid_length(L + 1, &self));
+            }
+            items.push(item);
+        }
+
+        let len = items.len();
+        items
+            .try_into()
+            .map_err(|_| de::Error::invalid_length(len, &self))
+    }
+}
+
+/// Deserialize a sequence of exactly `L` elements
+fn deserialize_array<'de, D, T, const L: usize>(deserializer: D) -> Result<[T; L], D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    deserializer.deserialize_seq(ArrayVisitor::<T, L>(PhantomData))
+}
+
+impl<T, const N: usize> Serialize for Polynomial<T, N>
+where
+    T: FiniteField + Default + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.coefficients.iter())
+    }
+}
+
+impl<'de, T, const N: usize> Deserialize<'de> for Polynomial<T, N>
+where
+    T: FiniteField + Clone + Default + Copy + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::from_vec(deserialize_array(deserializer)?))
+    }
+}
+
+impl<T, const D: usize> Serialize for PolyVec<T, D>
+where
+    T: FiniteRing + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.coefficients.iter())
+    }
+}
+
+impl<'de, T, const D: usize> Deserialize<'de> for PolyVec<T, D>
+where
+    T: FiniteRing + Clone + Default + Deserialize<'de>,
+{
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        Ok(Self::from_vec(deserialize_array(deserializer)?))
+    }
+}
+
+/// Matrices are serialized as the sequence of their rows
+impl<K, const X: usize, const Y: usize> Serialize for Matrix<K, X, Y>
+where
+    K: FiniteRing + Clone + Default + Copy + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq((0..Y).map(|j| self.row(j)))
+    }
+}
+
+impl<'de, K, const X: usize, const Y: usize> Deserialize<'de> for Matrix<K, X, Y>
+where
+    K: FiniteRing + Clone + Default + Copy + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rows: [PolyVec<K, X>; Y] = deserialize_array(deserializer)?;
+
+        let mut m = Self::init();
+        for (j, row) in rows.iter().enumerate() {
+            for (i, c) in row.coefficients.iter().enumerate() {
+                m.set(j, i, *c);
+            }
+        }
+        Ok(m)
+    }
+}
+
+#[test]
+fn serde_bytearray() {
+    let bytes = ByteArray::from_bytes(&[0, 1, 0xab, 0xff]);
+
+    let json = serde_json::to_string(&bytes).unwrap();
+    assert_eq!(json, "\"0001abff\"");
+    assert_eq!(serde_json::from_str::<ByteArray>(&json).unwrap(), bytes);
+    assert!(serde_json::from_str::<ByteArray>("\"0g\"").is_err());
+
+    let mut cbor = Vec::new();
+    ciborium::ser::into_writer(&bytes, &mut cbor).unwrap();
+    // Major type 2 (byte string) of length 4
+    assert_eq!(cbor, vec![0x44, 0, 1, 0xab, 0xff]);
+    assert_eq!(
+        ciborium::de::from_reader::<ByteArray, _>(&cbor[..]).unwrap(),
+        bytes
+    );
+}
+
+#[test]
+fn serde_algebraics() {
+    use crate::structures::{Poly3329, PolyMatrix3329, PolyVec3329};
+
+    let mut p = Poly3329::<4>::init();
+    p.set_coeff(1, F3329::from_int(3328));
+    p.set_coeff(2, F3329::from_int(5));
+
+    let json = serde_json::to_string(&p).unwrap();
+    assert_eq!(json, "[0,3328,5,0]");
+    let q: Poly3329<4> = serde_json::from_str(&json).unwrap();
+    assert!(p == q);
+    assert_eq!(q.degree(), Some(2));
+
+    // Coefficients must be reduced and the length must match
+    assert!(serde_json::from_str::<Poly3329<4>>("[0,3329,5,0]").is_err());
+    assert!(serde_json::from_str::<Poly3329<4>>("[0,1,5]").is_err());
+    assert!(serde_json::from_str::<Poly3329<4>>("[0,1,5,0,0]").is_err());
+
+    let v = PolyVec3329::<4, 2>::from_vec([p, Poly3329::init()]);
+    let json = serde_json::to_string(&v).unwrap();
+    assert_eq!(json, "[[0,3328,5,0],[0,0,0,0]]");
+    let w: PolyVec3329<4, 2> = serde_json::from_str(&json).unwrap();
+    assert!(w.coefficients == v.coefficients);
+    assert!(serde_json::from_str::<PolyVec3329<4, 3>>(&json).is_err());
+
+    let mut m = PolyMatrix3329::<4, 2, 2>::init();
+    m.set(1, 0, p);
+    let mut cbor = Vec::new();
+    ciborium::ser::into_writer(&m, &mut cbor).unwrap();
+    let m2: PolyMatrix3329<4, 2, 2> = ciborium::de::from_reader(&cbor[..]).unwrap();
+    for j in 0..2 {
+        for i in 0..2 {
+            assert!(m.get(j, i) == m2.get(j, i));
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! TLS 1.3 hybrid key shares
+//!
+//! Codec of the `key_share` entries of the hybrid named groups, and computation of their
+//! shared secret. The groups follow the layouts of the drafts:
+//! - `Kyber768X25519` (`0xfe31`), layout of X25519MLKEM768: the KEM part comes first,
+//!   client share `pk || x25519`, server share `ct || x25519` and shared secret
+//!   `ss_kem || ss_x25519`
+//! - `X25519Kyber768` (`0xfe32`), layout of X25519Kyber768Draft00: the X25519 part comes
+//!   first, client share `x25519 || pk`, server share `x25519 || ct` and shared secret
+//!   `ss_x25519 || ss_kem`
+//!
+//! The KEM is the round 2 Kyber768 of this crate, which does not interoperate with ML-KEM
+//! (or round 3 Kyber) peers: the groups use private-use codepoints (RFC 8446 section
+//! 4.2.7) instead of the IANA ones, and the shares of the IANA groups are not decoded.
+
+#[cfg(feature = "rustls")]
+pub mod provider;
+
+use crate::hybrid::{DiffieHellman, X25519};
+use crate::kem::SEED_BYTES;
+use crate::kyber768kem;
+use crate::params::{Kyber768, ParameterSet};
+use crate::structures::ByteArray;
+use crate::validate::{validate_ciphertext, validate_public_key};
+
+/// Size of an X25519 share
+const X25519_BYTES: usize = 32;
+
+/// Hybrid named group
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NamedGroup {
+    /// KEM share first, as X25519MLKEM768 (draft-ietf-tls-ecdhe-mlkem)
+    Kyber768X25519,
+    /// X25519 share first, as X25519Kyber768Draft00 (draft-tls-westerbaan-xyber768d00)
+    X25519Kyber768,
+}
+
+impl NamedGroup {
+    /// Size of a client share
+    pub const CLIENT_SHARE_BYTES: usize = Kyber768::PK_BYTES + X25519_BYTES;
+    /// Size of a server share
+    pub const SERVER_SHARE_BYTES: usize = Kyber768::CT_BYTES + X25519_BYTES;
+    /// Size of the shared secret
+    pub const SHARED_SECRET_BYTES: usize = Kyber768::SS_BYTES + X25519_BYTES;
+
+    /// Group of a private-use codepoint
+    pub fn from_codepoint(codepoint: u16) -> Option<Self> {
+        match codepoint {
+            0xfe31 => Some(NamedGroup::Kyber768X25519),
+            0xfe32 => Some(NamedGroup::X25519Kyber768),
+            _ => None,
+        }
+    }
+
+    /// Codepoint of the group
+    pub fn codepoint(self) -> u16 {
+        match self {
+            NamedGroup::Kyber768X25519 => 0xfe31,
+            NamedGroup::X25519Kyber768 => 0xfe32,
+        }
+    }
+
+    /// Whether the KEM part comes first in the shares and the shared secret
+    fn kem_first(self) -> bool {
+        self == NamedGroup::Kyber768X25519
+    }
+
+    /// Concatenate the KEM and X25519 parts in the order of the group
+    fn join(self, kem: &ByteArray, dh: &ByteArray) -> ByteArray {
+        if self.kem_first() {
+            kem.append(dh)
+        } else {
+            dh.append(kem)
+        }
+    }
+
+    /// Split a share into its (KEM, X25519) parts in the order of the group
+    fn split(self, share: &[u8]) -> (ByteArray, ByteArray) {
+        let share = ByteArray::from_bytes(share);
+        if self.kem_first() {
+            share.split_at(share.data.len() - X25519_BYTES)
+        } else {
+            let (dh, kem) = share.split_at(X25519_BYTES);
+            (kem, dh)
+        }
+    }
+}
+
+/// Client `key_share`: KEM public key and X25519 share
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientShare {
+    /// Named group
+    pub group: NamedGroup,
+    /// Kyber768 public key
+    pub pk: ByteArray,
+    /// X25519 public key
+    pub x25519: ByteArray,
+}
+
+impl ClientShare {
+    /// Decode the `key_exchange` field of a client share
+    pub fn decode(group: NamedGroup, bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != NamedGroup::CLIENT_SHARE_BYTES {
+            return Err(format!(
+                "client share must be {} bytes",
+                NamedGroup::CLIENT_SHARE_BYTES
+            ));
+        }
+        let (pk, x25519) = group.split(bytes);
+        validate_public_key::<Kyber768>(&pk)?;
+
+        Ok(Self { group, pk, x25519 })
+    }
+
+    /// Encode the `key_exchange` field
+    pub fn encode(&self) -> Vec<u8> {
+        self.group.join(&self.pk, &self.x25519).data
+    }
+}
+
+/// Server `key_share`: KEM ciphertext and X25519 share
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerShare {
+    /// Named group
+    pub group: NamedGroup,
+    /// Kyber768 ciphertext
+    pub ct: ByteArray,
+    /// X25519 public key
+    pub x25519: ByteArray,
+}
+
+impl ServerShare {
+    /// Decode the `key_exchange` field of a server share
+    pub fn decode(group: NamedGroup, bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != NamedGroup::SERVER_SHARE_BYTES {
+            return Err(format!(
+                "server share must be {} bytes",
+                NamedGroup::SERVER_SHARE_BYTES
+            ));
+        }
+        let (ct, x25519) = group.split(bytes);
+        validate_ciphertext::<Kyber768>(&ct)?;
+
+        Ok(Self { group, ct, x25519 })
+    }
+
+    /// Encode the `key_exchange` field
+    pub fn encode(&self) -> Vec<u8> {
+        self.group.join(&self.ct, &self.x25519).data
+    }
+}
+
+/// Encode a `KeyShareEntry`: group codepoint and length-prefixed `key_exchange`
+pub fn encode_entry(group: NamedGroup, key_exchange: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(4 + key_exchange.len());
+    entry.extend_from_slice(&group.codepoint().to_be_bytes());
+    entry.extend_from_slice(&(key_exchange.len() as u16).to_be_bytes());
+    entry.extend_from_slice(key_exchange);
+
+    entry
+}
+
+/// Decode a `KeyShareEntry` of a hybrid group => (group, key_exchange)
+pub fn decode_entry(entry: &[u8]) -> Result<(NamedGroup, &[u8]), String> {
+    if entry.len() < 4 {
+        return Err("truncated key share entry".to_string());
+    }
+    let codepoint = u16::from_be_bytes([entry[0], entry[1]]);
+    let len = u16::from_be_bytes([entry[2], entry[3]]) as usize;
+    let group = NamedGroup::from_codepoint(codepoint)
+        .ok_or_else(|| format!("unsupported named group 0x{:04x}", codepoint))?;
+    if entry.len() != 4 + len {
+        return Err("key share entry length mismatch".to_string());
+    }
+
+    Ok((group, &entry[4..]))
+}
+
+/// Client key exchange waiting for the server share
+pub struct ClientKeyExchange {
+    share: ClientShare,
+    sk: ByteArray,
+    x25519_sk: ByteArray,
+}
+
+impl ClientKeyExchange {
+    /// Size of the seed of `from_seed`: KEM seed and X25519 secret key
+    pub const SEED_BYTES: usize = SEED_BYTES + X25519_BYTES;
+
+    /// Start a key exchange for `group`
+    pub fn generate(group: NamedGroup) -> Self {
+        // Unwraps safely since the seed has the right size
+        Self::from_seed(group, &ByteArray::random(Self::SEED_BYTES)).unwrap()
+    }
+
+    /// Deterministic key exchange from a seed of `SEED_BYTES` bytes
+    pub fn from_seed(group: NamedGroup, seed: &ByteArray) -> Result<Self, String> {
+        if seed.data.len() != Self::SEED_BYTES {
+            return Err(format!("seed must be {} bytes", Self::SEED_BYTES));
+        }
+        let (kem_seed, x25519_sk) = seed.split_at(SEED_BYTES);
+        let (sk, pk) = kyber768kem().expand_seed(&kem_seed)?;
+        let x25519 = X25519::public_key(&x25519_sk);
+
+        Ok(Self {
+            share: ClientShare { group, pk, x25519 },
+            sk,
+            x25519_sk,
+        })
+    }
+
+    /// Client share to send
+    pub fn share(&self) -> &ClientShare {
+        &self.share
+    }
+
+    /// Process the encoded server share => shared secret
+    pub fn complete(&self, server_share: &[u8]) -> Result<ByteArray, String> {
+        let group = self.share.group;
+        let share = ServerShare::decode(group, server_share)?;
+        let ss_kem = kyber768kem().decaps_checked(&share.ct, &self.sk)?;
+        let ss_dh = X25519::dh(&self.x25519_sk, &share.x25519)?;
+
+        Ok(group.join(&ss_kem, &ss_dh))
+    }
+}
+
+/// Answer an encoded client share => (server share, shared secret)
+pub fn server_respond(
+    group: NamedGroup,
+    client_share: &[u8],
+) -> Result<(ServerShare, ByteArray), String> {
+    server_respond_derand(group, client_share, &ByteArray::random(64))
+}
+
+/// Deterministic server answer with 64 bytes of randomness: KEM message and X25519 secret key
+pub fn server_respond_derand(
+    group: NamedGroup,
+    client_share: &[u8],
+    coins: &ByteArray,
+) -> Result<(ServerShare, ByteArray), String> {
+    if coins.data.len() != 64 {
+        return Err("randomness must be 64 bytes".to_string());
+    }
+    let share = ClientShare::decode(group, client_share)?;
+    let (m, x25519_sk) = coins.split_at(32);
+
+    let (ct, ss_kem) = kyber768kem().encaps_derand(&share.pk, &m);
+    let ss_dh = X25519::dh(&x25519_sk, &share.x25519)?;
+    let x25519 = X25519::public_key(&x25519_sk);
+
+    Ok((
+        ServerShare { group, ct, x25519 },
+        group.join(&ss_kem, &ss_dh),
+    ))
+}
+
+#[test]
+fn tls_key_exchange() {
+    for group in [NamedGroup::Kyber768X25519, NamedGroup::X25519Kyber768].iter() {
+        let client = ClientKeyExchange::generate(*group);
+        let entry = encode_entry(*group, &client.share().encode());
+        assert_eq!(entry.len(), 4 + NamedGroup::CLIENT_SHARE_BYTES);
+
+        let (decoded, key_exchange) = decode_entry(&entry).unwrap();
+        assert_eq!(decoded, *group);
+        let (server_share, ss) = server_respond(decoded, key_exchange).unwrap();
+        let encoded = server_share.encode();
+        assert_eq!(encoded.len(), NamedGroup::SERVER_SHARE_BYTES);
+        assert_eq!(ServerShare::decode(*group, &encoded).unwrap(), server_share);
+
+        assert_eq!(client.complete(&encoded).unwrap(), ss);
+        assert_eq!(ss.data.len(), NamedGroup::SHARED_SECRET_BYTES);
+    }
+}
+
+#[test]
+fn tls_layouts() {
+    use crate::formats::hex;
+    use crate::functions::hash::sha3_256;
+    use crate::hybrid::x25519::Rfc7748;
+
+    let Rfc7748 {
+        alice,
+        alice_pk,
+        bob,
+        bob_pk,
+        shared: ss_dh,
+    } = Rfc7748::keys();
+
+    let seed = ByteArray::from_bytes(&(0..64).collect::<Vec<u8>>()).append(&alice);
+    let coins = ByteArray::from_bytes(&[0x42; 32]).append(&bob);
+
+    let kem_first = NamedGroup::Kyber768X25519;
+    let client = ClientKeyExchange::from_seed(kem_first, &seed).unwrap();
+    let client_share = client.share().encode();
+    let (server_share, ss) = server_respond_derand(kem_first, &client_share, &coins).unwrap();
+    let server_share = server_share.encode();
+    assert_eq!(client.complete(&server_share).unwrap(), ss);
+
+    // KEM first
+    assert_eq!(client_share[Kyber768::PK_BYTES..], alice_pk.data[..]);
+    assert_eq!(server_share[Kyber768::CT_BYTES..], bob_pk.data[..]);
+    assert_eq!(ss.data[32..], ss_dh.data[..]);
+
+    // X25519 first, with the same KEM values
+    let xyber = NamedGroup::X25519Kyber768;
+    let client = ClientKeyExchange::from_seed(xyber, &seed).unwrap();
+    let xyber_share = client.share().encode();
+    let (xyber_server, xyber_ss) = server_respond_derand(xyber, &xyber_share, &coins).unwrap();
+    let xyber_server = xyber_server.encode();
+    assert_eq!(client.complete(&xyber_server).unwrap(), xyber_ss);
+
+    assert_eq!(xyber_share[..32], alice_pk.data[..]);
+    assert_eq!(xyber_share[32..], client_share[..Kyber768::PK_BYTES]);
+    assert_eq!(xyber_server[..32], bob_pk.data[..]);
+    assert_eq!(xyber_server[32..], server_share[..Kyber768::CT_BYTES]);
+    assert_eq!(xyber_ss.data[..32], ss_dh.data[..]);
+    assert_eq!(xyber_ss.data[32..], ss.data[..32]);
+
+    // Regression values, see `Rfc7748`
+    assert_eq!(
+        hex::encode(&sha3_256(&client_share)),
+        "cc90bba43d81f60aec4268f9cb81ff240fd573f25c9ccb301a495a5101badaa6"
+    );
+    assert_eq!(
+        hex::encode(&sha3_256(&server_share)),
+        "898a756a25ba0f1038135fbc9d5a48ede9bee187973b2175c94bdd64cf1bfb74"
+    );
+    assert_eq!(
+        hex::encode(&ss.data),
+        "244f5a909061d571564b0f7a6266555e0cc5d00930bae569d16ac1f17\
+         4c89f834a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742"
+    );
+}
+
+#[test]
+fn tls_malformed_shares() {
+    let group = NamedGroup::Kyber768X25519;
+    let client = ClientKeyExchange::generate(group);
+    let share = client.share().encode();
+
+    assert_eq!(NamedGroup::from_codepoint(0xfe31), Some(group));
+    assert_eq!(NamedGroup::from_codepoint(0x001d), None);
+    // The IANA groups use ML-KEM
+    assert_eq!(NamedGroup::from_codepoint(0x11ec), None);
+    assert_eq!(NamedGroup::from_codepoint(0x6399), None);
+    assert!(decode_entry(&encode_entry(group, &share)[..100]).is_err());
+    assert!(decode_entry(&[0x00, 0x1d, 0x00, 0x00]).is_err());
+    assert!(decode_entry(&[0x11]).is_err());
+
+    assert!(ClientShare::decode(group, &share[1..]).is_err());
+    assert!(server_respond(group, &share[1..]).is_err());
+    assert!(client.complete(&share).is_err());
+
+    // A low order X25519 share is rejected
+    let mut low_order = share.clone();
+    for b in low_order[Kyber768::PK_BYTES..].iter_mut() {
+        *b = 0;
+    }
+    assert!(server_respond(group, &low_order).is_err());
+}
@@ -0,0 +1,286 @@
+//! rustls provider
+//!
+//! Key exchange groups for rustls backed by the Kyber768 KEM of this crate:
+//! - `KYBER768` (`0xfe30`): the client share is the public key, the server share the
+//!   ciphertext
+//! - `KYBER768_X25519` (`0xfe31`): the `Kyber768X25519` hybrid of the parent module
+//!
+//! The KEM is round 2 Kyber, so the groups use private-use codepoints and only
+//! negotiate with peers using this provider.
+
+use crate::kyber768kem;
+use crate::structures::ByteArray;
+use crate::tls::{self, ClientKeyExchange};
+
+use rustls::crypto::{
+    ActiveKeyExchange, CompletedKeyExchange, CryptoProvider, SharedSecret, SupportedKxGroup,
+};
+use rustls::ffdhe_groups::FfdheGroup;
+use rustls::{Error, NamedGroup, PeerMisbehaved, ProtocolVersion};
+
+/// Private-use codepoint of Kyber768
+const KYBER768_CODEPOINT: u16 = 0xfe30;
+
+/// Kyber768
+pub static KYBER768: &dyn SupportedKxGroup = &Kyber768Group;
+
+/// Kyber768 + X25519, KEM share first
+pub static KYBER768_X25519: &dyn SupportedKxGroup = &Kyber768X25519Group;
+
+/// All the groups, by order of preference
+pub static ALL_KX_GROUPS: &[&dyn SupportedKxGroup] = &[KYBER768_X25519, KYBER768];
+
+/// Append the groups of this crate to the key exchange groups of `base`
+///
+/// The groups of `base` keep their priority: to prefer the groups of this crate, put
+/// `ALL_KX_GROUPS` first in the provider.
+pub fn provider(base: CryptoProvider) -> CryptoProvider {
+    let mut kx_groups = base.kx_groups;
+    kx_groups.extend(ALL_KX_GROUPS);
+
+    CryptoProvider { kx_groups, ..base }
+}
+
+fn invalid_key_share<E>(_: E) -> Error {
+    PeerMisbehaved::InvalidKeyShare.into()
+}
+
+#[derive(Debug)]
+struct Kyber768Group;
+
+impl SupportedKxGroup for Kyber768Group {
+    fn start(&self) -> Result<Box<dyn ActiveKeyExchange>, Error> {
+        let (sk, pk) = kyber768kem().keygen();
+
+        Ok(Box::new(Kyber768Active { sk, pk: pk.data }))
+    }
+
+    fn start_and_complete(&self, client_share: &[u8]) -> Result<CompletedKeyExchange, Error> {
+        let pk = ByteArray::from_bytes(client_share);
+        let (ct, ss) = kyber768kem()
+            .encaps_checked(&pk)
+            .map_err(invalid_key_share)?;
+
+        Ok(CompletedKeyExchange {
+            group: self.name(),
+            pub_key: ct.data,
+            secret: SharedSecret::from(ss.data),
+        })
+    }
+
+    fn ffdhe_group(&self) -> Option<FfdheGroup<'static>> {
+        None
+    }
+
+    fn name(&self) -> NamedGroup {
+        NamedGroup::Unknown(KYBER768_CODEPOINT)
+    }
+
+    fn usable_for_version(&self, version: ProtocolVersion) -> bool {
+        version == ProtocolVersion::TLSv1_3
+    }
+}
+
+struct Kyber768Active {
+    sk: ByteArray,
+    pk: Vec<u8>,
+}
+
+impl ActiveKeyExchange for Kyber768Active {
+    // The peer share is the ciphertext
+    fn complete(self: Box<Self>, peer_pub_key: &[u8]) -> Result<SharedSecret, Error> {
+        let ct = ByteArray::from_bytes(peer_pub_key);
+        let ss = kyber768kem()
+            .decaps_checked(&ct, &self.sk)
+            .map_err(invalid_key_share)?;
+
+        Ok(SharedSecret::from(ss.data))
+    }
+
+    fn pub_key(&self) -> &[u8] {
+        &self.pk
+    }
+
+    fn ffdhe_group(&self) -> Option<FfdheGroup<'static>> {
+        None
+    }
+
+    fn group(&self) -> NamedGroup {
+        NamedGroup::Unknown(KYBER768_CODEPOINT)
+    }
+}
+
+#[derive(Debug)]
+struct Kyber768X25519Group;
+
+impl SupportedKxGroup for Kyber768X25519Group {
+    fn start(&self) -> Result<Box<dyn ActiveKeyExchange>, Error> {
+        let kx = ClientKeyExchange::generate(tls::NamedGroup::Kyber768X25519);
+        let pk = kx.share().encode();
+
+        Ok(Box::new(Kyber768X25519Active { kx, pk }))
+    }
+
+    fn start_and_complete(&self, client_share: &[u8]) -> Result<CompletedKeyExchange, Error> {
+        let (share, ss) = tls::server_respond(tls::NamedGroup::Kyber768X25519, client_share)
+            .map_err(invalid_key_share)?;
+
+        Ok(CompletedKeyExchange {
+            group: self.name(),
+            pub_key: share.encode(),
+            secret: SharedSecret::from(ss.data),
+        })
+    }
+
+    fn ffdhe_group(&self) -> Option<FfdheGroup<'static>> {
+        None
+    }
+
+    fn name(&self) -> NamedGroup {
+        NamedGroup::Unknown(tls::NamedGroup::Kyber768X25519.codepoint())
+    }
+
+    fn usable_for_version(&self, version: ProtocolVersion) -> bool {
+        version == ProtocolVersion::TLSv1_3
+    }
+}
+
+struct Kyber768X25519Active {
+    kx: ClientKeyExchange,
+    pk: Vec<u8>,
+}
+
+impl ActiveKeyExchange for Kyber768X25519Active {
+    fn complete(self: Box<Self>, peer_pub_key: &[u8]) -> Result<SharedSecret, Error> {
+        let ss = self.kx.complete(peer_pub_key).map_err(invalid_key_share)?;
+
+        Ok(SharedSecret::from(ss.data))
+    }
+
+    fn pub_key(&self) -> &[u8] {
+        &self.pk
+    }
+
+    fn ffdhe_group(&self) -> Option<FfdheGroup<'static>> {
+        None
+    }
+
+    fn group(&self) -> NamedGroup {
+        NamedGroup::Unknown(tls::NamedGroup::Kyber768X25519.codepoint())
+    }
+}
+
+/// Move the pending TLS records of `from` to `to`
+#[cfg(test)]
+fn transfer<A, B, DA, DB>(from: &mut A, to: &mut B) -> Result<(), Error>
+where
+    A: std::ops::DerefMut<Target = rustls::ConnectionCommon<DA>>,
+    B: std::ops::DerefMut<Target = rustls::ConnectionCommon<DB>>,
+{
+    let mut stream = Vec::new();
+    while from.wants_write() {
+        from.write_tls(&mut stream).unwrap();
+    }
+    let mut reader = &stream[..];
+    while !reader.is_empty() {
+        to.read_tls(&mut reader).unwrap();
+        to.process_new_packets()?;
+    }
+
+    Ok(())
+}
+
+/// Handshake between a client and a server restricted to `group`, then exchange data
+#[cfg(test)]
+fn handshake(group: &'static dyn SupportedKxGroup) {
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName};
+    use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection};
+    use std::convert::TryFrom;
+    use std::io::{Read, Write};
+    use std::sync::Arc;
+
+    // Test PKI: a CA and a certificate for localhost, valid until 2126
+    let ca = CertificateDer::from(&include_bytes!("../../tests/data/ca.der")[..]);
+    let cert = CertificateDer::from(&include_bytes!("../../tests/data/localhost.der")[..]);
+    let key = PrivatePkcs8KeyDer::from(&include_bytes!("../../tests/data/localhost.key.der")[..]);
+
+    let provider = Arc::new(CryptoProvider {
+        kx_groups: vec![group],
+        ..rustls::crypto::ring::default_provider()
+    });
+    let mut roots = RootCertStore::empty();
+    roots.add(ca).unwrap();
+    let client_config = ClientConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let server_config = ServerConfig::builder_with_provider(provider)
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert], PrivateKeyDer::Pkcs8(key.clone_key()))
+        .unwrap();
+
+    let name = ServerName::try_from("localhost").unwrap();
+    let mut client = ClientConnection::new(Arc::new(client_config), name).unwrap();
+    let mut server = ServerConnection::new(Arc::new(server_config)).unwrap();
+
+    while client.is_handshaking() || server.is_handshaking() {
+        transfer(&mut client, &mut server).unwrap();
+        transfer(&mut server, &mut client).unwrap();
+    }
+    for conn in [
+        client.negotiated_key_exchange_group(),
+        server.negotiated_key_exchange_group(),
+    ]
+    .iter()
+    {
+        assert_eq!(conn.unwrap().name(), group.name());
+    }
+
+    client.writer().write_all(b"ping").unwrap();
+    transfer(&mut client, &mut server).unwrap();
+    let mut received = [0; 4];
+    server.reader().read_exact(&mut received).unwrap();
+    assert_eq!(&received, b"ping");
+
+    server.writer().write_all(b"pong").unwrap();
+    transfer(&mut server, &mut client).unwrap();
+    client.reader().read_exact(&mut received).unwrap();
+    assert_eq!(&received, b"pong");
+}
+
+#[test]
+fn rustls_handshake() {
+    handshake(KYBER768);
+    handshake(KYBER768_X25519);
+}
+
+#[test]
+fn rustls_invalid_shares() {
+    use crate::params::{Kyber768, ParameterSet};
+
+    let kx = KYBER768.start().unwrap();
+    assert_eq!(kx.pub_key().len(), Kyber768::PK_BYTES);
+    assert!(KYBER768.start_and_complete(&kx.pub_key()[1..]).is_err());
+    let done = KYBER768.start_and_complete(kx.pub_key()).unwrap();
+    assert!(kx.complete(&done.pub_key[1..]).is_err());
+
+    let kx = KYBER768_X25519.start().unwrap();
+    assert_eq!(kx.pub_key().len(), tls::NamedGroup::CLIENT_SHARE_BYTES);
+    let done = KYBER768_X25519.start_and_complete(kx.pub_key()).unwrap();
+    assert_eq!(done.pub_key.len(), tls::NamedGroup::SERVER_SHARE_BYTES);
+    assert_eq!(
+        kx.complete(&done.pub_key).unwrap().secret_bytes(),
+        done.secret.secret_bytes()
+    );
+
+    // The groups of this crate come last, with private-use codepoints
+    let base = rustls::crypto::ring::default_provider().kx_groups;
+    let groups = provider(rustls::crypto::ring::default_provider()).kx_groups;
+    assert_eq!(groups.len(), base.len() + 2);
+    assert_eq!(groups[0].name(), base[0].name());
+    assert_eq!(groups[base.len()].name(), NamedGroup::Unknown(0xfe31));
+    assert_eq!(groups[base.len() + 1].name(), NamedGroup::Unknown(0xfe30));
+}
@@ -0,0 +1,106 @@
+//! Validation
+//!
+//! Input checks of FIPS 203 section 7 on encapsulation keys, decapsulation keys and ciphertexts
+
+use crate::functions::utils::h;
+use crate::params::ParameterSet;
+use crate::structures::{ByteArray, F3329};
+
+fn check_len(data: &ByteArray, expected: usize, what: &str) -> Result<(), String> {
+    if data.data.len() != expected {
+        return Err(format!(
+            "{} has {} bytes, expected {}",
+            what,
+            data.data.len(),
+            expected
+        ));
+    }
+    Ok(())
+}
+
+/// Check a public key: length and modulus check
+///
+/// Every 12-bit coefficient of the encoded `t` must be reduced modulo q, which is
+/// equivalent to `ByteEncode12(ByteDecode12(t)) == t`.
+pub fn validate_public_key<P: ParameterSet>(pk: &ByteArray) -> Result<(), String> {
+    check_len(pk, P::PK_BYTES, "public key")?;
+
+    let q = F3329::order() as u16;
+    for b in pk.data[..P::POLYVEC_BYTES].chunks(3) {
+        let c0 = b[0] as u16 | ((b[1] as u16 & 0x0f) << 8);
+        let c1 = (b[1] as u16 >> 4) | ((b[2] as u16) << 4);
+        if c0 >= q || c1 >= q {
+            return Err("public key coefficient is not reduced modulo q".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Check a KEM secret key `sk' || pk || H(pk) || z`: length and hash check
+pub fn validate_secret_key<P: ParameterSet>(sk: &ByteArray) -> Result<(), String> {
+    check_len(sk, P::SK_BYTES, "secret key")?;
+
+    let (_, rem) = sk.split_at(P::PKE_SK_BYTES);
+    let (pk, rem) = rem.split_at(P::PK_BYTES);
+    let (hash, _) = rem.split_at(32);
+
+    let (h1, h2) = h(&pk);
+    if h1.append(&h2) != hash {
+        return Err("secret key hash check failed".to_string());
+    }
+
+    Ok(())
+}
+
+/// Check the length of a ciphertext
+pub fn validate_ciphertext<P: ParameterSet>(c: &ByteArray) -> Result<(), String> {
+    check_len(c, P::CT_BYTES, "ciphertext")
+}
+
+#[cfg(test)]
+use crate::Kyber512;
+
+#[test]
+fn valid_keys_pass() {
+    let (sk, pk) = crate::kyber512kem().keygen();
+    validate_public_key::<Kyber512>(&pk).unwrap();
+    validate_secret_key::<Kyber512>(&sk).unwrap();
+
+    let (c, _) = crate::kyber512kem().encaps(&pk);
+    validate_ciphertext::<Kyber512>(&c).unwrap();
+}
+
+#[test]
+fn unreduced_public_key_fails() {
+    let (_, mut pk) = crate::kyber512kem().keygen();
+
+    // First coefficient set to 4095
+    pk.data[0] = 0xff;
+    pk.data[1] |= 0x0f;
+    assert!(validate_public_key::<Kyber512>(&pk).is_err());
+
+    // Coefficient equal to q
+    pk.data[0] = (3329 & 0xff) as u8;
+    pk.data[1] = (pk.data[1] & 0xf0) | (3329 >> 8) as u8;
+    assert!(validate_public_key::<Kyber512>(&pk).is_err());
+}
+
+#[test]
+fn wrong_lengths_fail() {
+    let (sk, pk) = crate::kyber512kem().keygen();
+
+    assert!(validate_public_key::<Kyber512>(&pk.truncate(799)).is_err());
+    assert!(validate_public_key::<crate::Kyber768>(&pk).is_err());
+    assert!(validate_secret_key::<Kyber512>(&sk.truncate(100)).is_err());
+    assert!(validate_ciphertext::<Kyber512>(&ByteArray::random(735)).is_err());
+}
+
+#[test]
+fn tampered_secret_key_fails() {
+    let (mut sk, _) = crate::kyber512kem().keygen();
+
+    // Flip a bit of the embedded public key
+    sk.data[Kyber512::PKE_SK_BYTES] ^= 1;
+    assert!(validate_secret_key::<Kyber512>(&sk).is_err());
+}
@@ -0,0 +1,89 @@
+use kybe_rs::age::{Identity, Stanza};
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Run the plugin with a scripted client transcript on stdin
+fn plugin(args: &[&str], transcript: &[Stanza]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_age-plugin-kybe"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input: Vec<u8> = transcript.iter().flat_map(Stanza::to_bytes).collect();
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stanzas(output: &[u8]) -> Vec<Stanza> {
+    let mut reader = output;
+    let mut stanzas = Vec::new();
+    while !reader.is_empty() {
+        stanzas.push(Stanza::read(&mut reader).unwrap());
+    }
+    stanzas
+}
+
+#[test]
+fn age_plugin_generate_encrypt_decrypt() {
+    let out = plugin(&["--generate"], &[]);
+    assert!(out.status.success());
+    let generated = String::from_utf8(out.stdout).unwrap();
+    let identity: Identity = generated.lines().nth(1).unwrap().parse().unwrap();
+    let recipient = generated
+        .lines()
+        .next()
+        .unwrap()
+        .strip_prefix("# recipient: ")
+        .unwrap();
+    assert_eq!(identity.recipient().to_string(), recipient);
+
+    let file_key = [0x2a; 16];
+    let ok = Stanza::new("ok", &[], &[]);
+    let out = plugin(
+        &["--age-plugin=recipient-v1"],
+        &[
+            Stanza::new("add-recipient", &[recipient], &[]),
+            Stanza::new("wrap-file-key", &[], &file_key),
+            Stanza::new("done", &[], &[]),
+            ok.clone(),
+        ],
+    );
+    assert!(out.status.success());
+    let sent = stanzas(&out.stdout);
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[0].tag, "recipient-stanza");
+    assert_eq!(sent[1].tag, "done");
+
+    let out = plugin(
+        &["--age-plugin=identity-v1"],
+        &[
+            Stanza::new("add-identity", &[&identity.to_string()], &[]),
+            sent[0].clone(),
+            Stanza::new("done", &[], &[]),
+            ok,
+        ],
+    );
+    assert!(out.status.success());
+    assert_eq!(
+        stanzas(&out.stdout),
+        [
+            Stanza::new("file-key", &["0"], &file_key),
+            Stanza::new("done", &[], &[]),
+        ]
+    );
+}
+
+#[test]
+fn age_plugin_invalid_usage() {
+    assert_eq!(plugin(&[], &[]).status.code(), Some(2));
+    assert_eq!(
+        plugin(&["--age-plugin=recipient-v2"], &[]).status.code(),
+        Some(2)
+    );
+    // Truncated transcript
+    let out = plugin(&["--age-plugin=identity-v1"], &[]);
+    assert_eq!(out.status.code(), Some(1));
+}
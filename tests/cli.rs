@@ -0,0 +1,255 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Temporary directory removed at the end of the test
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("kybe-cli-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn path(&self, file: &str) -> String {
+        self.0.join(file).to_str().unwrap().to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn kybe(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_kybe"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn kem_roundtrip(level: &str, format: &str) {
+    let dir = TempDir::new(&format!("kem-{}-{}", level, format));
+    let (pk, sk, ct) = (dir.path("pk"), dir.path("sk"), dir.path("ct"));
+    let (ss1, ss2) = (dir.path("ss1"), dir.path("ss2"));
+
+    let out = kybe(&[
+        "keygen", "--level", level, "--pk", &pk, "--sk", &sk, "--format", format,
+    ]);
+    assert!(out.status.success());
+
+    let out = kybe(&[
+        "encaps", "--pk", &pk, "--ct", &ct, "--ss", &ss1, "--format", format,
+    ]);
+    assert!(out.status.success());
+
+    let out = kybe(&[
+        "decaps", "--sk", &sk, "--ct", &ct, "--ss", &ss2, "--format", format,
+    ]);
+    assert!(out.status.success());
+
+    assert_eq!(fs::read(&ss1).unwrap(), fs::read(&ss2).unwrap());
+}
+
+#[test]
+fn kem_roundtrip_all_levels_and_formats() {
+    for level in ["512", "768", "1024"].iter() {
+        for format in ["raw", "hex", "pem"].iter() {
+            kem_roundtrip(level, format);
+        }
+    }
+}
+
+#[test]
+fn pke_roundtrip() {
+    let dir = TempDir::new("pke");
+    let (pk, sk, m, c, m2) = (
+        dir.path("pk"),
+        dir.path("sk"),
+        dir.path("m"),
+        dir.path("c"),
+        dir.path("m2"),
+    );
+    fs::write(&m, [42u8; 32]).unwrap();
+
+    assert!(
+        kybe(&["keygen", "--level", "768", "--pk", &pk, "--sk", &sk])
+            .status
+            .success()
+    );
+    assert!(kybe(&["pke-encrypt", "--pk", &pk, "--in", &m, "--out", &c])
+        .status
+        .success());
+    assert!(
+        kybe(&["pke-decrypt", "--sk", &sk, "--in", &c, "--out", &m2])
+            .status
+            .success()
+    );
+
+    assert_eq!(fs::read(&m).unwrap(), fs::read(&m2).unwrap());
+}
+
+#[cfg(unix)]
+#[test]
+fn secret_files_are_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new("modes");
+    let (pk, sk, ct, ss) = (
+        dir.path("pk"),
+        dir.path("sk"),
+        dir.path("ct"),
+        dir.path("ss"),
+    );
+    let mode = |path: &str| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+    // An existing world-readable file is restricted as well
+    fs::write(&sk, b"").unwrap();
+    fs::set_permissions(&sk, fs::Permissions::from_mode(0o644)).unwrap();
+
+    assert!(
+        kybe(&["keygen", "--level", "512", "--pk", &pk, "--sk", &sk])
+            .status
+            .success()
+    );
+    assert!(kybe(&["encaps", "--pk", &pk, "--ct", &ct, "--ss", &ss])
+        .status
+        .success());
+
+    assert_eq!(mode(&sk), 0o600);
+    assert_eq!(mode(&ss), 0o600);
+}
+
+#[test]
+fn pem_keys_are_armored() {
+    let dir = TempDir::new("pem");
+    let (pk, sk) = (dir.path("pk"), dir.path("sk"));
+
+    let out = kybe(&[
+        "keygen", "--level", "512", "--pk", &pk, "--sk", &sk, "--format", "pem",
+    ]);
+    assert!(out.status.success());
+
+    let pem = fs::read_to_string(&pk).unwrap();
+    assert!(pem.starts_with("-----BEGIN KYBER PUBLIC KEY-----\n"));
+    assert!(pem.ends_with("-----END KYBER PUBLIC KEY-----\n"));
+}
+
+#[test]
+fn malformed_inputs_fail() {
+    let dir = TempDir::new("malformed");
+    let (pk, sk, ct, ss) = (
+        dir.path("pk"),
+        dir.path("sk"),
+        dir.path("ct"),
+        dir.path("ss"),
+    );
+    assert!(
+        kybe(&["keygen", "--level", "512", "--pk", &pk, "--sk", &sk])
+            .status
+            .success()
+    );
+
+    // Truncated public key
+    let bad = dir.path("bad");
+    fs::write(&bad, &fs::read(&pk).unwrap()[..100]).unwrap();
+    let out = kybe(&["encaps", "--pk", &bad, "--ct", &ct, "--ss", &ss]);
+    assert_eq!(out.status.code(), Some(1));
+
+    // Invalid hex
+    fs::write(&bad, "not hex").unwrap();
+    let out = kybe(&[
+        "encaps", "--pk", &bad, "--ct", &ct, "--ss", &ss, "--format", "hex",
+    ]);
+    assert_eq!(out.status.code(), Some(1));
+
+    // Wrong PEM label
+    let out = kybe(&[
+        "keygen", "--level", "512", "--pk", &pk, "--sk", &sk, "--format", "pem",
+    ]);
+    assert!(out.status.success());
+    let out = kybe(&[
+        "decaps", "--sk", &pk, "--ct", &ct, "--ss", &ss, "--format", "pem",
+    ]);
+    assert_eq!(out.status.code(), Some(1));
+
+    // Ciphertext of the wrong size
+    assert!(
+        kybe(&["keygen", "--level", "512", "--pk", &pk, "--sk", &sk])
+            .status
+            .success()
+    );
+    fs::write(&ct, [0u8; 10]).unwrap();
+    let out = kybe(&["decaps", "--sk", &sk, "--ct", &ct, "--ss", &ss]);
+    assert_eq!(out.status.code(), Some(1));
+
+    // Missing file
+    let out = kybe(&[
+        "decaps",
+        "--sk",
+        &dir.path("missing"),
+        "--ct",
+        &ct,
+        "--ss",
+        &ss,
+    ]);
+    assert_eq!(out.status.code(), Some(1));
+
+    // Mismatched level
+    let out = kybe(&[
+        "encaps", "--pk", &pk, "--ct", &ct, "--ss", &ss, "--level", "768",
+    ]);
+    assert_eq!(out.status.code(), Some(1));
+}
+
+#[test]
+fn usage_errors() {
+    assert_eq!(kybe(&[]).status.code(), Some(2));
+    assert_eq!(kybe(&["frobnicate"]).status.code(), Some(2));
+    assert_eq!(kybe(&["keygen", "--level", "256"]).status.code(), Some(2));
+    assert_eq!(kybe(&["keygen", "--level"]).status.code(), Some(2));
+    assert_eq!(
+        kybe(&["keygen", "--level", "512", "--pk", "a", "--sk", "b", "--format", "der"])
+            .status
+            .code(),
+        Some(2)
+    );
+}
+
+#[test]
+fn invalid_keys_fail() {
+    let dir = TempDir::new("invalid");
+    let (pk, sk, ct, ss) = (
+        dir.path("pk"),
+        dir.path("sk"),
+        dir.path("ct"),
+        dir.path("ss"),
+    );
+    assert!(
+        kybe(&["keygen", "--level", "512", "--pk", &pk, "--sk", &sk])
+            .status
+            .success()
+    );
+    assert!(kybe(&["encaps", "--pk", &pk, "--ct", &ct, "--ss", &ss])
+        .status
+        .success());
+
+    // Public key coefficient out of range
+    let mut bad = fs::read(&pk).unwrap();
+    bad[0] = 0xff;
+    bad[1] |= 0x0f;
+    let bad_pk = dir.path("bad_pk");
+    fs::write(&bad_pk, bad).unwrap();
+    let out = kybe(&["encaps", "--pk", &bad_pk, "--ct", &ct, "--ss", &ss]);
+    assert_eq!(out.status.code(), Some(1));
+
+    // Secret key with a wrong H(pk)
+    let mut bad = fs::read(&sk).unwrap();
+    bad[768 + 800] ^= 1;
+    let bad_sk = dir.path("bad_sk");
+    fs::write(&bad_sk, bad).unwrap();
+    let out = kybe(&["decaps", "--sk", &bad_sk, "--ct", &ct, "--ss", &ss]);
+    assert_eq!(out.status.code(), Some(1));
+}
@@ -0,0 +1,25 @@
+use kybe_rs::{Poly3329, PolyMatrix3329, PolyVec3329, F3329};
+
+#[test]
+fn algebraic_structures_from_outside() {
+    let mut coefficients = [F3329::from_int(0); 4];
+    coefficients[1] = F3329::from_int(3328);
+    let p = Poly3329::<4>::from_vec(coefficients);
+    assert_eq!(p.degree(), Some(1));
+    assert_eq!(p[1].to_int(), 3328);
+
+    let json = serde_json::to_string(&p).unwrap();
+    assert_eq!(json, "[0,3328,0,0]");
+    let q: Poly3329<4> = serde_json::from_str(&json).unwrap();
+    assert!(p == q);
+
+    let v = PolyVec3329::<4, 2>::from_vec([p, Poly3329::init()]);
+    let w: PolyVec3329<4, 2> = serde_json::from_str(&serde_json::to_string(&v).unwrap()).unwrap();
+    assert!(w.coefficients == v.coefficients);
+
+    let mut m = PolyMatrix3329::<4, 2, 2>::init();
+    m.set(0, 1, p);
+    let m2: PolyMatrix3329<4, 2, 2> =
+        serde_json::from_str(&serde_json::to_string(&m).unwrap()).unwrap();
+    assert!(m2.row(0).coefficients[1] == p);
+}